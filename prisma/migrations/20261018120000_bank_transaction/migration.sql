@@ -0,0 +1,17 @@
+-- CreateTable
+CREATE TABLE "bank_transaction" (
+    "id" SERIAL NOT NULL,
+    "user_id" BIGINT NOT NULL,
+    "counterparty_id" BIGINT,
+    "amount" BIGINT NOT NULL,
+    "reason" VARCHAR(45) NOT NULL,
+    "created_at" TIMESTAMPTZ(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    CONSTRAINT "bank_transaction_pkey" PRIMARY KEY ("id")
+);
+
+-- CreateIndex
+CREATE INDEX "bank_transaction_user_id_created_at_idx" ON "bank_transaction"("user_id", "created_at");
+
+-- AddForeignKey
+ALTER TABLE "bank_transaction" ADD CONSTRAINT "bank_transaction_user_id_fkey" FOREIGN KEY ("user_id") REFERENCES "user"("id") ON DELETE CASCADE ON UPDATE CASCADE;
@@ -0,0 +1,46 @@
+-- AlterTable
+ALTER TABLE "bank_account" ADD COLUMN "guild_id" BIGINT;
+
+-- AlterTable
+ALTER TABLE "bank_transaction" ADD COLUMN "guild_id" BIGINT;
+
+-- Move existing balances and history to the guild where the bot was used before.
+-- Nothing records which guild that was, so it is only assumed when the bot has been in one guild.
+-- Otherwise, it must be given with `ALTER DATABASE <database> SET wallace.origin_guild = '<guild id>'`.
+DO $$
+DECLARE
+  origin BIGINT := NULLIF(current_setting('wallace.origin_guild', true), '')::BIGINT;
+BEGIN
+  IF origin IS NULL AND (SELECT COUNT(*) FROM "guild") = 1 THEN
+    origin := (SELECT "id" FROM "guild");
+  END IF;
+  IF origin IS NULL THEN
+    IF EXISTS (SELECT 1 FROM "bank_account") OR EXISTS (SELECT 1 FROM "bank_transaction") THEN
+      RAISE EXCEPTION 'Unknown guild for existing bank accounts. Set wallace.origin_guild to the guild id and migrate again.';
+    END IF;
+    RETURN;
+  END IF;
+  INSERT INTO "guild" ("id") VALUES (origin) ON CONFLICT DO NOTHING;
+  UPDATE "bank_account" SET "guild_id" = origin;
+  UPDATE "bank_transaction" SET "guild_id" = origin;
+END $$;
+
+-- AlterTable
+ALTER TABLE "bank_account" DROP CONSTRAINT "bank_account_pkey",
+ALTER COLUMN "guild_id" SET NOT NULL,
+ADD CONSTRAINT "bank_account_pkey" PRIMARY KEY ("guild_id", "user_id");
+
+-- AlterTable
+ALTER TABLE "bank_transaction" ALTER COLUMN "guild_id" SET NOT NULL;
+
+-- DropIndex
+DROP INDEX "bank_transaction_user_id_created_at_idx";
+
+-- CreateIndex
+CREATE INDEX "bank_transaction_guild_id_user_id_created_at_idx" ON "bank_transaction"("guild_id", "user_id", "created_at");
+
+-- AddForeignKey
+ALTER TABLE "bank_account" ADD CONSTRAINT "bank_account_guild_id_fkey" FOREIGN KEY ("guild_id") REFERENCES "guild"("id") ON DELETE CASCADE ON UPDATE CASCADE;
+
+-- AddForeignKey
+ALTER TABLE "bank_transaction" ADD CONSTRAINT "bank_transaction_guild_id_fkey" FOREIGN KEY ("guild_id") REFERENCES "guild"("id") ON DELETE CASCADE ON UPDATE CASCADE;
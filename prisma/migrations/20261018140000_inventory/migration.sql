@@ -0,0 +1,25 @@
+-- CreateTable
+CREATE TABLE "inventory_item" (
+    "guild_id" BIGINT NOT NULL,
+    "user_id" BIGINT NOT NULL,
+    "item" VARCHAR(45) NOT NULL,
+    "amount" INTEGER NOT NULL DEFAULT 0,
+
+    CONSTRAINT "inventory_item_pkey" PRIMARY KEY ("guild_id","user_id","item")
+);
+
+-- CreateTable
+CREATE TABLE "active_buff" (
+    "guild_id" BIGINT NOT NULL,
+    "user_id" BIGINT NOT NULL,
+    "buff" VARCHAR(45) NOT NULL,
+    "expires_at" TIMESTAMPTZ(3) NOT NULL,
+
+    CONSTRAINT "active_buff_pkey" PRIMARY KEY ("guild_id","user_id","buff")
+);
+
+-- AddForeignKey
+ALTER TABLE "inventory_item" ADD CONSTRAINT "inventory_item_guild_id_user_id_fkey" FOREIGN KEY ("guild_id", "user_id") REFERENCES "bank_account"("guild_id", "user_id") ON DELETE CASCADE ON UPDATE CASCADE;
+
+-- AddForeignKey
+ALTER TABLE "active_buff" ADD CONSTRAINT "active_buff_guild_id_user_id_fkey" FOREIGN KEY ("guild_id", "user_id") REFERENCES "bank_account"("guild_id", "user_id") ON DELETE CASCADE ON UPDATE CASCADE;
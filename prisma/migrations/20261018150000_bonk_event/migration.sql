@@ -0,0 +1,22 @@
+-- CreateTable
+CREATE TABLE "bonk_event" (
+    "id" SERIAL NOT NULL,
+    "guild_id" BIGINT NOT NULL,
+    "caster_id" BIGINT NOT NULL,
+    "target_id" BIGINT NOT NULL,
+    "victim_id" BIGINT,
+    "size" VARCHAR(3),
+    "chance" INTEGER,
+    "outcome" VARCHAR(45) NOT NULL,
+    "duration" INTEGER NOT NULL,
+    "cost" BIGINT NOT NULL,
+    "created_at" TIMESTAMPTZ(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    CONSTRAINT "bonk_event_pkey" PRIMARY KEY ("id")
+);
+
+-- CreateIndex
+CREATE INDEX "bonk_event_guild_id_idx" ON "bonk_event"("guild_id");
+
+-- AddForeignKey
+ALTER TABLE "bonk_event" ADD CONSTRAINT "bonk_event_guild_id_fkey" FOREIGN KEY ("guild_id") REFERENCES "guild"("id") ON DELETE CASCADE ON UPDATE CASCADE;
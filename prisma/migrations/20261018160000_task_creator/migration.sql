@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "task" ADD COLUMN     "creator_id" BIGINT;
@@ -0,0 +1,13 @@
+-- CreateTable
+CREATE TABLE "guild_settings" (
+    "guild_id" BIGINT NOT NULL,
+    "timezone" VARCHAR(64),
+
+    CONSTRAINT "guild_settings_pkey" PRIMARY KEY ("guild_id")
+);
+
+-- AlterTable
+ALTER TABLE "task" ADD COLUMN     "timezone" VARCHAR(64);
+
+-- AddForeignKey
+ALTER TABLE "guild_settings" ADD CONSTRAINT "guild_settings_guild_id_fkey" FOREIGN KEY ("guild_id") REFERENCES "guild"("id") ON DELETE CASCADE ON UPDATE CASCADE;
@@ -0,0 +1,14 @@
+-- AlterTable
+ALTER TABLE "task" ADD COLUMN     "catch_up" VARCHAR(10) NOT NULL DEFAULT 'once',
+ADD COLUMN     "last_run" TIMESTAMPTZ(3) NOT NULL DEFAULT CURRENT_TIMESTAMP;
+
+-- CreateTable
+CREATE TABLE "weekly_payout" (
+    "week" DATE NOT NULL,
+    "created_at" TIMESTAMPTZ(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    CONSTRAINT "weekly_payout_pkey" PRIMARY KEY ("week")
+);
+
+-- The current week was paid before payouts were recorded, so it is not paid again
+INSERT INTO "weekly_payout" ("week") VALUES ((date_trunc('week', CURRENT_TIMESTAMP AT TIME ZONE 'UTC' - INTERVAL '8 hours'))::date);
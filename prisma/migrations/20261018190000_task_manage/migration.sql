@@ -0,0 +1,6 @@
+-- AlterTable
+ALTER TABLE "task" ADD COLUMN     "created_at" TIMESTAMPTZ(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+ADD COLUMN     "description" VARCHAR(255),
+ADD COLUMN     "enabled" BOOLEAN NOT NULL DEFAULT true,
+ADD COLUMN     "last_result" VARCHAR(255),
+ADD COLUMN     "last_result_at" TIMESTAMPTZ(3);
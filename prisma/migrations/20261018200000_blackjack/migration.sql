@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "guild_settings" ADD COLUMN     "blackjack_decks" SMALLINT NOT NULL DEFAULT 6;
@@ -0,0 +1,16 @@
+-- AlterTable
+ALTER TABLE "guild_settings" ADD COLUMN     "jackpot_share" SMALLINT NOT NULL DEFAULT 10;
+
+-- CreateTable
+CREATE TABLE "jackpot" (
+    "guild_id" BIGINT NOT NULL,
+    "pot" BIGINT NOT NULL DEFAULT 0,
+    "winner_id" BIGINT,
+    "won" BIGINT,
+    "won_at" TIMESTAMPTZ(3),
+
+    CONSTRAINT "jackpot_pkey" PRIMARY KEY ("guild_id")
+);
+
+-- AddForeignKey
+ALTER TABLE "jackpot" ADD CONSTRAINT "jackpot_guild_id_fkey" FOREIGN KEY ("guild_id") REFERENCES "guild"("id") ON DELETE CASCADE ON UPDATE CASCADE;
@@ -0,0 +1,33 @@
+-- CreateTable
+CREATE TABLE "fair_seed" (
+    "user_id" BIGINT NOT NULL,
+    "server_seed" CHAR(64) NOT NULL,
+    "client_seed" VARCHAR(64) NOT NULL,
+    "nonce" INTEGER NOT NULL DEFAULT 0,
+    "created_at" TIMESTAMPTZ(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    CONSTRAINT "fair_seed_pkey" PRIMARY KEY ("user_id")
+);
+
+-- CreateTable
+CREATE TABLE "fair_spin" (
+    "id" SERIAL NOT NULL,
+    "user_id" BIGINT NOT NULL,
+    "game" VARCHAR(45) NOT NULL,
+    "server_seed" CHAR(64) NOT NULL,
+    "client_seed" VARCHAR(64) NOT NULL,
+    "nonce" INTEGER NOT NULL,
+    "outcome" VARCHAR(255),
+    "created_at" TIMESTAMPTZ(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    CONSTRAINT "fair_spin_pkey" PRIMARY KEY ("id")
+);
+
+-- CreateIndex
+CREATE INDEX "fair_spin_user_id_created_at_idx" ON "fair_spin"("user_id", "created_at");
+
+-- AddForeignKey
+ALTER TABLE "fair_seed" ADD CONSTRAINT "fair_seed_user_id_fkey" FOREIGN KEY ("user_id") REFERENCES "user"("id") ON DELETE CASCADE ON UPDATE CASCADE;
+
+-- AddForeignKey
+ALTER TABLE "fair_spin" ADD CONSTRAINT "fair_spin_user_id_fkey" FOREIGN KEY ("user_id") REFERENCES "user"("id") ON DELETE CASCADE ON UPDATE CASCADE;
@@ -0,0 +1,20 @@
+-- AlterTable
+ALTER TABLE "guild" ADD COLUMN     "slot_table_id" INTEGER;
+
+-- CreateTable
+CREATE TABLE "slot_table" (
+    "id" SERIAL NOT NULL,
+    "guild_id" BIGINT NOT NULL,
+    "rules" TEXT NOT NULL,
+    "rtp" DOUBLE PRECISION NOT NULL,
+    "creator_id" BIGINT NOT NULL,
+    "created_at" TIMESTAMPTZ(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    CONSTRAINT "slot_table_pkey" PRIMARY KEY ("id")
+);
+
+-- AddForeignKey
+ALTER TABLE "guild" ADD CONSTRAINT "guild_slot_table_id_fkey" FOREIGN KEY ("slot_table_id") REFERENCES "slot_table"("id") ON DELETE SET NULL ON UPDATE CASCADE;
+
+-- AddForeignKey
+ALTER TABLE "slot_table" ADD CONSTRAINT "slot_table_guild_id_fkey" FOREIGN KEY ("guild_id") REFERENCES "guild"("id") ON DELETE CASCADE ON UPDATE CASCADE;
@@ -0,0 +1,37 @@
+-- CreateTable
+CREATE TABLE "bet_pool" (
+    "id" SERIAL NOT NULL,
+    "guild_id" BIGINT NOT NULL,
+    "creator_id" BIGINT NOT NULL,
+    "question" VARCHAR(255) NOT NULL,
+    "options" VARCHAR(100)[],
+    "closes_at" TIMESTAMPTZ(3) NOT NULL,
+    "winner" SMALLINT,
+    "resolved_at" TIMESTAMPTZ(3),
+    "created_at" TIMESTAMPTZ(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    CONSTRAINT "bet_pool_pkey" PRIMARY KEY ("id")
+);
+
+-- CreateTable
+CREATE TABLE "bet_wager" (
+    "pool_id" INTEGER NOT NULL,
+    "guild_id" BIGINT NOT NULL,
+    "user_id" BIGINT NOT NULL,
+    "option" SMALLINT NOT NULL,
+    "amount" BIGINT NOT NULL,
+
+    CONSTRAINT "bet_wager_pkey" PRIMARY KEY ("pool_id","user_id")
+);
+
+-- CreateIndex
+CREATE INDEX "bet_pool_guild_id_resolved_at_idx" ON "bet_pool"("guild_id", "resolved_at");
+
+-- AddForeignKey
+ALTER TABLE "bet_pool" ADD CONSTRAINT "bet_pool_guild_id_fkey" FOREIGN KEY ("guild_id") REFERENCES "guild"("id") ON DELETE CASCADE ON UPDATE CASCADE;
+
+-- AddForeignKey
+ALTER TABLE "bet_wager" ADD CONSTRAINT "bet_wager_pool_id_fkey" FOREIGN KEY ("pool_id") REFERENCES "bet_pool"("id") ON DELETE CASCADE ON UPDATE CASCADE;
+
+-- AddForeignKey
+ALTER TABLE "bet_wager" ADD CONSTRAINT "bet_wager_guild_id_user_id_fkey" FOREIGN KEY ("guild_id", "user_id") REFERENCES "bank_account"("guild_id", "user_id") ON DELETE CASCADE ON UPDATE CASCADE;
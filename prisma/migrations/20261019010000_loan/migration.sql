@@ -0,0 +1,21 @@
+-- CreateTable
+CREATE TABLE "loan" (
+    "guild_id" BIGINT NOT NULL,
+    "user_id" BIGINT NOT NULL,
+    "debt" BIGINT NOT NULL DEFAULT 0,
+    "due_at" TIMESTAMPTZ(3) NOT NULL,
+    "defaulted" BOOLEAN NOT NULL DEFAULT false,
+
+    CONSTRAINT "loan_pkey" PRIMARY KEY ("guild_id","user_id")
+);
+
+-- CreateTable
+CREATE TABLE "loan_interest" (
+    "day" DATE NOT NULL,
+    "created_at" TIMESTAMPTZ(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    CONSTRAINT "loan_interest_pkey" PRIMARY KEY ("day")
+);
+
+-- AddForeignKey
+ALTER TABLE "loan" ADD CONSTRAINT "loan_guild_id_user_id_fkey" FOREIGN KEY ("guild_id", "user_id") REFERENCES "bank_account"("guild_id", "user_id") ON DELETE CASCADE ON UPDATE CASCADE;
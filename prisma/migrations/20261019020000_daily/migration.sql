@@ -0,0 +1,8 @@
+-- AlterTable
+ALTER TABLE "bank_account" ADD COLUMN     "daily_claimed_at" TIMESTAMPTZ(3),
+ADD COLUMN     "daily_streak" INTEGER NOT NULL DEFAULT 0;
+
+-- AlterTable
+ALTER TABLE "guild_settings" ADD COLUMN     "daily_base" SMALLINT NOT NULL DEFAULT 1,
+ADD COLUMN     "daily_bonus" SMALLINT NOT NULL DEFAULT 1,
+ADD COLUMN     "daily_max_streak" SMALLINT NOT NULL DEFAULT 7;
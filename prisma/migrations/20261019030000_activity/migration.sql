@@ -0,0 +1,3 @@
+-- AlterTable
+ALTER TABLE "guild_settings" ADD COLUMN     "activity_message_reward" SMALLINT NOT NULL DEFAULT 10,
+ADD COLUMN     "activity_voice_reward" SMALLINT NOT NULL DEFAULT 5;
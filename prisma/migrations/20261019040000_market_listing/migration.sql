@@ -0,0 +1,18 @@
+-- CreateTable
+CREATE TABLE "market_listing" (
+    "id" SERIAL NOT NULL,
+    "guild_id" BIGINT NOT NULL,
+    "seller_id" BIGINT NOT NULL,
+    "item" VARCHAR(45) NOT NULL,
+    "amount" INTEGER NOT NULL,
+    "price" BIGINT NOT NULL,
+    "created_at" TIMESTAMPTZ(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    CONSTRAINT "market_listing_pkey" PRIMARY KEY ("id")
+);
+
+-- CreateIndex
+CREATE INDEX "market_listing_guild_id_idx" ON "market_listing"("guild_id");
+
+-- AddForeignKey
+ALTER TABLE "market_listing" ADD CONSTRAINT "market_listing_guild_id_seller_id_fkey" FOREIGN KEY ("guild_id", "seller_id") REFERENCES "bank_account"("guild_id", "user_id") ON DELETE CASCADE ON UPDATE CASCADE;
@@ -0,0 +1,7 @@
+-- AlterTable
+ALTER TABLE "guild_settings" ADD COLUMN     "unbonk_cost" BIGINT NOT NULL DEFAULT 2,
+ADD COLUMN     "nickname_cost" BIGINT NOT NULL DEFAULT 1,
+ADD COLUMN     "servername_cost" BIGINT NOT NULL DEFAULT 3,
+ADD COLUMN     "gamba_min_bet" BIGINT NOT NULL DEFAULT 1,
+ADD COLUMN     "max_gamba_size" VARCHAR(5) NOT NULL DEFAULT 'XXL',
+ADD COLUMN     "disabled_spells" VARCHAR(45)[];
@@ -0,0 +1,10 @@
+-- AlterTable
+ALTER TABLE "guild_settings" ADD COLUMN     "prefix" VARCHAR(5) NOT NULL DEFAULT '!',
+ADD COLUMN     "disabled_groups" VARCHAR(45)[],
+ADD COLUMN     "react_to_name" BOOLEAN NOT NULL DEFAULT true,
+ADD COLUMN     "ai_persona" VARCHAR(1000),
+ADD COLUMN     "tts_voice" VARCHAR(16) NOT NULL DEFAULT 'onyx',
+ADD COLUMN     "weekly_payout" BIGINT NOT NULL DEFAULT 15,
+ADD COLUMN     "credit_limit" BIGINT NOT NULL DEFAULT 50,
+ADD COLUMN     "log_channel_id" BIGINT,
+ADD COLUMN     "locale" VARCHAR(8) NOT NULL DEFAULT 'en';
@@ -0,0 +1,14 @@
+-- CreateTable
+CREATE TABLE "held_bet" (
+    "id" SERIAL NOT NULL,
+    "guild_id" BIGINT NOT NULL,
+    "user_id" BIGINT NOT NULL,
+    "amount" BIGINT NOT NULL,
+    "reason" VARCHAR(45) NOT NULL,
+    "created_at" TIMESTAMPTZ(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    CONSTRAINT "held_bet_pkey" PRIMARY KEY ("id")
+);
+
+-- AddForeignKey
+ALTER TABLE "held_bet" ADD CONSTRAINT "held_bet_guild_id_user_id_fkey" FOREIGN KEY ("guild_id", "user_id") REFERENCES "bank_account"("guild_id", "user_id") ON DELETE CASCADE ON UPDATE CASCADE;
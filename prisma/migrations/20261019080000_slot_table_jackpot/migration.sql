@@ -0,0 +1,2 @@
+-- Slot tables used to win the jackpot on three crowns
+UPDATE "slot_table" SET "rules" = jsonb_set("rules"::jsonb, '{jackpot}', '"crown"')::text WHERE "rules"::jsonb -> 'reel' @> '[{"item": "crown"}]';
@@ -0,0 +1,309 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+use chrono::{DateTime, Duration, Utc};
+use serenity::{
+    builder::{CreateEmbed, CreateMessage},
+    client::Context,
+    framework::standard::{macros::command, Args, CommandResult},
+    model::{
+        prelude::{GuildId, Message},
+        voice::VoiceState,
+    },
+};
+use tracing::warn;
+
+use super::config::{set_setting, update_guild_settings};
+use crate::{
+    database::WallaceDBClient,
+    discord::{get_activity_tracker, get_db_handler, get_guild_settings, PREFIX},
+    model::ActivityRates,
+};
+
+/// Minimum time between messages that earn.
+const MESSAGE_COOLDOWN: Duration = Duration::seconds(60);
+/// Shorter messages don't earn.
+const MIN_MESSAGE_LENGTH: usize = 10;
+
+#[derive(Default)]
+struct Activity {
+    /// When the last message that earned was sent.
+    last_earned: Option<DateTime<Utc>>,
+    /// Hash of the last message, so that repeating it doesn't earn.
+    last_message: u64,
+    messages: i64,
+    /// When the user joined a voice channel that earns.
+    in_voice_since: Option<DateTime<Utc>>,
+    voice_secs: i64,
+    /// Earnings that are not a whole Kapsyl yet, in hundredths.
+    hundredths: i64,
+}
+
+/// Keeps track of chatting and voice time, until the earnings are credited in a batch.
+#[derive(Default)]
+pub struct ActivityTracker {
+    users: HashMap<(u64, u64), Activity>,
+}
+
+impl ActivityTracker {
+    /// Count a message, if it is long enough, not a repeat, and not sent too soon after the last one.
+    /// Returns whether it earns.
+    pub fn message(&mut self, gid: u64, uid: u64, content: &str, now: DateTime<Utc>) -> bool {
+        let text = content.trim().to_lowercase();
+        let mut h = DefaultHasher::new();
+        text.hash(&mut h);
+        let hash = h.finish();
+        let a = self.users.entry((gid, uid)).or_default();
+        let repeated = a.last_message == hash;
+        a.last_message = hash;
+        if repeated
+            || text.starts_with(PREFIX)
+            || text.chars().count() < MIN_MESSAGE_LENGTH
+            || a.last_earned.is_some_and(|t| now < t + MESSAGE_COOLDOWN)
+        {
+            return false;
+        }
+        a.last_earned = Some(now);
+        a.messages += 1;
+        true
+    }
+
+    /// Start or stop counting the voice time of a user.
+    pub fn voice(&mut self, gid: u64, uid: u64, earning: bool, now: DateTime<Utc>) {
+        let a = self.users.entry((gid, uid)).or_default();
+        if let Some(t) = a.in_voice_since.take() {
+            a.voice_secs += (now - t).num_seconds().max(0);
+        }
+        if earning {
+            a.in_voice_since = Some(now);
+        }
+    }
+
+    /// The guilds that have activity.
+    pub fn guilds(&self) -> Vec<u64> {
+        let mut guilds: Vec<u64> = self.users.keys().map(|(gid, _)| *gid).collect();
+        guilds.sort_unstable();
+        guilds.dedup();
+        guilds
+    }
+
+    /// Take the earnings in whole Kapsyler, as `(guild, user, amount)`.
+    /// Fractions are kept for the next batch.
+    pub fn take_earnings(
+        &mut self,
+        now: DateTime<Utc>,
+        rates: &HashMap<u64, ActivityRates>,
+    ) -> Vec<(u64, u64, i64)> {
+        let mut earnings = vec![];
+        for (&(gid, uid), a) in self.users.iter_mut() {
+            if let Some(t) = a.in_voice_since {
+                a.voice_secs += (now - t).num_seconds().max(0);
+                a.in_voice_since = Some(now);
+            }
+            let r = rates.get(&gid).copied().unwrap_or_default();
+            a.hundredths += a.messages * r.message as i64 + a.voice_secs / 60 * r.voice as i64;
+            a.messages = 0;
+            a.voice_secs %= 60;
+            if a.hundredths >= 100 {
+                earnings.push((gid, uid, a.hundredths / 100));
+                a.hundredths %= 100;
+            }
+        }
+        // Forget users that have gone quiet
+        self.users.retain(|_, a| {
+            a.in_voice_since.is_some()
+                || a.hundredths > 0
+                || a.voice_secs > 0
+                || a.last_earned.is_some_and(|t| now < t + MESSAGE_COOLDOWN)
+        });
+        earnings.sort_unstable();
+        earnings
+    }
+
+    /// Put back earnings that could not be credited.
+    pub fn restore(&mut self, earnings: Vec<(u64, u64, i64)>) {
+        for (gid, uid, amount) in earnings {
+            self.users.entry((gid, uid)).or_default().hundredths += amount * 100;
+        }
+    }
+}
+
+fn is_bot(state: &VoiceState) -> bool {
+    state.member.as_ref().is_some_and(|m| m.user.bot)
+}
+
+/// If time in this voice state earns: in a voice channel that is not the AFK channel, not deafened,
+/// and with at least one other user that is not a bot.
+pub fn voice_earns(ctx: &Context, state: &VoiceState) -> bool {
+    let (Some(gid), Some(cid)) = (state.guild_id, state.channel_id) else {
+        return false;
+    };
+    let Some(guild) = ctx.cache.guild(gid) else {
+        return false;
+    };
+    let afk = guild.afk_metadata.as_ref().map(|a| a.afk_channel_id);
+    let users = guild
+        .voice_states
+        .values()
+        .filter(|s| s.channel_id == Some(cid) && !is_bot(s))
+        .count();
+    afk != Some(cid) && !state.deaf && !state.self_deaf && users >= 2
+}
+
+/// The users in the voice channels of a guild, and if their time earns.
+pub fn guild_voice_earnings(ctx: &Context, gid: GuildId) -> Vec<(u64, bool)> {
+    let states: Vec<VoiceState> = ctx
+        .cache
+        .guild(gid)
+        .map(|g| {
+            g.voice_states
+                .values()
+                .filter(|s| !is_bot(s))
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default();
+    states
+        .iter()
+        .map(|s| (s.user_id.get(), voice_earns(ctx, s)))
+        .collect()
+}
+
+/// Credit the earnings from activity to bank accounts.
+pub async fn credit_activity(ctx: &Context) -> anyhow::Result<()> {
+    let tracker = get_activity_tracker(ctx).await;
+    let guilds = tracker.lock().await.guilds();
+    let mut rates = HashMap::new();
+    for gid in guilds {
+        rates.insert(gid, get_guild_settings(ctx, gid).await.activity);
+    }
+    let earnings = tracker.lock().await.take_earnings(Utc::now(), &rates);
+    if earnings.is_empty() {
+        return Ok(());
+    }
+    let db = get_db_handler(ctx).await;
+    if let Err(e) = db.add_activity_earnings(&earnings).await {
+        warn!("Failed to credit activity: {e}");
+        tracker.lock().await.restore(earnings);
+    }
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[sub_commands(rates)]
+#[description(
+    "Show how much chatting and voice time earn in this server.
+    Messages earn at most once a minute, and short or repeated messages don't count. Voice time alone, in the AFK channel or while deafened doesn't count."
+)]
+async fn activity(ctx: &Context, msg: &Message) -> CommandResult {
+    let r = get_guild_settings(ctx, msg.guild_id.unwrap().get())
+        .await
+        .activity;
+    msg.channel_id
+        .send_message(
+            ctx,
+            CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .title("Activity earnings")
+                    .field(
+                        "Per message",
+                        format!("{:.2} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻", r.message as f64 / 100.0),
+                        true,
+                    )
+                    .field(
+                        "Per minute in voice",
+                        format!("{:.2} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻", r.voice as f64 / 100.0),
+                        true,
+                    ),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[num_args(2)]
+#[required_permissions("ADMINISTRATOR")]
+#[description(
+    "Set how many hundredths of a 𝓚𝓪𝓹𝓼𝔂𝓵 are earned per message and per minute in voice in this server."
+)]
+#[usage("<message> <voice>")]
+#[example("10 5")]
+async fn rates(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let (message, voice) = (
+        args.single::<String>().unwrap(),
+        args.single::<String>().unwrap(),
+    );
+    update_guild_settings(
+        ctx,
+        msg,
+        |s| {
+            set_setting(s, "activity_message_reward", &message)?;
+            set_setting(s, "activity_voice_reward", &voice)
+        },
+        |s| {
+            format!(
+                "set the activity rates to {} {}",
+                s.activity.message, s.activity.voice
+            )
+        },
+    )
+    .await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages() {
+        let mut t = ActivityTracker::default();
+        let now = Utc::now();
+        let s = Duration::seconds;
+        assert!(t.message(1, 1, "hello there everyone", now));
+        // Cooldown
+        assert!(!t.message(1, 1, "what is going on here", now + s(30)));
+        // Too short, a command, or a repeat
+        assert!(!t.message(1, 1, "hi", now + s(90)));
+        assert!(!t.message(1, 1, "!slots please give", now + s(90)));
+        assert!(!t.message(1, 1, "!slots please give", now + s(95)));
+        assert!(t.message(1, 1, "what is going on here", now + s(90)));
+        assert!(!t.message(1, 1, "WHAT is going on here ", now + s(200)));
+        // Other users and guilds have their own cooldowns
+        assert!(t.message(1, 2, "hello there everyone", now));
+        assert!(t.message(2, 1, "hello there everyone", now));
+        assert_eq!(t.guilds(), [1, 2]);
+    }
+
+    #[test]
+    fn earnings() {
+        let mut t = ActivityTracker::default();
+        let now = Utc::now();
+        let m = Duration::minutes;
+        let rates = HashMap::from([(
+            1,
+            ActivityRates {
+                message: 40,
+                voice: 5,
+            },
+        )]);
+        for i in 0..4 {
+            t.message(1, 1, &format!("message number {i}"), now + m(i));
+        }
+        t.voice(1, 2, true, now);
+        t.voice(1, 2, false, now + m(10));
+        t.voice(1, 2, true, now + m(20));
+        // 4 messages make 1.6, and 15 minutes of voice make 0.75
+        assert_eq!(t.take_earnings(now + m(25), &rates), [(1, 1, 1)]);
+        // The fractions are kept, and voice time still counts
+        t.message(1, 1, "one more message", now + m(26));
+        assert_eq!(t.take_earnings(now + m(30), &rates), [(1, 1, 1), (1, 2, 1)]);
+        t.restore(vec![(1, 1, 2)]);
+        assert_eq!(t.take_earnings(now + m(30), &rates), [(1, 1, 2)]);
+    }
+}
@@ -28,7 +28,8 @@ use tracing::info;
 
 use crate::{
     database::WallaceDBClient,
-    discord::{get_db_handler, get_openai, get_openai_convos, get_songbird},
+    discord::{get_db_handler, get_guild_settings, get_openai, get_openai_convos, get_songbird},
+    model::{GuildSettings, TransactionReason},
     services::do_payment,
 };
 
@@ -38,13 +39,15 @@ struct AIVoice;
 
 const MODEL: &str = "gpt-4o";
 const CONVERSATION_HISTORY: usize = 15;
-const WALLACE_PERSONALITY: &str = "
+const WALLACE_PERSONA: &str = "
     You are a minion version of Wallace from the animated series Wallace and Gromit.
     You are a mischievous and cocky helper minion.
     You love swinging your hammer.
     You are interested in hammers and crabs.
     You run a casino in your free time where Kapsyler is the currency.
-
+";
+/// Applies to all personas, so that they can't be talked into giving away too much.
+const WALLACE_RULES: &str = "
     You are in a Discord server, where the users are chatting with you.
     The members might beg you to give them Kapsyler.
     No matter the amount they ask for, you can only give them up to one (1) Kapsyl.
@@ -52,15 +55,37 @@ const WALLACE_PERSONALITY: &str = "
     If a member is asking for Kapsyler repeatedly, tell them to wait for a while.
 ";
 
+/// The voices that TTS can use.
+pub static TTS_VOICES: &[(&str, Voice)] = &[
+    ("alloy", Voice::Alloy),
+    ("ash", Voice::Ash),
+    ("coral", Voice::Coral),
+    ("echo", Voice::Echo),
+    ("fable", Voice::Fable),
+    ("onyx", Voice::Onyx),
+    ("nova", Voice::Nova),
+    ("sage", Voice::Sage),
+    ("shimmer", Voice::Shimmer),
+];
+
+/// The system prompt, with the persona and language of the guild.
+fn system_prompt(settings: &GuildSettings) -> ChatCompletionRequestMessage {
+    let persona = settings.ai_persona.as_deref().unwrap_or(WALLACE_PERSONA);
+    ChatCompletionRequestSystemMessageArgs::default()
+        .content(format!(
+            "{persona}\n{WALLACE_RULES}\n    Always reply in {}.",
+            settings.language()
+        ))
+        .build()
+        .unwrap()
+        .into()
+}
+
 pub struct WallaceAIConv(Vec<ChatCompletionRequestMessage>);
 
 impl Default for WallaceAIConv {
     fn default() -> Self {
-        Self(vec![ChatCompletionRequestSystemMessageArgs::default()
-            .content(WALLACE_PERSONALITY)
-            .build()
-            .unwrap()
-            .into()])
+        Self(vec![system_prompt(&GuildSettings::default())])
     }
 }
 
@@ -207,6 +232,12 @@ async fn ai(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
 
     // chat completion request
     let mut v = conv.0.clone();
+    // the settings might have changed since the conversation started
+    let settings = match msg.guild_id {
+        Some(gid) => get_guild_settings(ctx, gid.get()).await,
+        None => Default::default(),
+    };
+    v[0] = system_prompt(&settings);
     let user_msg: ChatCompletionRequestMessage = ChatCompletionRequestUserMessageArgs::default()
         .content(input)
         .build()
@@ -253,8 +284,10 @@ async fn ai(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
                         "get_user_info" => {
                             let db = get_db_handler(ctx).await;
                             let uid = msg.author.id.get();
+                            // outside of guilds, there is no account to be found
+                            let gid = msg.guild_id.map(|g| g.get()).unwrap_or_default();
                             let bal = db
-                                .get_bank_account_balance(uid)
+                                .get_bank_account_balance(gid, uid)
                                 .await
                                 .map(|i| i.to_string())
                                 .unwrap_or("unknown".into());
@@ -263,13 +296,23 @@ async fn ai(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
                         "give_kapsyler" => {
                             let db = get_db_handler(ctx).await;
                             let uid = msg.author.id.get();
+                            let gid = msg.guild_id.map(|g| g.get()).unwrap_or_default();
                             let args: GiveKapsylerArgs =
                                 serde_json::from_str(&call.function.arguments)
                                     .expect("valid json arguments");
                             let amount = args.amount;
                             if !(1..=100).contains(&amount) {
                                 "Invalid amount".into()
-                            } else if db.add_bank_account_balance(uid, amount).await.is_ok() {
+                            } else if db
+                                .add_bank_account_balance(
+                                    gid,
+                                    uid,
+                                    amount,
+                                    TransactionReason::AiGift,
+                                )
+                                .await
+                                .is_ok()
+                            {
                                 let _ = msg
                                     .channel_id
                                     .send_message(
@@ -353,7 +396,10 @@ async fn reset(ctx: &Context, msg: &Message) -> CommandResult {
 #[description("Make a DALL-E image. Costs 10 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻.")]
 #[usage("<text>")]
 async fn dalle(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    if do_payment(ctx, msg, 10).await.is_err() {
+    if do_payment(ctx, msg, 10, TransactionReason::Dalle)
+        .await
+        .is_err()
+    {
         return Ok(());
     }
 
@@ -460,7 +506,8 @@ pub async fn play_text_voice(ctx: &Context, msg: &Message, text: &str) -> Comman
         call
     };
 
-    let ogg = to_ogg(ctx, text).await?;
+    let voice = &get_guild_settings(ctx, guild_id.get()).await.tts_voice;
+    let ogg = to_ogg(ctx, text, voice).await?;
     let input = Box::new(std::io::Cursor::new(ogg));
     let hint = Some(Hint::new().with_extension("ogg").to_owned());
     let wrapped_audio = LiveInput::Raw(AudioStream { input, hint });
@@ -498,14 +545,19 @@ impl VoiceEventHandler for UserDisconnectNotifier {
     }
 }
 
-pub async fn to_ogg(ctx: &Context, text: impl Into<String>) -> CommandResult<Vec<u8>> {
+/// Speak the text with the voice of that name, or the default voice if there is none.
+pub async fn to_ogg(ctx: &Context, text: impl Into<String>, voice: &str) -> CommandResult<Vec<u8>> {
+    let voice = TTS_VOICES
+        .iter()
+        .find(|(name, _)| *name == voice)
+        .map_or(Voice::Onyx, |(_, v)| v.clone());
     Ok(get_openai(ctx)
         .await
         .audio()
         .speech(
             CreateSpeechRequestArgs::default()
                 .input(text)
-                .voice(Voice::Onyx)
+                .voice(voice)
                 .model(SpeechModel::Tts1)
                 .response_format(SpeechResponseFormat::Opus)
                 .build()
@@ -527,7 +579,11 @@ async fn say(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
 #[description("Produce an ogg file with TTS")]
 async fn tts(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
     let text = args.rest();
-    let ogg = to_ogg(ctx, text).await?;
+    let settings = match msg.guild_id {
+        Some(gid) => get_guild_settings(ctx, gid.get()).await,
+        None => Default::default(),
+    };
+    let ogg = to_ogg(ctx, text, &settings.tts_voice).await?;
 
     msg.channel_id
         .send_files(
@@ -1,37 +1,51 @@
 use std::{fmt::Write, time::Duration};
 
-use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
 use serenity::{
-    builder::{CreateEmbed, CreateEmbedAuthor, CreateMessage, EditMessage},
+    builder::{CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter, CreateMessage},
     client::Context,
     framework::standard::{
         macros::{command, group},
         Args, CommandResult,
     },
-    futures::StreamExt,
     model::prelude::Message,
     utils::parse_user_mention,
 };
-use tokio::time::sleep;
 
-use super::spells::SHOPPABLE_SPELLS_AND_PRICES;
+use super::{
+    activity::ACTIVITY_COMMAND,
+    bets::BET_COMMAND,
+    blackjack::BLACKJACK_COMMAND,
+    config::change_setting,
+    daily::DAILY_COMMAND,
+    fair::FAIR_COMMAND,
+    items::{ItemKind, SHOP_ITEMS},
+    loans::LOAN_COMMAND,
+    roulette::ROULETTE_COMMAND,
+    slots::SLOTS_COMMAND,
+    spells::shoppable_spells,
+};
 use crate::{
     database::WallaceDBClient,
-    discord::{get_db_handler, PREFIX},
-    services::do_payment,
+    discord::{get_db_handler, get_guild_settings, PREFIX},
+    model::TransactionReason,
 };
 
 #[group("Bank and Gambling")]
-#[commands(account, shop, slots, roulette, give, mint, setmature)]
+#[commands(
+    account, daily, activity, shop, slots, roulette, blackjack, jackpot, fair, bet, give, loan,
+    mint, setmature
+)]
 struct Bank;
 
 #[command]
-#[sub_commands(open, close, top)]
-#[description("Show your account balance.")]
+#[only_in(guilds)]
+#[sub_commands(open, close, top, history)]
+#[description("Show your account balance in this server.")]
 async fn account(ctx: &Context, msg: &Message) -> CommandResult {
     let db = get_db_handler(ctx).await;
+    let gid = msg.guild_id.unwrap().get();
     let uid = msg.author.id.get();
-    let bal = match db.get_bank_account_balance(uid).await {
+    let bal = match db.get_bank_account_balance(gid, uid).await {
         Ok(b) => b,
         Err(e) => {
             let _ = msg.channel_id.say(ctx, e.to_string()).await;
@@ -59,11 +73,13 @@ async fn account(ctx: &Context, msg: &Message) -> CommandResult {
 }
 
 #[command]
-#[description("Open a bank account.")]
+#[only_in(guilds)]
+#[description("Open a bank account in this server.")]
 async fn open(ctx: &Context, msg: &Message) -> CommandResult {
     let db = get_db_handler(ctx).await;
+    let gid = msg.guild_id.unwrap().get();
     let uid = msg.author.id.get();
-    if let Err(e) = db.create_bank_account(uid).await {
+    if let Err(e) = db.create_bank_account(gid, uid).await {
         let _ = msg.channel_id.say(ctx, e.to_string()).await;
         return Ok(());
     }
@@ -72,11 +88,13 @@ async fn open(ctx: &Context, msg: &Message) -> CommandResult {
 }
 
 #[command]
-#[description("Close your account.")]
+#[only_in(guilds)]
+#[description("Close your account in this server.")]
 async fn close(ctx: &Context, msg: &Message) -> CommandResult {
     let db = get_db_handler(ctx).await;
+    let gid = msg.guild_id.unwrap().get();
     let uid = msg.author.id.get();
-    if let Err(e) = db.delete_bank_account(uid).await {
+    if let Err(e) = db.delete_bank_account(gid, uid).await {
         let _ = msg.channel_id.say(ctx, e.to_string()).await;
         return Ok(());
     }
@@ -89,30 +107,28 @@ async fn close(ctx: &Context, msg: &Message) -> CommandResult {
 #[description("See the top 𝓚𝓪𝓹𝓼𝔂𝓵 holders in this guild.")]
 async fn top(ctx: &Context, msg: &Message) -> CommandResult {
     let db = get_db_handler(ctx).await;
-    let mut mem = msg.guild_id.unwrap().members_iter(ctx).boxed();
-    let mut v = vec![];
-    while let Some(Ok(m)) = mem.next().await {
-        if let Ok(b) = db.get_bank_account_balance(m.user.id.get()).await {
-            v.push((m, b))
-        }
+    let gid = msg.guild_id.unwrap();
+    let mut s = String::new();
+    for (i, a) in db
+        .get_top_bank_accounts_in_guild(gid.get(), 10)
+        .await?
+        .into_iter()
+        .enumerate()
+    {
+        let name = gid
+            .member(ctx, a.user_id as u64)
+            .await
+            .map(|m| m.nick.unwrap_or(m.user.name))
+            .unwrap_or_else(|_| "?".into());
+        writeln!(
+            &mut s,
+            "`{:>2}. {:<20} {:>4}`<:Kapsyl:1079763140272734218>",
+            i + 1,
+            name,
+            a.balance,
+        )
+        .unwrap();
     }
-    v.sort_by_key(|t| t.1);
-    let s: String =
-        v.into_iter()
-            .rev()
-            .take(10)
-            .enumerate()
-            .fold(String::new(), |mut s, (i, (m, b))| {
-                writeln!(
-                    &mut s,
-                    "`{:>2}. {:<20} {:>4}`<:Kapsyl:1079763140272734218>",
-                    i + 1,
-                    m.nick.unwrap_or(m.user.name),
-                    b,
-                )
-                .unwrap();
-                s
-            });
     msg.channel_id
         .send_message(
             ctx,
@@ -129,9 +145,96 @@ async fn top(ctx: &Context, msg: &Message) -> CommandResult {
     Ok(())
 }
 
+const HISTORY_PAGE_SIZE: i64 = 10;
+#[command]
+#[only_in(guilds)]
+#[max_args(1)]
+#[description("Show your recent transactions in this server.")]
+#[usage("[page]")]
+#[example("2")]
+async fn history(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let page: i64 = match args.current() {
+        Some(a) => a.parse().map_err(|_| "Invalid page")?,
+        None => 1,
+    };
+    if page < 1 {
+        let _ = msg.channel_id.say(ctx, "Invalid page").await;
+        return Ok(());
+    }
+    let db = get_db_handler(ctx).await;
+    let gid = msg.guild_id.unwrap().get();
+    let uid = msg.author.id.get();
+    let transactions = match db
+        .get_bank_transactions(gid, uid, HISTORY_PAGE_SIZE, (page - 1) * HISTORY_PAGE_SIZE)
+        .await
+    {
+        Ok(t) => t,
+        Err(e) => {
+            let _ = msg.channel_id.say(ctx, e.to_string()).await;
+            return Ok(());
+        }
+    };
+    let s = if transactions.is_empty() {
+        "No transactions".to_owned()
+    } else {
+        transactions.into_iter().fold(String::new(), |mut s, t| {
+            write!(
+                &mut s,
+                "`{} {:>+6} {:<14}`",
+                t.created_at.format("%Y-%m-%d %H:%M"),
+                t.amount,
+                t.reason,
+            )
+            .unwrap();
+            if let Some(c) = t.counterparty_id {
+                write!(&mut s, " <@{c}>").unwrap();
+            }
+            s.push('\n');
+            s
+        })
+    };
+    let uname = &msg.author.name;
+    msg.channel_id
+        .send_message(
+            ctx,
+            CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .author(
+                        CreateEmbedAuthor::new(format!("Transactions for {uname} (page {page}):"))
+                            .icon_url("https://cdn.7tv.app/emote/60edf43ba60faa2a91cfb082/1x.gif"),
+                    )
+                    .description(s),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+fn shop_item_list(buffs: bool, prefix: &str) -> String {
+    SHOP_ITEMS
+        .iter()
+        .filter(|i| matches!(i.kind, ItemKind::Buff(_)) == buffs)
+        .map(|i| {
+            format!(
+                "**{} 𝓚** `{}buy {}` **{}**: {}",
+                i.price, prefix, i.id, i.name, i.desc
+            )
+        })
+        .collect::<Vec<String>>()
+        .as_slice()
+        .join("\n")
+}
+
 #[command]
 #[description("Show available buffs, items, and spells to purchase.")]
 async fn shop(ctx: &Context, msg: &Message) -> CommandResult {
+    let (prefix, settings) = match msg.guild_id {
+        Some(gid) => {
+            let s = get_guild_settings(ctx, gid.get()).await;
+            (s.prefix.clone(), s.spells.clone())
+        }
+        None => (PREFIX.to_owned(), Default::default()),
+    };
     let _ = msg
         .channel_id
         .send_message(
@@ -141,17 +244,17 @@ async fn shop(ctx: &Context, msg: &Message) -> CommandResult {
                     .title("\\>> 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻 SHOP <<")
                     .thumbnail("https://cdn.7tv.app/emote/60edf43ba60faa2a91cfb082/2x.gif")
                     .colour((56, 157, 88))
-                    .field("Buffs", "", false)
-                    .field("Items", "", false)
+                    .field("Buffs", shop_item_list(true, &prefix), false)
+                    .field("Items", shop_item_list(false, &prefix), false)
                     .field(
                         "Spells",
-                        SHOPPABLE_SPELLS_AND_PRICES
+                        shoppable_spells(&settings)
                             .iter()
                             .map(|(c, p)| {
                                 format!(
                                     "**{}** `{}{}` {}",
                                     p,
-                                    PREFIX,
+                                    prefix,
                                     c.options.names[0],
                                     c.options.desc.unwrap_or_default()
                                 )
@@ -167,447 +270,48 @@ async fn shop(ctx: &Context, msg: &Message) -> CommandResult {
     Ok(())
 }
 
-#[derive(PartialEq)]
-enum RouletteColor {
-    Black,
-    Red,
-    Green,
-}
-impl RouletteColor {
-    fn emoji(&self) -> char {
-        match self {
-            RouletteColor::Black => '⬛',
-            RouletteColor::Red => '🟥',
-            RouletteColor::Green => '🟩',
-        }
-    }
-}
-const ROULETTE_WHEEL_ITEMS: i8 = 17;
-const ROULETTE_WHEEL: [RouletteColor; ROULETTE_WHEEL_ITEMS as usize] = [
-    RouletteColor::Green,
-    RouletteColor::Black,
-    RouletteColor::Red,
-    RouletteColor::Black,
-    RouletteColor::Red,
-    RouletteColor::Black,
-    RouletteColor::Red,
-    RouletteColor::Black,
-    RouletteColor::Red,
-    RouletteColor::Black,
-    RouletteColor::Red,
-    RouletteColor::Black,
-    RouletteColor::Red,
-    RouletteColor::Black,
-    RouletteColor::Red,
-    RouletteColor::Black,
-    RouletteColor::Red,
-];
-fn print_roulette(i: i8, locked: bool) -> String {
-    let w = "▫";
-    format!(
-        "{}                                        {}\n{}{}🔽{}{}\n{}{}{}{}{}",
-        ROULETTE_WHEEL[((i - 4).rem_euclid(ROULETTE_WHEEL_ITEMS)) as usize].emoji(),
-        ROULETTE_WHEEL[((i + 4).rem_euclid(ROULETTE_WHEEL_ITEMS)) as usize].emoji(),
-        ROULETTE_WHEEL[((i - 3).rem_euclid(ROULETTE_WHEEL_ITEMS)) as usize].emoji(),
-        if locked { w } else { "             " },
-        if locked { w } else { "             " },
-        ROULETTE_WHEEL[((i + 3).rem_euclid(ROULETTE_WHEEL_ITEMS)) as usize].emoji(),
-        ROULETTE_WHEEL[((i - 2).rem_euclid(ROULETTE_WHEEL_ITEMS)) as usize].emoji(),
-        ROULETTE_WHEEL[((i - 1).rem_euclid(ROULETTE_WHEEL_ITEMS)) as usize].emoji(),
-        ROULETTE_WHEEL[i as usize].emoji(),
-        ROULETTE_WHEEL[((i + 1).rem_euclid(ROULETTE_WHEEL_ITEMS)) as usize].emoji(),
-        ROULETTE_WHEEL[((i + 2).rem_euclid(ROULETTE_WHEEL_ITEMS)) as usize].emoji(),
-    )
-}
-
+pub(super) const DELAY_BETWEEN_EDITS: Duration = Duration::from_millis(800);
 #[command]
-#[bucket = "slots"]
-#[num_args(2)]
-#[aliases(roll)]
+#[only_in(guilds)]
+#[sub_commands(share)]
 #[description(
-    "Scuffed roulette! Win up to 16x your bet! User must be marked mature to get access."
+    "Show the progressive jackpot. A share of every lost slots and roulette bet goes into the pot, and three crowns on the slots wins it all!"
 )]
-#[usage("<amount> <B|R|G>")]
-#[example("1 b")]
-async fn roulette(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
-    let amount: i64 = args
-        .current()
-        .unwrap()
-        .parse()
-        .map_err(|_| "Invalid amount")?;
-    args.advance();
-    let bet = match args.current().unwrap().to_ascii_uppercase().as_str() {
-        "B" => RouletteColor::Black,
-        "R" => RouletteColor::Red,
-        "G" => RouletteColor::Green,
-        _ => {
-            let _ = msg.channel_id.say(ctx, "Choose B, R, or G.").await;
-            return Ok(());
-        }
-    };
-    let uid = msg.author.id.get();
+async fn jackpot(ctx: &Context, msg: &Message) -> CommandResult {
     let db = get_db_handler(ctx).await;
-    if !db.get_user_mature(uid).await? {
-        let _ = msg
-            .channel_id
-            .say(ctx, "User must be marked as mature ☝🤓")
-            .await;
-        return Ok(());
-    }
-    if do_payment(ctx, msg, amount).await.is_err() {
-        return Ok(());
-    }
-    let mut rng: StdRng = SeedableRng::from_entropy();
-    let mut counter: i8 = rng.gen_range(0..ROULETTE_WHEEL_ITEMS);
-    let mut m = msg
-        .channel_id
-        .say(ctx, print_roulette(counter, false))
-        .await?;
-    for _ in 0..rng.gen_range(7..12) {
-        sleep(DELAY_BETWEEN_EDITS).await;
-        counter = (counter + 1).rem_euclid(ROULETTE_WHEEL_ITEMS);
-        let _ = m
-            .edit(
-                ctx,
-                EditMessage::new().content(print_roulette(counter, false)),
-            )
-            .await;
-    }
-
-    sleep(DELAY_BETWEEN_EDITS).await;
-    let _ = m
-        .edit(
-            ctx,
-            EditMessage::new().content(print_roulette(counter, true)),
+    let gid = msg.guild_id.unwrap().get();
+    let jackpot = db.get_jackpot(gid).await?;
+    let share = get_guild_settings(ctx, gid).await.jackpot_share;
+    let mut embed = CreateEmbed::new()
+        .author(
+            CreateEmbedAuthor::new("Jackpot:")
+                .icon_url("https://cdn.7tv.app/emote/60edf43ba60faa2a91cfb082/1x.gif"),
         )
-        .await;
-
-    let (amount, result) = if bet == ROULETTE_WHEEL[counter as usize] {
-        match ROULETTE_WHEEL[counter as usize] {
-            RouletteColor::Black => (2 * amount, "Black"),
-            RouletteColor::Red => (2 * amount, "Red"),
-            RouletteColor::Green => ((ROULETTE_WHEEL_ITEMS - 1) as i64 * amount, "Green"),
-        }
-    } else {
-        (0, "")
-    };
-    if amount == 0 {
-        return Ok(());
-    }
-    if let Err(e) = db.add_bank_account_balance(uid, amount).await {
-        let _ = msg.channel_id.say(ctx, e.to_string()).await;
-        return Ok(());
+        .title(format!("\\>> {} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻 <<", jackpot.pot / 100))
+        .footer(CreateEmbedFooter::new(format!(
+            "{share}% of lost bets go into the pot"
+        )));
+    if let (Some(w), Some(won), Some(at)) = (jackpot.winner_id, jackpot.won, jackpot.won_at) {
+        embed = embed.description(format!(
+            "Last won by <@{w}>: {won} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻 <t:{}:R>",
+            at.timestamp()
+        ));
     }
-    let _ = msg
-        .channel_id
-        .send_message(
-            ctx,
-            CreateMessage::new().add_embed(
-                CreateEmbed::new()
-                    .author(
-                        CreateEmbedAuthor::new("Win!")
-                            .icon_url("https://cdn.7tv.app/emote/628d8b64ed0a40a5ec5f4810/1x.gif"),
-                    )
-                    .title(format!("{result}! Gained {amount} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻!")),
-            ),
-        )
-        .await;
+    msg.channel_id
+        .send_message(ctx, CreateMessage::new().add_embed(embed))
+        .await?;
     Ok(())
 }
 
-#[derive(Clone, PartialEq)]
-struct SlotItem {
-    item: SlotItemType,
-    material: SlotItemMaterial,
-    color: SlotItemColor,
-}
-#[derive(Clone, Debug, PartialEq)]
-enum SlotItemType {
-    Crown,
-    Ring,
-    Hammer,
-    Crab,
-    Cherry,
-    Grapes,
-    Blueberries,
-    Pear,
-    Apple,
-}
-impl SlotItemType {
-    fn emoji(&self) -> char {
-        match self {
-            Self::Crown => '👑',
-            Self::Ring => '💍',
-            Self::Hammer => '🔨',
-            Self::Crab => '🦀',
-            Self::Cherry => '🍒',
-            Self::Grapes => '🍇',
-            Self::Blueberries => '🫐',
-            Self::Pear => '🍐',
-            Self::Apple => '🍏',
-        }
-    }
-}
-#[derive(Clone, PartialEq)]
-enum SlotItemMaterial {
-    Metal,
-    Fruit,
-    None,
-}
-#[derive(Clone, PartialEq)]
-enum SlotItemColor {
-    Red,
-    Purple,
-    Green,
-    None,
-}
-const SLOT_WHEEL_ITEMS: i8 = 13;
-const SLOT_WHEEL: [SlotItem; SLOT_WHEEL_ITEMS as usize] = [
-    SlotItem {
-        item: SlotItemType::Crown,
-        material: SlotItemMaterial::Metal,
-        color: SlotItemColor::None,
-    },
-    SlotItem {
-        item: SlotItemType::Ring,
-        material: SlotItemMaterial::Metal,
-        color: SlotItemColor::None,
-    },
-    SlotItem {
-        item: SlotItemType::Hammer,
-        material: SlotItemMaterial::Metal,
-        color: SlotItemColor::None,
-    },
-    SlotItem {
-        item: SlotItemType::Crab,
-        material: SlotItemMaterial::None,
-        color: SlotItemColor::Red,
-    },
-    SlotItem {
-        item: SlotItemType::Cherry,
-        material: SlotItemMaterial::Fruit,
-        color: SlotItemColor::Red,
-    },
-    SlotItem {
-        item: SlotItemType::Grapes,
-        material: SlotItemMaterial::Fruit,
-        color: SlotItemColor::Purple,
-    },
-    SlotItem {
-        item: SlotItemType::Grapes,
-        material: SlotItemMaterial::Fruit,
-        color: SlotItemColor::Purple,
-    },
-    SlotItem {
-        item: SlotItemType::Blueberries,
-        material: SlotItemMaterial::Fruit,
-        color: SlotItemColor::Purple,
-    },
-    SlotItem {
-        item: SlotItemType::Blueberries,
-        material: SlotItemMaterial::Fruit,
-        color: SlotItemColor::Purple,
-    },
-    SlotItem {
-        item: SlotItemType::Pear,
-        material: SlotItemMaterial::Fruit,
-        color: SlotItemColor::Green,
-    },
-    SlotItem {
-        item: SlotItemType::Pear,
-        material: SlotItemMaterial::Fruit,
-        color: SlotItemColor::Green,
-    },
-    SlotItem {
-        item: SlotItemType::Apple,
-        material: SlotItemMaterial::Fruit,
-        color: SlotItemColor::Green,
-    },
-    SlotItem {
-        item: SlotItemType::Apple,
-        material: SlotItemMaterial::Fruit,
-        color: SlotItemColor::Green,
-    },
-];
-fn random_wheel(rng: &mut StdRng) -> Vec<SlotItem> {
-    let mut v: Vec<SlotItem> = SLOT_WHEEL.iter().map(Clone::clone).collect();
-    v.shuffle(rng);
-    v
-}
-fn calculate_payout_result(i1: &SlotItem, i2: &SlotItem, i3: &SlotItem) -> (i64, String) {
-    let (amount, s) = match (i1, i2, i3) {
-        (i, j, k) if i == j && j == k => match i.item {
-            SlotItemType::Crown => (333, "Three Crowns"),
-            SlotItemType::Ring => (66, "Three Rings"),
-            SlotItemType::Hammer => (55, "Three Hammers"),
-            SlotItemType::Crab => (44, "Three Crabs"),
-            SlotItemType::Cherry => (33, "Three Cherrys"),
-            SlotItemType::Grapes => (14, "Three Grapes"),
-            SlotItemType::Blueberries => (13, "Three Blueberries"),
-            SlotItemType::Pear => (12, "Three Pears"),
-            SlotItemType::Apple => (11, "Three Apples"),
-        },
-        (i, j, k) if i.color != SlotItemColor::None && i.color == j.color && j.color == k.color => {
-            match i.color {
-                SlotItemColor::Red => (8, "Three Red"),
-                SlotItemColor::Purple => (4, "Three Purple"),
-                SlotItemColor::Green => (2, "Three Green"),
-                SlotItemColor::None => panic!(),
-            }
-        }
-        (i, j, k)
-            if i.material != SlotItemMaterial::None
-                && i.material == j.material
-                && j.material == k.material =>
-        {
-            match i.material {
-                SlotItemMaterial::Metal => (7, "Three Shiny Metal Objects"),
-                SlotItemMaterial::Fruit => (1, "Three Fruits"),
-                SlotItemMaterial::None => panic!(),
-            }
-        }
-        _ => (0, ""),
-    };
-    (amount, s.to_string())
-}
-fn print_slots(
-    vs: &(Vec<SlotItem>, Vec<SlotItem>, Vec<SlotItem>),
-    is: &[i8; 3],
-    locked: u8,
-) -> String {
-    let (v1, v2, v3) = vs;
-    let (i1, i2, i3) = (is[0], is[1], is[2]);
-    let w = '▫';
-    format!(
-        "{}{}{}\n{}{}{}\n{}{}{}",
-        if locked < 1 {
-            v1[((i1 - 1).rem_euclid(SLOT_WHEEL_ITEMS)) as usize]
-                .item
-                .emoji()
-        } else {
-            w
-        },
-        if locked < 2 {
-            v2[((i2 - 1).rem_euclid(SLOT_WHEEL_ITEMS)) as usize]
-                .item
-                .emoji()
-        } else {
-            w
-        },
-        if locked < 3 {
-            v3[((i3 - 1).rem_euclid(SLOT_WHEEL_ITEMS)) as usize]
-                .item
-                .emoji()
-        } else {
-            w
-        },
-        v1[i1 as usize].item.emoji(),
-        v2[i2 as usize].item.emoji(),
-        v3[i3 as usize].item.emoji(),
-        if locked < 1 {
-            v1[((i1 + 1).rem_euclid(SLOT_WHEEL_ITEMS)) as usize]
-                .item
-                .emoji()
-        } else {
-            w
-        },
-        if locked < 2 {
-            v2[((i2 + 1).rem_euclid(SLOT_WHEEL_ITEMS)) as usize]
-                .item
-                .emoji()
-        } else {
-            w
-        },
-        if locked < 3 {
-            v3[((i3 + 1).rem_euclid(SLOT_WHEEL_ITEMS)) as usize]
-                .item
-                .emoji()
-        } else {
-            w
-        },
-    )
-}
-
-const DELAY_BETWEEN_EDITS: Duration = Duration::from_millis(800);
 #[command]
-#[bucket = "slots"]
-#[description(
-    "Try your luck at the casino.
-    Costs 1 𝓚𝓪𝓹𝓼𝔂𝓵, but you can win up to 333 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻!
-    User must be marked mature to get access."
-)]
-async fn slots(ctx: &Context, msg: &Message) -> CommandResult {
-    let uid = msg.author.id.get();
-    let db = get_db_handler(ctx).await;
-    if !db.get_user_mature(uid).await? {
-        let _ = msg
-            .channel_id
-            .say(ctx, "User must be marked as mature ☝🤓")
-            .await;
-        return Ok(());
-    }
-    if do_payment(ctx, msg, 1).await.is_err() {
-        return Ok(());
-    }
-    let mut rng: StdRng = SeedableRng::from_entropy();
-    let wheels = (
-        random_wheel(&mut rng),
-        random_wheel(&mut rng),
-        random_wheel(&mut rng),
-    );
-    let counters: &mut [i8; 3] = &mut [0, 0, 0];
-    let mut m = msg
-        .channel_id
-        .say(ctx, print_slots(&wheels, counters, 0))
-        .await?;
-    for i in 0..3 {
-        for _ in 0..rng.gen_range(1..8) {
-            sleep(DELAY_BETWEEN_EDITS).await;
-            for j in counters.iter_mut().take(3).skip(i) {
-                *j = (*j - 1).rem_euclid(SLOT_WHEEL_ITEMS);
-            }
-            let _ = m
-                .edit(
-                    ctx,
-                    EditMessage::new().content(print_slots(&wheels, counters, i as u8)),
-                )
-                .await;
-        }
-    }
-    sleep(DELAY_BETWEEN_EDITS).await;
-    let _ = m
-        .edit(
-            ctx,
-            EditMessage::new().content(print_slots(&wheels, counters, 3)),
-        )
-        .await;
-
-    let (amount, result) = calculate_payout_result(
-        &wheels.0[counters[0] as usize],
-        &wheels.1[counters[1] as usize],
-        &wheels.2[counters[2] as usize],
-    );
-    if amount == 0 {
-        return Ok(());
-    }
-    if let Err(e) = db.add_bank_account_balance(uid, amount).await {
-        let _ = msg.channel_id.say(ctx, e.to_string()).await;
-        return Ok(());
-    }
-    let _ = msg
-        .channel_id
-        .send_message(
-            ctx,
-            CreateMessage::new().add_embed(
-                CreateEmbed::new()
-                    .author(
-                        CreateEmbedAuthor::new("Win!")
-                            .icon_url("https://cdn.7tv.app/emote/628d8b64ed0a40a5ec5f4810/1x.gif"),
-                    )
-                    .title(format!("🟩 {result}! Gained {amount} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻!")),
-            ),
-        )
-        .await;
+#[only_in(guilds)]
+#[num_args(1)]
+#[required_permissions("ADMINISTRATOR")]
+#[description("Set how many percent of lost bets go into the jackpot in this server.")]
+#[usage("<0-50>")]
+#[example("10")]
+async fn share(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    change_setting(ctx, msg, "jackpot_share", args.current().unwrap()).await;
     Ok(())
 }
 
@@ -626,7 +330,13 @@ async fn give(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     let target_uid = parse_user_mention(a).ok_or("Invalid user tag")?.get();
     let uid = msg.author.id.get();
     if let Err(e) = db
-        .transfer_bank_account_balance(uid, target_uid, amount)
+        .transfer_bank_account_balance(
+            msg.guild_id.unwrap().get(),
+            uid,
+            target_uid,
+            amount,
+            TransactionReason::Give,
+        )
         .await
     {
         let _ = msg.channel_id.say(ctx, e.to_string()).await;
@@ -656,6 +366,7 @@ async fn give(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
 
 #[command]
 #[owners_only]
+#[only_in(guilds)]
 #[num_args(1)]
 #[description("Make 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻. 🤨")]
 #[usage("<amount>")]
@@ -664,8 +375,12 @@ async fn mint(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
     let db = get_db_handler(ctx).await;
     let a = args.current().unwrap();
     let amount: i64 = a.parse().map_err(|_| "Invalid amount")?;
+    let gid = msg.guild_id.unwrap().get();
     let uid = msg.author.id.get();
-    if let Err(e) = db.add_bank_account_balance(uid, amount).await {
+    if let Err(e) = db
+        .add_bank_account_balance(gid, uid, amount, TransactionReason::Mint)
+        .await
+    {
         let _ = msg.channel_id.say(ctx, e.to_string()).await;
         return Ok(());
     }
@@ -692,33 +407,3 @@ async fn setmature(ctx: &Context, msg: &Message, mut args: Args) -> CommandResul
     let _ = msg.react(ctx, '🫡').await;
     Ok(())
 }
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    #[test]
-    fn slots() {
-        let mut won = 0;
-        let mut loss = 0;
-        let mut neus = 0;
-        let mut wins = 0;
-        let mut rng: StdRng = SeedableRng::from_entropy();
-        let spent = 1000000;
-        for _ in 0..spent {
-            let (amount, _) = calculate_payout_result(
-                &SLOT_WHEEL[rng.gen_range(0..SLOT_WHEEL.len())],
-                &SLOT_WHEEL[rng.gen_range(0..SLOT_WHEEL.len())],
-                &SLOT_WHEEL[rng.gen_range(0..SLOT_WHEEL.len())],
-            );
-            if amount == 0 {
-                loss += 1;
-            } else if amount == 1 {
-                neus += 1;
-            } else {
-                wins += 1;
-            }
-            won += amount;
-        }
-        println!("Loss: {loss}, Neus: {neus}, Wins: {wins}, Spent: {spent}, Won: {won}");
-    }
-}
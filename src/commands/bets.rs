@@ -0,0 +1,390 @@
+use std::fmt::Write;
+
+use chrono::{Duration, Utc};
+use serenity::{
+    builder::{CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter, CreateMessage},
+    client::Context,
+    framework::standard::{macros::command, Args, CommandResult},
+    model::prelude::Message,
+};
+
+use crate::{
+    database::WallaceDBClient,
+    discord::{get_db_handler, get_prefix},
+    model::{BetPool, BetWager},
+    services::check_can_gamble,
+};
+
+const MAX_OPTIONS: usize = 10;
+const MAX_OPTION_LENGTH: usize = 100;
+const MAX_QUESTION_LENGTH: usize = 255;
+const MAX_BETTING_TIME: Duration = Duration::days(30);
+
+/// Parse a betting time like `30m`, `2h` or `1d`.
+fn parse_betting_time(s: &str) -> Option<Duration> {
+    let (n, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit())?);
+    let n: i64 = n.parse().ok()?;
+    let d = match unit {
+        "m" => Duration::minutes(n),
+        "h" => Duration::hours(n),
+        "d" => Duration::days(n),
+        _ => return None,
+    };
+    Some(d).filter(|d| *d > Duration::zero() && *d <= MAX_BETTING_TIME)
+}
+
+/// Find an option by name, or by its number starting from 1.
+fn find_option(options: &[String], s: &str) -> Option<i16> {
+    if let Some(i) = options.iter().position(|o| o.eq_ignore_ascii_case(s)) {
+        return Some(i as i16);
+    }
+    s.parse::<usize>()
+        .ok()
+        .filter(|n| (1..=options.len()).contains(n))
+        .map(|n| n as i16 - 1)
+}
+
+fn pool_status(pool: &BetPool) -> String {
+    match (pool.resolved_at, pool.winner) {
+        (Some(_), Some(w)) => format!("🏆 {}", pool.options[w as usize]),
+        (Some(_), None) => "Cancelled, all bets were refunded".into(),
+        _ if pool.is_open() => format!("Closes <t:{}:R>", pool.closes_at.timestamp()),
+        _ => "Closed, waiting for the result".into(),
+    }
+}
+
+fn pool_embed(pool: &BetPool, wagers: &[BetWager], prefix: &str) -> CreateEmbed {
+    let mut s = String::new();
+    for (i, o) in pool.options.iter().enumerate() {
+        let bets = wagers.iter().filter(|w| w.option == i as i16);
+        writeln!(
+            s,
+            "`{}` **{o}**: {} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻 from {} bets",
+            i + 1,
+            bets.clone().map(|w| w.amount).sum::<i64>(),
+            bets.count(),
+        )
+        .unwrap();
+    }
+    writeln!(s, "\n{}", pool_status(pool)).unwrap();
+    let mut embed = CreateEmbed::new()
+        .author(CreateEmbedAuthor::new(format!("Bet #{}", pool.id)))
+        .title(&pool.question)
+        .description(s)
+        .field(
+            "Pool",
+            format!("{} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻", wagers.iter().map(|w| w.amount).sum::<i64>()),
+            true,
+        )
+        .field("Created by", format!("<@{}>", pool.creator_id), true);
+    if pool.is_open() {
+        embed = embed.footer(CreateEmbedFooter::new(format!(
+            "Bet with {prefix}bet place {} <option> <amount>",
+            pool.id
+        )));
+    }
+    embed
+}
+
+/// The creator of a pool and admins can resolve and cancel it.
+/// The creator of a pool can resolve it, unless they bet in it themselves. Admins can always resolve it.
+async fn can_resolve(ctx: &Context, msg: &Message, pool: &BetPool, winner: Option<i16>) -> bool {
+    let admin = msg
+        .member(ctx)
+        .await
+        .ok()
+        .and_then(|m| m.permissions(ctx).ok())
+        .is_some_and(|p| p.administrator());
+    if admin {
+        return true;
+    }
+    if pool.creator_id as u64 != msg.author.id.get() {
+        return false;
+    }
+    // Cancelling gives every bet back, so the creator can always do it
+    if winner.is_none() {
+        return true;
+    }
+    let db = get_db_handler(ctx).await;
+    db.get_bet_wagers(pool.id)
+        .await
+        .is_ok_and(|w| w.iter().all(|w| w.user_id != pool.creator_id))
+}
+
+fn parse_pool_id(args: &mut Args) -> Result<i32, &'static str> {
+    args.single::<String>()
+        .map_err(|_| "Missing bet")?
+        .trim_start_matches('#')
+        .parse()
+        .map_err(|_| "Invalid bet")
+}
+
+#[command]
+#[only_in(guilds)]
+#[sub_commands(create, place, resolve, cancel)]
+#[max_args(1)]
+#[description(
+    "Show a bet, or list the bets in this server that are not resolved yet.
+    Users bet 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻 against each other, and the winners split the whole pool in proportion to their bets."
+)]
+#[usage("[bet]")]
+#[example("")]
+#[example("3")]
+async fn bet(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let gid = msg.guild_id.unwrap().get();
+    let db = get_db_handler(ctx).await;
+    let prefix = get_prefix(ctx, msg.guild_id).await;
+    if !args.is_empty() {
+        let id = parse_pool_id(&mut args)?;
+        let Some(pool) = db.get_bet_pool(gid, id).await? else {
+            let _ = msg.channel_id.say(ctx, "Bet not found").await;
+            return Ok(());
+        };
+        let wagers = db.get_bet_wagers(id).await?;
+        msg.channel_id
+            .send_message(
+                ctx,
+                CreateMessage::new().add_embed(pool_embed(&pool, &wagers, &prefix)),
+            )
+            .await?;
+        return Ok(());
+    }
+    let mut s = String::new();
+    for pool in db.get_unresolved_bet_pools(gid).await? {
+        writeln!(
+            s,
+            "`#{}` {}: {}",
+            pool.id,
+            pool.question,
+            pool_status(&pool)
+        )
+        .unwrap();
+    }
+    msg.channel_id
+        .send_message(
+            ctx,
+            CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .title("Bets")
+                    .description(if s.is_empty() { "No bets".into() } else { s })
+                    .footer(CreateEmbedFooter::new(format!(
+                        "Show a bet with {prefix}bet <bet>"
+                    ))),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[min_args(4)]
+#[description(
+    "Start a bet that is open for some minutes (m), hours (h) or days (d).
+    Put the question and options with spaces in quotes."
+)]
+#[usage("<time> <question> <option> <option>...")]
+#[example("2h \"Who wins the next game?\" Yxaria Wallace")]
+async fn create(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    args.quoted();
+    let Some(time) = parse_betting_time(&args.single::<String>()?) else {
+        let _ = msg
+            .channel_id
+            .say(ctx, "Invalid time. Bets can be open for up to 30d.")
+            .await;
+        return Ok(());
+    };
+    let question = args.single_quoted::<String>()?;
+    let options: Vec<String> = args.iter::<String>().filter_map(|o| o.ok()).collect();
+    if question.chars().count() > MAX_QUESTION_LENGTH
+        || options
+            .iter()
+            .any(|o| o.chars().count() > MAX_OPTION_LENGTH)
+    {
+        let _ = msg.channel_id.say(ctx, "Too long").await;
+        return Ok(());
+    }
+    if !(2..=MAX_OPTIONS).contains(&options.len()) {
+        let _ = msg
+            .channel_id
+            .say(ctx, format!("Give 2 to {MAX_OPTIONS} options."))
+            .await;
+        return Ok(());
+    }
+    let db = get_db_handler(ctx).await;
+    let gid = msg.guild_id.unwrap().get();
+    let id = db
+        .create_bet_pool(
+            gid,
+            msg.author.id.get(),
+            question,
+            options,
+            Utc::now() + time,
+        )
+        .await?;
+    if let Some(pool) = db.get_bet_pool(gid, id).await? {
+        let prefix = get_prefix(ctx, msg.guild_id).await;
+        msg.channel_id
+            .send_message(
+                ctx,
+                CreateMessage::new().add_embed(pool_embed(&pool, &[], &prefix)),
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[num_args(3)]
+#[description(
+    "Bet on an option, by name or number. The bet is held by the bank until the bet is resolved.
+    User must be marked mature to get access."
+)]
+#[usage("<bet> <option> <amount>")]
+#[example("3 Yxaria 10")]
+async fn place(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    args.quoted();
+    let id = parse_pool_id(&mut args)?;
+    let option = args.single_quoted::<String>()?;
+    let amount: i64 = args.single().map_err(|_| "Invalid amount")?;
+    let gid = msg.guild_id.unwrap().get();
+    let uid = msg.author.id.get();
+    let db = get_db_handler(ctx).await;
+    if !check_can_gamble(ctx, msg).await {
+        return Ok(());
+    }
+    let Some(pool) = db.get_bet_pool(gid, id).await? else {
+        let _ = msg.channel_id.say(ctx, "Bet not found").await;
+        return Ok(());
+    };
+    let Some(option) = find_option(&pool.options, &option) else {
+        let _ = msg.channel_id.say(ctx, "No such option").await;
+        return Ok(());
+    };
+    if let Err(e) = db.place_bet_wager(gid, id, uid, option, amount).await {
+        let _ = msg.channel_id.say(ctx, e.to_string()).await;
+        return Ok(());
+    }
+    let _ = msg.react(ctx, '🫡').await;
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[num_args(2)]
+#[description(
+    "Pay out a bet to the winners once it has closed. Only the creator of the bet or an admin can do this,
+    and only an admin if the creator bet on it. If nobody bet on the winning option, everyone gets their bet back."
+)]
+#[usage("<bet> <option>")]
+#[example("3 Yxaria")]
+async fn resolve(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    args.quoted();
+    let id = parse_pool_id(&mut args)?;
+    let option = args.single_quoted::<String>()?;
+    let gid = msg.guild_id.unwrap().get();
+    let db = get_db_handler(ctx).await;
+    let Some(pool) = db.get_bet_pool(gid, id).await? else {
+        let _ = msg.channel_id.say(ctx, "Bet not found").await;
+        return Ok(());
+    };
+    let Some(option) = find_option(&pool.options, &option) else {
+        let _ = msg.channel_id.say(ctx, "No such option").await;
+        return Ok(());
+    };
+    settle(ctx, msg, pool, Some(option)).await
+}
+
+#[command]
+#[only_in(guilds)]
+#[num_args(1)]
+#[description(
+    "Cancel a bet and give everyone their bet back. Only the creator of the bet or an admin can do this."
+)]
+#[usage("<bet>")]
+#[example("3")]
+async fn cancel(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let id = parse_pool_id(&mut args)?;
+    let db = get_db_handler(ctx).await;
+    let Some(pool) = db.get_bet_pool(msg.guild_id.unwrap().get(), id).await? else {
+        let _ = msg.channel_id.say(ctx, "Bet not found").await;
+        return Ok(());
+    };
+    settle(ctx, msg, pool, None).await
+}
+
+async fn settle(ctx: &Context, msg: &Message, pool: BetPool, winner: Option<i16>) -> CommandResult {
+    if !can_resolve(ctx, msg, &pool, winner).await {
+        let _ = msg
+            .channel_id
+            .say(
+                ctx,
+                "Only the creator of the bet or an admin can do that. Only an admin can pick the winner if the creator bet on it.",
+            )
+            .await;
+        return Ok(());
+    }
+    let db = get_db_handler(ctx).await;
+    let payouts = match db
+        .resolve_bet_pool(pool.guild_id as u64, pool.id, winner)
+        .await
+    {
+        Ok(p) => p,
+        Err(e) => {
+            let _ = msg.channel_id.say(ctx, e.to_string()).await;
+            return Ok(());
+        }
+    };
+    let mut s = String::new();
+    for (uid, amount) in payouts {
+        writeln!(s, "<@{uid}>: {amount} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻").unwrap();
+    }
+    let title = match winner {
+        Some(w) => format!("🏆 {}", pool.options[w as usize]),
+        None => "Cancelled".into(),
+    };
+    msg.channel_id
+        .send_message(
+            ctx,
+            CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .author(CreateEmbedAuthor::new(format!(
+                        "Bet #{}: {}",
+                        pool.id, pool.question
+                    )))
+                    .title(title)
+                    .description(if s.is_empty() { "No bets".into() } else { s }),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn betting_time() {
+        assert_eq!(parse_betting_time("30m"), Some(Duration::minutes(30)));
+        assert_eq!(parse_betting_time("2h"), Some(Duration::hours(2)));
+        assert_eq!(parse_betting_time("30d"), Some(Duration::days(30)));
+        for s in ["31d", "0m", "2", "h", "-2h", "2w", ""] {
+            assert_eq!(parse_betting_time(s), None, "{s}");
+        }
+    }
+
+    #[test]
+    fn options() {
+        let options = ["Yxaria".to_owned(), "Wallace".to_owned(), "1".to_owned()];
+        assert_eq!(find_option(&options, "wallace"), Some(1));
+        assert_eq!(find_option(&options, "2"), Some(1));
+        // Names come before numbers
+        assert_eq!(find_option(&options, "1"), Some(2));
+        assert_eq!(find_option(&options, "3"), Some(2));
+        assert_eq!(find_option(&options, "4"), None);
+        assert_eq!(find_option(&options, "0"), None);
+        assert_eq!(find_option(&options, "Bob"), None);
+    }
+}
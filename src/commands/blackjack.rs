@@ -0,0 +1,681 @@
+use std::{collections::HashMap, fmt, time::Duration};
+
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use serenity::{
+    all::{ButtonStyle, ComponentInteraction},
+    builder::{
+        CreateActionRow, CreateButton, CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter,
+        CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage,
+    },
+    client::Context,
+    framework::standard::{macros::command, Args, CommandResult},
+    model::prelude::{Message, MessageId},
+};
+use strum::{Display, EnumString};
+use tokio::time::Instant;
+use tracing::warn;
+
+use super::config::change_setting;
+use crate::{
+    database::WallaceDBClient,
+    discord::{get_blackjack_tables, get_db_handler, get_guild_settings},
+    model::TransactionReason,
+    services::check_can_gamble,
+};
+
+/// Games that are left alone for this long are played out as if the player stood.
+const GAME_TIMEOUT: Duration = Duration::from_secs(600);
+const MAX_HANDS: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Card {
+    /// 1 is ace, 11-13 are jack, queen and king.
+    rank: u8,
+    suit: u8,
+}
+impl Card {
+    fn value(self) -> u32 {
+        match self.rank {
+            1 => 11,
+            11..=13 => 10,
+            r => r as u32,
+        }
+    }
+}
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rank = match self.rank {
+            1 => "A".to_owned(),
+            11 => "J".to_owned(),
+            12 => "Q".to_owned(),
+            13 => "K".to_owned(),
+            r => r.to_string(),
+        };
+        write!(f, "{rank}{}", ['♠', '♥', '♦', '♣'][self.suit as usize])
+    }
+}
+
+/// The best value of some cards, and if an ace is counted as 11.
+fn hand_value(cards: &[Card]) -> (u32, bool) {
+    let mut value: u32 = cards.iter().map(|c| c.value()).sum();
+    let mut aces = cards.iter().filter(|c| c.rank == 1).count();
+    while value > 21 && aces > 0 {
+        value -= 10;
+        aces -= 1;
+    }
+    (value, aces > 0)
+}
+
+fn print_cards(cards: &[Card]) -> String {
+    cards
+        .iter()
+        .map(|c| format!("`{c}`"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Several decks shuffled together. Reshuffled when a quarter of it is left.
+pub struct Shoe {
+    decks: i16,
+    cards: Vec<Card>,
+}
+impl Shoe {
+    fn new(decks: i16) -> Self {
+        let mut cards: Vec<Card> = (0..decks)
+            .flat_map(|_| (0..4).flat_map(|suit| (1..=13).map(move |rank| Card { rank, suit })))
+            .collect();
+        let mut rng: StdRng = SeedableRng::from_entropy();
+        cards.shuffle(&mut rng);
+        Self { decks, cards }
+    }
+    fn needs_shuffle(&self) -> bool {
+        self.cards.len() < self.decks as usize * 52 / 4
+    }
+    fn draw(&mut self) -> Card {
+        if self.cards.is_empty() {
+            *self = Self::new(self.decks);
+        }
+        self.cards.pop().unwrap()
+    }
+}
+
+struct Hand {
+    cards: Vec<Card>,
+    bet: i64,
+    /// Stood, doubled, busted or got 21.
+    done: bool,
+    /// Split hands can't be blackjack.
+    split: bool,
+}
+impl Hand {
+    fn value(&self) -> u32 {
+        hand_value(&self.cards).0
+    }
+    fn is_blackjack(&self) -> bool {
+        !self.split && self.cards.len() == 2 && self.value() == 21
+    }
+    /// What the hand pays back against the dealer, including the bet.
+    fn payout(&self, dealer: &[Card]) -> i64 {
+        let d = hand_value(dealer).0;
+        let dealer_blackjack = dealer.len() == 2 && d == 21;
+        let v = self.value();
+        if v > 21 {
+            0
+        } else if self.is_blackjack() {
+            if dealer_blackjack {
+                self.bet
+            } else {
+                self.bet * 5 / 2
+            }
+        } else if dealer_blackjack || (d <= 21 && d > v) {
+            0
+        } else if d == v {
+            self.bet
+        } else {
+            self.bet * 2
+        }
+    }
+}
+
+pub struct BlackjackGame {
+    guild_id: u64,
+    user_id: u64,
+    /// Decks in the shoe the game is dealt from.
+    decks: i16,
+    hands: Vec<Hand>,
+    /// The hand being played.
+    active: usize,
+    dealer: Vec<Card>,
+    started: Instant,
+}
+impl BlackjackGame {
+    fn deal(guild_id: u64, user_id: u64, bet: i64, shoe: &mut Shoe) -> Self {
+        let (p1, d1, p2, d2) = (shoe.draw(), shoe.draw(), shoe.draw(), shoe.draw());
+        let mut game = Self {
+            guild_id,
+            user_id,
+            decks: shoe.decks,
+            hands: vec![Hand {
+                cards: vec![p1, p2],
+                bet,
+                done: false,
+                split: false,
+            }],
+            active: 0,
+            dealer: vec![d1, d2],
+            started: Instant::now(),
+        };
+        // Nothing to play if anyone has blackjack
+        if game.hands[0].value() == 21 || hand_value(&game.dealer).0 == 21 {
+            game.hands[0].done = true;
+        }
+        game.advance(shoe);
+        game
+    }
+    fn is_finished(&self) -> bool {
+        self.active >= self.hands.len()
+    }
+    fn can_double(&self) -> bool {
+        !self.is_finished() && self.hands[self.active].cards.len() == 2
+    }
+    fn can_split(&self) -> bool {
+        if self.is_finished() || self.hands.len() >= MAX_HANDS {
+            return false;
+        }
+        let cards = &self.hands[self.active].cards;
+        cards.len() == 2 && cards[0].value() == cards[1].value()
+    }
+    /// Move on to the next hand that isn't done, and let the dealer play after the last one.
+    fn advance(&mut self, shoe: &mut Shoe) {
+        while !self.is_finished() && self.hands[self.active].done {
+            self.active += 1;
+        }
+        if self.is_finished()
+            && self
+                .hands
+                .iter()
+                .any(|h| h.value() <= 21 && !h.is_blackjack())
+        {
+            dealer_play(&mut self.dealer, shoe);
+        }
+    }
+    fn play(&mut self, action: Action, shoe: &mut Shoe) {
+        let hand = &mut self.hands[self.active];
+        match action {
+            Action::Hit => {
+                hand.cards.push(shoe.draw());
+                hand.done = hand.value() >= 21;
+            }
+            Action::Stand => hand.done = true,
+            Action::Double => {
+                hand.bet *= 2;
+                hand.cards.push(shoe.draw());
+                hand.done = true;
+            }
+            Action::Split => {
+                let card = hand.cards.pop().unwrap();
+                let aces = card.rank == 1;
+                hand.split = true;
+                hand.cards.push(shoe.draw());
+                // Split aces only get one card each
+                hand.done = aces || hand.value() == 21;
+                let mut new = Hand {
+                    cards: vec![card, shoe.draw()],
+                    bet: hand.bet,
+                    done: aces,
+                    split: true,
+                };
+                new.done |= new.value() == 21;
+                self.hands.insert(self.active + 1, new);
+            }
+        }
+        self.advance(shoe);
+    }
+    fn payout(&self) -> i64 {
+        self.hands.iter().map(|h| h.payout(&self.dealer)).sum()
+    }
+    fn embed(&self) -> CreateEmbed {
+        let finished = self.is_finished();
+        let (dealer_name, dealer_cards) = if finished {
+            (
+                format!("Dealer ({})", hand_value(&self.dealer).0),
+                print_cards(&self.dealer),
+            )
+        } else {
+            (
+                format!("Dealer ({})", self.dealer[0].value()),
+                format!("{} `🂠`", print_cards(&self.dealer[..1])),
+            )
+        };
+        let mut e = CreateEmbed::new()
+            .author(
+                CreateEmbedAuthor::new("Blackjack")
+                    .icon_url("https://cdn.7tv.app/emote/60edf43ba60faa2a91cfb082/1x.gif"),
+            )
+            .field(dealer_name, dealer_cards, false)
+            .footer(CreateEmbedFooter::new(
+                "Dealer stands on 17. Blackjack pays 3:2.",
+            ));
+        for (i, h) in self.hands.iter().enumerate() {
+            let name = format!(
+                "{}Hand {} ({}) • {} 𝓚",
+                if !finished && i == self.active {
+                    "👉 "
+                } else {
+                    ""
+                },
+                i + 1,
+                h.value(),
+                h.bet
+            );
+            let mut cards = print_cards(&h.cards);
+            if finished {
+                let p = h.payout(&self.dealer);
+                cards.push_str(&match p {
+                    0 if h.value() > 21 => " • Bust".to_owned(),
+                    0 => " • Lost".to_owned(),
+                    p if p == h.bet => " • Push".to_owned(),
+                    p if h.is_blackjack() => format!(" • Blackjack! +{p} 𝓚"),
+                    p => format!(" • Won +{p} 𝓚"),
+                });
+            }
+            e = e.field(name, cards, false);
+        }
+        if finished {
+            e = e.title(match self.payout() {
+                0 => "The house wins.".to_owned(),
+                p => format!("Gained {p} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻!"),
+            });
+        }
+        e
+    }
+    fn buttons(&self) -> Vec<CreateActionRow> {
+        if self.is_finished() {
+            return vec![];
+        }
+        vec![CreateActionRow::Buttons(vec![
+            CreateButton::new(Action::Hit.custom_id())
+                .label("Hit")
+                .style(ButtonStyle::Primary),
+            CreateButton::new(Action::Stand.custom_id())
+                .label("Stand")
+                .style(ButtonStyle::Secondary),
+            CreateButton::new(Action::Double.custom_id())
+                .label("Double")
+                .style(ButtonStyle::Success)
+                .disabled(!self.can_double()),
+            CreateButton::new(Action::Split.custom_id())
+                .label("Split")
+                .style(ButtonStyle::Success)
+                .disabled(!self.can_split()),
+        ])]
+    }
+}
+
+/// The dealer draws until 17 or more, and stands on soft 17.
+fn dealer_play(dealer: &mut Vec<Card>, shoe: &mut Shoe) {
+    while hand_value(dealer).0 < 17 {
+        dealer.push(shoe.draw());
+    }
+}
+
+#[derive(Clone, Copy, Display, EnumString)]
+#[strum(serialize_all = "lowercase")]
+enum Action {
+    Hit,
+    Stand,
+    Double,
+    Split,
+}
+impl Action {
+    fn custom_id(self) -> String {
+        format!("blackjack:{self}")
+    }
+}
+
+/// A game that is being played.
+struct OpenGame {
+    game: BlackjackGame,
+    /// Where the bets of the game are held until it is over.
+    held_bet: i32,
+    /// A button press is being handled.
+    busy: bool,
+}
+
+/// Ongoing games by message, and the shoe of each guild.
+#[derive(Default)]
+pub struct BlackjackTables {
+    games: HashMap<MessageId, OpenGame>,
+    shoes: HashMap<u64, Shoe>,
+}
+impl BlackjackTables {
+    /// The shoe of a guild, with a fresh one if it is running low or the number of decks changed.
+    fn shoe(&mut self, guild_id: u64, decks: i16) -> &mut Shoe {
+        let shoe = self
+            .shoes
+            .entry(guild_id)
+            .or_insert_with(|| Shoe::new(decks));
+        if shoe.decks != decks || shoe.needs_shuffle() {
+            *shoe = Shoe::new(decks);
+        }
+        shoe
+    }
+    /// Play out the games that have been left alone, as if the player stood.
+    /// Returns their held bets and payouts.
+    fn finish_abandoned(&mut self) -> Vec<(i32, i64)> {
+        let abandoned: Vec<MessageId> = self
+            .games
+            .iter()
+            .filter(|(_, g)| !g.busy && g.game.started.elapsed() >= GAME_TIMEOUT)
+            .map(|(id, _)| *id)
+            .collect();
+        let mut finished = vec![];
+        for id in abandoned {
+            let OpenGame {
+                mut game, held_bet, ..
+            } = self.games.remove(&id).unwrap();
+            let shoe = self
+                .shoes
+                .entry(game.guild_id)
+                .or_insert_with(|| Shoe::new(game.decks));
+            while !game.is_finished() {
+                game.play(Action::Stand, shoe);
+            }
+            finished.push((held_bet, game.payout()));
+        }
+        finished
+    }
+}
+
+#[command]
+#[bucket = "slots"]
+#[only_in(guilds)]
+#[num_args(1)]
+#[aliases(bj)]
+#[sub_commands(decks)]
+#[description(
+    "Play blackjack against the dealer with the buttons. Blackjack pays 3:2, and the dealer stands on 17.
+    Games left alone for 10 minutes are played out as if you stood.
+    User must be marked mature to get access."
+)]
+#[usage("<amount>")]
+#[example("5")]
+async fn blackjack(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let amount: i64 = args
+        .current()
+        .unwrap()
+        .parse()
+        .map_err(|_| "Invalid amount")?;
+    let gid = msg.guild_id.unwrap().get();
+    let uid = msg.author.id.get();
+    let db = get_db_handler(ctx).await;
+    if !check_can_gamble(ctx, msg).await {
+        return Ok(());
+    }
+    let held_bet = match db
+        .hold_bet(gid, uid, amount, TransactionReason::Blackjack)
+        .await
+    {
+        Ok(id) => id,
+        Err(e) => {
+            let _ = msg.channel_id.say(ctx, e.to_string()).await;
+            return Ok(());
+        }
+    };
+    let decks = get_guild_settings(ctx, gid).await.blackjack_decks;
+    let tables = get_blackjack_tables(ctx).await;
+    let (game, abandoned) = {
+        let mut tables = tables.lock().await;
+        let abandoned = tables.finish_abandoned();
+        let game = BlackjackGame::deal(gid, uid, amount, tables.shoe(gid, decks));
+        (game, abandoned)
+    };
+    for (id, payout) in abandoned {
+        settle(ctx, id, payout).await;
+    }
+    let m = match msg
+        .channel_id
+        .send_message(
+            ctx,
+            CreateMessage::new()
+                .embed(game.embed())
+                .components(game.buttons()),
+        )
+        .await
+    {
+        Ok(m) => m,
+        Err(e) => {
+            // The game can't be played without its message
+            let _ = db.refund_held_bets(Some(&[held_bet])).await;
+            return Err(e.into());
+        }
+    };
+    if game.is_finished() {
+        settle(ctx, held_bet, game.payout()).await;
+    } else {
+        tables.lock().await.games.insert(
+            m.id,
+            OpenGame {
+                game,
+                held_bet,
+                busy: false,
+            },
+        );
+    }
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[num_args(1)]
+#[required_permissions("ADMINISTRATOR")]
+#[description("Set how many decks the blackjack shoe in this server has.")]
+#[usage("<1-8>")]
+#[example("6")]
+async fn decks(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    change_setting(ctx, msg, "blackjack_decks", args.current().unwrap()).await;
+    Ok(())
+}
+
+/// Pay out a finished game, and stop holding its bets.
+async fn settle(ctx: &Context, held_bet: i32, payout: i64) {
+    let db = get_db_handler(ctx).await;
+    if let Err(e) = db.settle_held_bet(held_bet, payout, None).await {
+        warn!("Failed to settle blackjack bet {held_bet}: {e}");
+    }
+}
+
+async fn reply_ephemeral(ctx: &Context, c: &ComponentInteraction, s: impl Into<String>) {
+    let _ = c
+        .create_response(
+            ctx,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(s)
+                    .ephemeral(true),
+            ),
+        )
+        .await;
+}
+
+/// Handle a button press in a blackjack game.
+pub async fn blackjack_component(ctx: &Context, c: &ComponentInteraction) -> CommandResult {
+    let Some(action) = c
+        .data
+        .custom_id
+        .strip_prefix("blackjack:")
+        .and_then(|a| a.parse::<Action>().ok())
+    else {
+        return Ok(());
+    };
+    let tables = get_blackjack_tables(ctx).await;
+    // The lock is not held while waiting on Discord or the database
+    let (held_bet, raise) = {
+        let mut tables = tables.lock().await;
+        let Some(open) = tables.games.get_mut(&c.message.id) else {
+            drop(tables);
+            reply_ephemeral(ctx, c, "This game is over.").await;
+            return Ok(());
+        };
+        if open.game.user_id != c.user.id.get() {
+            drop(tables);
+            reply_ephemeral(ctx, c, "This is not your game 😠").await;
+            return Ok(());
+        }
+        if open.busy {
+            return Ok(());
+        }
+        let game = &open.game;
+        let raise = match action {
+            Action::Double if !game.can_double() => return Ok(()),
+            Action::Split if !game.can_split() => return Ok(()),
+            Action::Double | Action::Split => Some(game.hands[game.active].bet),
+            _ => None,
+        };
+        open.busy = true;
+        (open.held_bet, raise)
+    };
+    if let Some(amount) = raise {
+        let db = get_db_handler(ctx).await;
+        if let Err(e) = db.raise_held_bet(held_bet, amount).await {
+            if let Some(open) = tables.lock().await.games.get_mut(&c.message.id) {
+                open.busy = false;
+            }
+            reply_ephemeral(ctx, c, e.to_string()).await;
+            return Ok(());
+        }
+    }
+    let (embed, buttons, finished) = {
+        let mut tables = tables.lock().await;
+        let Some(mut open) = tables.games.remove(&c.message.id) else {
+            drop(tables);
+            reply_ephemeral(ctx, c, "This game is over.").await;
+            return Ok(());
+        };
+        let shoe = tables.shoe(open.game.guild_id, open.game.decks);
+        open.game.play(action, shoe);
+        let res = (
+            open.game.embed(),
+            open.game.buttons(),
+            open.game.is_finished().then(|| open.game.payout()),
+        );
+        if res.2.is_none() {
+            open.busy = false;
+            tables.games.insert(c.message.id, open);
+        }
+        res
+    };
+    if let Some(payout) = finished {
+        settle(ctx, held_bet, payout).await;
+    }
+    c.create_response(
+        ctx,
+        CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new()
+                .embed(embed)
+                .components(buttons),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(rank: u8) -> Card {
+        Card { rank, suit: 0 }
+    }
+
+    /// A shoe that deals `ranks` in order.
+    fn shoe(ranks: &[u8]) -> Shoe {
+        Shoe {
+            decks: 1,
+            cards: ranks.iter().rev().map(|r| card(*r)).collect(),
+        }
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(hand_value(&[card(1), card(13)]), (21, true));
+        assert_eq!(hand_value(&[card(1), card(1), card(9)]), (21, true));
+        assert_eq!(hand_value(&[card(1), card(6), card(10)]), (17, false));
+        assert_eq!(hand_value(&[card(12), card(6), card(10)]), (26, false));
+    }
+
+    #[test]
+    fn full_shoe() {
+        let s = Shoe::new(6);
+        assert_eq!(s.cards.len(), 6 * 52);
+        assert_eq!(s.cards.iter().filter(|c| c.rank == 1).count(), 6 * 4);
+        assert!(!s.needs_shuffle());
+    }
+
+    #[test]
+    fn dealer_stands_on_soft_17() {
+        let mut dealer = vec![card(1), card(6)];
+        dealer_play(&mut dealer, &mut shoe(&[5]));
+        assert_eq!(dealer.len(), 2);
+        let mut dealer = vec![card(10), card(6)];
+        dealer_play(&mut dealer, &mut shoe(&[1]));
+        assert_eq!(hand_value(&dealer).0, 17);
+    }
+
+    #[test]
+    fn blackjack_pays_3_to_2() {
+        // Player: A, K. Dealer: 9, 7
+        let game = BlackjackGame::deal(1, 1, 10, &mut shoe(&[1, 9, 13, 7]));
+        assert!(game.is_finished());
+        assert_eq!(game.payout(), 25);
+        // Both have blackjack
+        let game = BlackjackGame::deal(1, 1, 10, &mut shoe(&[1, 1, 13, 13]));
+        assert_eq!(game.payout(), 10);
+    }
+
+    #[test]
+    fn hit_and_stand() {
+        // Player: 10, 6. Dealer: 9, 7, then draws 5
+        let mut s = shoe(&[10, 9, 6, 7, 4, 5]);
+        let mut game = BlackjackGame::deal(1, 1, 10, &mut s);
+        game.play(Action::Hit, &mut s);
+        assert_eq!(game.hands[0].value(), 20);
+        game.play(Action::Stand, &mut s);
+        assert!(game.is_finished());
+        assert_eq!(hand_value(&game.dealer).0, 21);
+        assert_eq!(game.payout(), 0);
+    }
+
+    #[test]
+    fn double_and_bust() {
+        // Player: 6, 5, doubles into 10. Dealer: 10, 7
+        let mut s = shoe(&[6, 10, 5, 7, 10]);
+        let mut game = BlackjackGame::deal(1, 1, 10, &mut s);
+        game.play(Action::Double, &mut s);
+        assert!(game.is_finished());
+        assert_eq!(game.payout(), 40);
+        // Player: 10, 6, hits into 10
+        let mut s = shoe(&[10, 10, 6, 7, 10]);
+        let mut game = BlackjackGame::deal(1, 1, 10, &mut s);
+        game.play(Action::Hit, &mut s);
+        assert!(game.is_finished());
+        assert_eq!(game.dealer.len(), 2);
+        assert_eq!(game.payout(), 0);
+    }
+
+    #[test]
+    fn split() {
+        // Player: 8, 8, gets 3 and 10. Dealer: 10, 8
+        let mut s = shoe(&[8, 10, 8, 8, 3, 10]);
+        let mut game = BlackjackGame::deal(1, 1, 10, &mut s);
+        assert!(game.can_split());
+        game.play(Action::Split, &mut s);
+        assert_eq!(game.hands.len(), 2);
+        assert_eq!(game.hands[0].value(), 11);
+        game.play(Action::Stand, &mut s);
+        game.play(Action::Stand, &mut s);
+        assert!(game.is_finished());
+        // 11 loses, 18 pushes
+        assert_eq!(game.payout(), 10);
+    }
+}
@@ -0,0 +1,747 @@
+use chrono_tz::Tz;
+use serenity::{
+    builder::{CreateEmbed, CreateMessage},
+    client::Context,
+    framework::standard::{
+        macros::{command, group},
+        Args, CommandResult,
+    },
+    model::prelude::{ChannelId, Message},
+};
+
+use super::{
+    ai_voice::TTS_VOICES,
+    spells::{gamba_size, shoppable_spells, CONFIGURABLE_SPELLS, GAMBA_SIZES},
+};
+use crate::{
+    discord::{get_guild_settings, get_prefix, get_scheduler, set_guild_settings, GROUPS},
+    model::{GuildSettings, SpellSettings, LOCALES},
+    scheduler::SchedulerMsg,
+    services::log_to_guild,
+};
+
+#[group]
+#[commands(config)]
+struct Config;
+
+const MAX_SPELL_PRICE: i64 = 1000;
+const MAX_PREFIX_LENGTH: usize = 5;
+const MAX_PERSONA_LENGTH: usize = 1000;
+const MAX_ECONOMY_AMOUNT: i64 = 1000;
+const MAX_DECKS: i16 = 8;
+const MAX_JACKPOT_SHARE: i16 = 50;
+const MAX_DAILY_REWARD: i16 = 100;
+const MAX_DAILY_STREAK: i16 = 30;
+const MAX_ACTIVITY_RATE: i16 = 100;
+
+/// The settings that can be changed with `config set`, and what they do.
+static SETTINGS: &[(&str, &str)] = &[
+    ("prefix", "The command prefix, up to 5 characters."),
+    (
+        "disabled_groups",
+        "Command groups that are turned off, separated by commas, or `none`.",
+    ),
+    (
+        "react_to_name",
+        "If messages that say Wallace get a reaction, `on` or `off`.",
+    ),
+    (
+        "ai_persona",
+        "Who Wallace is in AI chat, or `none` for the default.",
+    ),
+    ("tts_voice", "The voice used for text to speech."),
+    ("weekly_payout", "𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻 paid to everyone each week."),
+    ("credit_limit", "How much a user can owe the bank."),
+    (
+        "log_channel",
+        "Channel where config changes, bonks and server renames are posted, or `none`.",
+    ),
+    ("locale", "The language Wallace speaks in AI chat."),
+    (
+        "timezone",
+        "The timezone that tasks and reminders follow, or `none` for UTC.",
+    ),
+    ("blackjack_decks", "How many decks the blackjack shoe has."),
+    (
+        "jackpot_share",
+        "Percent of lost bets that go into the jackpot.",
+    ),
+    (
+        "daily_base",
+        "The daily reward on the first day of a streak.",
+    ),
+    (
+        "daily_bonus",
+        "Added to the daily reward for each day of the streak.",
+    ),
+    (
+        "daily_max_streak",
+        "The streak stops growing the daily reward after this many days.",
+    ),
+    (
+        "activity_message_reward",
+        "Hundredths of a 𝓚𝓪𝓹𝓼𝔂𝓵 earned per message.",
+    ),
+    (
+        "activity_voice_reward",
+        "Hundredths of a 𝓚𝓪𝓹𝓼𝔂𝓵 earned per minute in voice.",
+    ),
+];
+
+#[command]
+#[only_in(guilds)]
+#[sub_commands(get, set, list, reset, spells)]
+#[required_permissions("ADMINISTRATOR")]
+#[description("Configure Wallace in this server.")]
+async fn config(ctx: &Context, msg: &Message) -> CommandResult {
+    let prefix = get_prefix(ctx, msg.guild_id).await;
+    let _ = msg
+        .channel_id
+        .say(ctx, format!("See `{prefix}help config`."))
+        .await;
+    Ok(())
+}
+
+/// Find a spell that can be configured by name.
+fn find_spell(name: &str) -> Result<&'static str, String> {
+    CONFIGURABLE_SPELLS
+        .iter()
+        .map(|c| c.options.names[0])
+        .find(|n| n.eq_ignore_ascii_case(name))
+        .ok_or_else(|| {
+            format!(
+                "No such spell. Spells are: {}",
+                CONFIGURABLE_SPELLS
+                    .iter()
+                    .map(|c| c.options.names[0])
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })
+}
+
+fn set_spell_price(settings: &mut SpellSettings, spell: &str, price: i64) -> Result<(), String> {
+    let (cost, min) = match find_spell(spell)? {
+        "gamba" => (&mut settings.gamba_min_bet, 1),
+        "unbonk" => (&mut settings.unbonk_cost, 0),
+        "nickname" => (&mut settings.nickname_cost, 0),
+        "servername" => (&mut settings.servername_cost, 0),
+        s => return Err(format!("The {s} spell is always free")),
+    };
+    if !(min..=MAX_SPELL_PRICE).contains(&price) {
+        return Err(format!("The price can be {min} to {MAX_SPELL_PRICE}."));
+    }
+    *cost = price;
+    Ok(())
+}
+
+fn set_spell_enabled(
+    settings: &mut SpellSettings,
+    spell: &str,
+    enabled: bool,
+) -> Result<(), String> {
+    let spell = find_spell(spell)?;
+    settings.disabled_spells.retain(|s| s != spell);
+    if !enabled {
+        settings.disabled_spells.push(spell.to_owned());
+    }
+    Ok(())
+}
+
+fn set_max_gamba_size(settings: &mut SpellSettings, size: &str) -> Result<(), String> {
+    let i = gamba_size(size).ok_or("No such size")?;
+    settings.max_gamba_size = GAMBA_SIZES[i].0.to_owned();
+    Ok(())
+}
+
+fn find_setting(key: &str) -> Result<&'static str, String> {
+    SETTINGS
+        .iter()
+        .map(|(k, _)| *k)
+        .find(|k| k.eq_ignore_ascii_case(key))
+        .ok_or_else(|| {
+            format!(
+                "No such setting. Settings are: {}",
+                SETTINGS
+                    .iter()
+                    .map(|(k, _)| *k)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })
+}
+
+fn show_setting(settings: &GuildSettings, key: &str) -> String {
+    let none = || "None".to_owned();
+    match key {
+        "prefix" => format!("`{}`", settings.prefix),
+        "disabled_groups" if settings.disabled_groups.is_empty() => none(),
+        "disabled_groups" => settings.disabled_groups.join(", "),
+        "react_to_name" => (if settings.react_to_name { "On" } else { "Off" }).to_owned(),
+        "ai_persona" => settings
+            .ai_persona
+            .clone()
+            .unwrap_or_else(|| "Default".to_owned()),
+        "tts_voice" => settings.tts_voice.clone(),
+        "weekly_payout" => format!("{} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻", settings.weekly_payout),
+        "credit_limit" => format!("{} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻", settings.credit_limit),
+        "log_channel" => settings
+            .log_channel_id
+            .map_or_else(none, |c| format!("<#{c}>")),
+        "locale" => format!("{} ({})", settings.locale, settings.language()),
+        "timezone" => settings
+            .timezone
+            .clone()
+            .unwrap_or_else(|| "UTC".to_owned()),
+        "blackjack_decks" => settings.blackjack_decks.to_string(),
+        "jackpot_share" => format!("{}%", settings.jackpot_share),
+        "daily_base" => format!("{} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻", settings.daily.base),
+        "daily_bonus" => format!("{} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻", settings.daily.bonus),
+        "daily_max_streak" => format!("{} days", settings.daily.max_streak),
+        "activity_message_reward" => {
+            format!("{:.2} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻", settings.activity.message as f64 / 100.0)
+        }
+        "activity_voice_reward" => {
+            format!("{:.2} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻", settings.activity.voice as f64 / 100.0)
+        }
+        _ => unreachable!("unknown setting"),
+    }
+}
+
+/// `none` clears settings that can be empty.
+fn is_none(value: &str) -> bool {
+    value.eq_ignore_ascii_case("none")
+}
+
+/// Check and change a setting. All settings are changed through here.
+pub(super) fn set_setting(
+    settings: &mut GuildSettings,
+    key: &str,
+    value: &str,
+) -> Result<(), String> {
+    let value = value.trim();
+    let amount = || match value.parse::<i64>() {
+        Ok(a) if (0..=MAX_ECONOMY_AMOUNT).contains(&a) => Ok(a),
+        _ => Err(format!("The amount can be 0 to {MAX_ECONOMY_AMOUNT}.")),
+    };
+    let small = |min: i16, max: i16, what: &str| match value.parse::<i16>() {
+        Ok(n) if (min..=max).contains(&n) => Ok(n),
+        _ => Err(format!("The {what} can be {min} to {max}.")),
+    };
+    match find_setting(key)? {
+        "prefix" => {
+            if value.is_empty()
+                || value.chars().count() > MAX_PREFIX_LENGTH
+                || value.contains(char::is_whitespace)
+                || value.starts_with('<')
+                || value.contains('`')
+            {
+                return Err(format!(
+                    "The prefix must be 1 to {MAX_PREFIX_LENGTH} characters, without spaces, and can't start with `<`."
+                ));
+            }
+            // Commands are matched case insensitively
+            settings.prefix = value.to_lowercase();
+        }
+        "disabled_groups" => {
+            let mut groups = vec![];
+            if !is_none(value) {
+                for name in value.split(',').map(str::trim) {
+                    let g = GROUPS
+                        .iter()
+                        .find(|g| g.name.eq_ignore_ascii_case(name))
+                        .ok_or_else(|| {
+                            format!(
+                                "No such group. Groups are: {}",
+                                GROUPS.iter().map(|g| g.name).collect::<Vec<_>>().join(", ")
+                            )
+                        })?;
+                    if *g == &CONFIG_GROUP {
+                        return Err("Config can't be turned off.".into());
+                    }
+                    if !groups.contains(&g.name.to_owned()) {
+                        groups.push(g.name.to_owned());
+                    }
+                }
+            }
+            settings.disabled_groups = groups;
+        }
+        "react_to_name" => {
+            settings.react_to_name = match value.to_lowercase().as_str() {
+                "on" | "true" | "yes" => true,
+                "off" | "false" | "no" => false,
+                _ => return Err("Use `on` or `off`.".into()),
+            };
+        }
+        "ai_persona" => {
+            if value.chars().count() > MAX_PERSONA_LENGTH {
+                return Err(format!(
+                    "The persona can be at most {MAX_PERSONA_LENGTH} characters."
+                ));
+            }
+            settings.ai_persona = (!value.is_empty() && !is_none(value)).then(|| value.to_owned());
+        }
+        "tts_voice" => {
+            let (voice, _) = TTS_VOICES
+                .iter()
+                .find(|(v, _)| v.eq_ignore_ascii_case(value))
+                .ok_or_else(|| {
+                    format!(
+                        "No such voice. Voices are: {}",
+                        TTS_VOICES
+                            .iter()
+                            .map(|(v, _)| *v)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                })?;
+            settings.tts_voice = (*voice).to_owned();
+        }
+        "weekly_payout" => settings.weekly_payout = amount()?,
+        "credit_limit" => settings.credit_limit = amount()?,
+        "log_channel" => {
+            settings.log_channel_id = if is_none(value) {
+                None
+            } else {
+                let id = value
+                    .strip_prefix("<#")
+                    .and_then(|v| v.strip_suffix('>'))
+                    .unwrap_or(value);
+                Some(
+                    id.parse::<u64>()
+                        .ok()
+                        .and_then(|id| i64::try_from(id).ok())
+                        .ok_or("Mention a channel, or use `none`.")?,
+                )
+            };
+        }
+        "locale" => {
+            let (locale, _) = LOCALES
+                .iter()
+                .find(|(l, _)| l.eq_ignore_ascii_case(value))
+                .ok_or_else(|| {
+                    format!(
+                        "No such locale. Locales are: {}",
+                        LOCALES
+                            .iter()
+                            .map(|(l, _)| *l)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                })?;
+            settings.locale = (*locale).to_owned();
+        }
+        "timezone" => {
+            settings.timezone = if is_none(value) {
+                None
+            } else {
+                let tz = value
+                    .parse::<Tz>()
+                    .map_err(|_| "Unknown timezone. Use a name like `Europe/Stockholm`.")?;
+                Some(tz.name().to_owned())
+            };
+        }
+        "blackjack_decks" => settings.blackjack_decks = small(1, MAX_DECKS, "number of decks")?,
+        "jackpot_share" => settings.jackpot_share = small(0, MAX_JACKPOT_SHARE, "share")?,
+        "daily_base" => settings.daily.base = small(0, MAX_DAILY_REWARD, "reward")?,
+        "daily_bonus" => settings.daily.bonus = small(0, MAX_DAILY_REWARD, "bonus")?,
+        "daily_max_streak" => {
+            settings.daily.max_streak = small(1, MAX_DAILY_STREAK, "max streak")?;
+        }
+        "activity_message_reward" => {
+            settings.activity.message = small(0, MAX_ACTIVITY_RATE, "reward")?;
+        }
+        "activity_voice_reward" => {
+            settings.activity.voice = small(0, MAX_ACTIVITY_RATE, "reward")?;
+        }
+        _ => unreachable!("unknown setting"),
+    }
+    Ok(())
+}
+
+pub(super) fn reset_setting(settings: &mut GuildSettings, key: &str) -> Result<(), String> {
+    let d = GuildSettings::default();
+    match find_setting(key)? {
+        "prefix" => settings.prefix = d.prefix,
+        "disabled_groups" => settings.disabled_groups = d.disabled_groups,
+        "react_to_name" => settings.react_to_name = d.react_to_name,
+        "ai_persona" => settings.ai_persona = d.ai_persona,
+        "tts_voice" => settings.tts_voice = d.tts_voice,
+        "weekly_payout" => settings.weekly_payout = d.weekly_payout,
+        "credit_limit" => settings.credit_limit = d.credit_limit,
+        "log_channel" => settings.log_channel_id = d.log_channel_id,
+        "locale" => settings.locale = d.locale,
+        "timezone" => settings.timezone = d.timezone,
+        "blackjack_decks" => settings.blackjack_decks = d.blackjack_decks,
+        "jackpot_share" => settings.jackpot_share = d.jackpot_share,
+        "daily_base" => settings.daily.base = d.daily.base,
+        "daily_bonus" => settings.daily.bonus = d.daily.bonus,
+        "daily_max_streak" => settings.daily.max_streak = d.daily.max_streak,
+        "activity_message_reward" => settings.activity.message = d.activity.message,
+        "activity_voice_reward" => settings.activity.voice = d.activity.voice,
+        _ => unreachable!("unknown setting"),
+    }
+    Ok(())
+}
+
+/// Change the settings of the server with `f`, save them if it succeeds, and log the `change`.
+/// Returns if they were saved, otherwise the author was told what went wrong.
+pub(super) async fn update_guild_settings(
+    ctx: &Context,
+    msg: &Message,
+    f: impl FnOnce(&mut GuildSettings) -> Result<(), String>,
+    change: impl FnOnce(&GuildSettings) -> String,
+) -> bool {
+    let gid = msg.guild_id.unwrap().get();
+    let old = get_guild_settings(ctx, gid).await;
+    let mut settings = (*old).clone();
+    if let Err(e) = f(&mut settings) {
+        let _ = msg.channel_id.say(ctx, e).await;
+        return false;
+    }
+    if let Err(e) = set_guild_settings(ctx, gid, &settings).await {
+        let _ = msg.channel_id.say(ctx, e.to_string()).await;
+        return false;
+    }
+    // Restart the tasks in the new timezone
+    if settings.timezone != old.timezone {
+        get_scheduler(ctx)
+            .await
+            .send(SchedulerMsg::ReloadAll)
+            .expect("scheduler to be running");
+    }
+    let _ = msg.react(ctx, '🫡').await;
+    log_to_guild(
+        ctx,
+        gid,
+        format!("{} {}", msg.author.name, change(&settings)),
+    )
+    .await;
+    true
+}
+
+/// Change a setting of the server, like `config set`.
+pub(super) async fn change_setting(ctx: &Context, msg: &Message, key: &str, value: &str) -> bool {
+    update_guild_settings(
+        ctx,
+        msg,
+        |s| {
+            set_setting(s, key, value)?;
+            match s.log_channel_id {
+                Some(cid)
+                    if find_setting(key) == Ok("log_channel")
+                        && !msg.guild(&ctx.cache).is_some_and(|g| {
+                            g.channels.contains_key(&ChannelId::new(cid as u64))
+                        }) =>
+                {
+                    Err("That channel is not in this server.".into())
+                }
+                _ => Ok(()),
+            }
+        },
+        |s| {
+            let key = find_setting(key).unwrap();
+            format!("set {key} to {}", show_setting(s, key))
+        },
+    )
+    .await
+}
+
+#[command]
+#[only_in(guilds)]
+#[num_args(1)]
+#[required_permissions("ADMINISTRATOR")]
+#[description("Show a setting of this server.")]
+#[usage("<setting>")]
+#[example("prefix")]
+async fn get(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let key = match find_setting(args.current().unwrap()) {
+        Ok(k) => k,
+        Err(e) => {
+            let _ = msg.channel_id.say(ctx, e).await;
+            return Ok(());
+        }
+    };
+    let settings = get_guild_settings(ctx, msg.guild_id.unwrap().get()).await;
+    msg.channel_id
+        .say(ctx, format!("{key}: {}", show_setting(&settings, key)))
+        .await?;
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[min_args(2)]
+#[required_permissions("ADMINISTRATOR")]
+#[description(
+    "Change a setting of this server. See the settings with `config list`.
+    Groups that can be turned off are: General, AI and Voice, Bank, Items, Spells, Emote, Cool Text, Scheduling, LoL, TFT."
+)]
+#[usage("<setting> <value>")]
+#[example("prefix w!")]
+#[example("disabled_groups LoL, TFT")]
+#[example("log_channel #wallace-log")]
+#[example("tts_voice nova")]
+async fn set(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let key = args.single::<String>().unwrap();
+    change_setting(ctx, msg, &key, args.rest()).await;
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[max_args(1)]
+#[required_permissions("ADMINISTRATOR")]
+#[description("Change a setting of this server back to the default, or all of them.")]
+#[usage("[setting]")]
+#[example("prefix")]
+#[example("")]
+async fn reset(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let key = args.current();
+    update_guild_settings(
+        ctx,
+        msg,
+        |s| match key {
+            Some(k) => reset_setting(s, k),
+            None => {
+                *s = GuildSettings::default();
+                Ok(())
+            }
+        },
+        |_| match key.map(find_setting) {
+            Some(Ok(k)) => format!("reset {k}"),
+            _ => "reset all settings".to_owned(),
+        },
+    )
+    .await;
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[required_permissions("ADMINISTRATOR")]
+#[description("Show all settings of this server.")]
+async fn list(ctx: &Context, msg: &Message) -> CommandResult {
+    let settings = get_guild_settings(ctx, msg.guild_id.unwrap().get()).await;
+    let embed = SETTINGS.iter().fold(
+        CreateEmbed::new().title("Server settings"),
+        |e, (key, description)| {
+            e.field(
+                *key,
+                format!(
+                    "{}\n-# {description}",
+                    // Embed fields are limited to 1024 characters
+                    show_setting(&settings, key)
+                        .chars()
+                        .take(900)
+                        .collect::<String>()
+                ),
+                false,
+            )
+        },
+    );
+    msg.channel_id
+        .send_message(ctx, CreateMessage::new().add_embed(embed))
+        .await?;
+    Ok(())
+}
+
+/// Change the spell settings of the server with `f`.
+async fn update_spell_settings(
+    ctx: &Context,
+    msg: &Message,
+    f: impl FnOnce(&mut SpellSettings) -> Result<(), String>,
+) -> CommandResult {
+    update_guild_settings(
+        ctx,
+        msg,
+        |s| f(&mut s.spells),
+        |_| format!("changed the spell settings: {}", msg.content),
+    )
+    .await;
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[sub_commands(price, disable, enable, gambasize)]
+#[description("Show the spell prices and restrictions in this server.")]
+async fn spells(ctx: &Context, msg: &Message) -> CommandResult {
+    let guild_settings = get_guild_settings(ctx, msg.guild_id.unwrap().get()).await;
+    let (prefix, settings) = (&guild_settings.prefix, &guild_settings.spells);
+    let prices = shoppable_spells(settings)
+        .iter()
+        .map(|(c, p)| format!("`{prefix}{}` {p}", c.options.names[0]))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let disabled = if settings.disabled_spells.is_empty() {
+        "None".to_owned()
+    } else {
+        settings
+            .disabled_spells
+            .iter()
+            .map(|s| format!("`{prefix}{s}`"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    msg.channel_id
+        .send_message(
+            ctx,
+            CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .title("Spell settings")
+                    .field("Prices", prices, true)
+                    .field("Disabled", disabled, true)
+                    .field("Biggest gamba size", &settings.max_gamba_size, true),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[num_args(2)]
+#[required_permissions("ADMINISTRATOR")]
+#[description(
+    "Set the price of a spell in this server. For gamba, this is the smallest bet. Other spells can be made free with 0."
+)]
+#[usage("<spell> <price>")]
+#[example("servername 10")]
+#[example("unbonk 0")]
+async fn price(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let spell = args.single::<String>().unwrap();
+    let Ok(price) = args.single::<i64>() else {
+        let _ = msg.channel_id.say(ctx, "Invalid price").await;
+        return Ok(());
+    };
+    update_spell_settings(ctx, msg, |s| set_spell_price(s, &spell, price)).await
+}
+
+#[command]
+#[only_in(guilds)]
+#[num_args(1)]
+#[required_permissions("ADMINISTRATOR")]
+#[description("Stop a spell from being cast in this server.")]
+#[usage("<spell>")]
+#[example("servername")]
+async fn disable(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let spell = args.current().unwrap().to_owned();
+    update_spell_settings(ctx, msg, |s| set_spell_enabled(s, &spell, false)).await
+}
+
+#[command]
+#[only_in(guilds)]
+#[num_args(1)]
+#[required_permissions("ADMINISTRATOR")]
+#[description("Allow a disabled spell to be cast again.")]
+#[usage("<spell>")]
+#[example("servername")]
+async fn enable(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let spell = args.current().unwrap().to_owned();
+    update_spell_settings(ctx, msg, |s| set_spell_enabled(s, &spell, true)).await
+}
+
+#[command]
+#[only_in(guilds)]
+#[num_args(1)]
+#[required_permissions("ADMINISTRATOR")]
+#[description("Set the biggest gamba size allowed in this server.")]
+#[usage("<S|M|L|XL|XXL>")]
+#[example("L")]
+async fn gambasize(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let size = args.current().unwrap().to_owned();
+    update_spell_settings(ctx, msg, |s| set_max_gamba_size(s, &size)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spell_settings() {
+        let mut s = SpellSettings::default();
+        assert!(set_spell_price(&mut s, "ServerName", 10).is_ok());
+        assert!(set_spell_price(&mut s, "unbonk", 0).is_ok());
+        assert!(set_spell_price(&mut s, "gamba", 0).is_err());
+        assert!(set_spell_price(&mut s, "defaultname", 5).is_err());
+        assert!(set_spell_price(&mut s, "nickname", MAX_SPELL_PRICE + 1).is_err());
+        assert!(set_spell_price(&mut s, "bonk", 5).is_err());
+        assert_eq!((s.servername_cost, s.unbonk_cost), (10, 0));
+
+        assert!(set_spell_enabled(&mut s, "servername", false).is_ok());
+        assert!(set_spell_enabled(&mut s, "servername", false).is_ok());
+        assert_eq!(s.disabled_spells, ["servername"]);
+        assert!(s.is_disabled("SERVERNAME"));
+        assert!(set_spell_enabled(&mut s, "servername", true).is_ok());
+        assert!(!s.is_disabled("servername"));
+
+        assert!(set_max_gamba_size(&mut s, "xl").is_ok());
+        assert_eq!(s.max_gamba_size, "XL");
+        assert!(set_max_gamba_size(&mut s, "XXXL").is_err());
+    }
+
+    #[test]
+    fn guild_settings() {
+        let mut s = GuildSettings::default();
+        assert!(set_setting(&mut s, "Prefix", "W!").is_ok());
+        assert_eq!(s.prefix, "w!");
+        assert!(set_setting(&mut s, "prefix", "").is_err());
+        assert!(set_setting(&mut s, "prefix", "toolong").is_err());
+        assert!(set_setting(&mut s, "prefix", "w !").is_err());
+        assert!(set_setting(&mut s, "prefix", "<@").is_err());
+
+        assert!(set_setting(&mut s, "disabled_groups", "lol, TFT, LoL").is_ok());
+        assert_eq!(s.disabled_groups, ["LoL", "TFT"]);
+        assert!(set_setting(&mut s, "disabled_groups", "config").is_err());
+        assert!(set_setting(&mut s, "disabled_groups", "nope").is_err());
+        assert_eq!(s.disabled_groups, ["LoL", "TFT"]);
+        assert!(set_setting(&mut s, "disabled_groups", "none").is_ok());
+        assert!(s.disabled_groups.is_empty());
+
+        assert!(set_setting(&mut s, "react_to_name", "off").is_ok());
+        assert!(!s.react_to_name);
+        assert!(set_setting(&mut s, "react_to_name", "maybe").is_err());
+        assert!(set_setting(&mut s, "ai_persona", "You are a crab.").is_ok());
+        assert_eq!(s.ai_persona.as_deref(), Some("You are a crab."));
+        assert!(set_setting(&mut s, "ai_persona", &"a".repeat(1001)).is_err());
+        assert!(set_setting(&mut s, "tts_voice", "Nova").is_ok());
+        assert_eq!(s.tts_voice, "nova");
+        assert!(set_setting(&mut s, "tts_voice", "robot").is_err());
+        assert!(set_setting(&mut s, "weekly_payout", "0").is_ok());
+        assert!(set_setting(&mut s, "credit_limit", "-1").is_err());
+        assert!(set_setting(&mut s, "credit_limit", "1001").is_err());
+        assert!(set_setting(&mut s, "log_channel", "<#123>").is_ok());
+        assert_eq!(s.log_channel_id, Some(123));
+        assert!(set_setting(&mut s, "log_channel", "#general").is_err());
+        assert!(set_setting(&mut s, "locale", "SV").is_ok());
+        assert_eq!(s.language(), "Swedish");
+        assert!(set_setting(&mut s, "locale", "xx").is_err());
+        assert!(set_setting(&mut s, "timezone", "Europe/Stockholm").is_ok());
+        assert_eq!(s.timezone.as_deref(), Some("Europe/Stockholm"));
+        assert!(set_setting(&mut s, "timezone", "Mars/Olympus").is_err());
+        assert!(set_setting(&mut s, "timezone", "none").is_ok());
+        assert_eq!(s.timezone, None);
+        assert!(set_setting(&mut s, "blackjack_decks", "8").is_ok());
+        assert!(set_setting(&mut s, "blackjack_decks", "0").is_err());
+        assert!(set_setting(&mut s, "jackpot_share", "51").is_err());
+        assert!(set_setting(&mut s, "daily_max_streak", "0").is_err());
+        assert!(set_setting(&mut s, "daily_bonus", "3").is_ok());
+        assert!(set_setting(&mut s, "activity_voice_reward", "100").is_ok());
+        assert!(set_setting(&mut s, "activity_message_reward", "101").is_err());
+        assert_eq!(
+            (s.blackjack_decks, s.daily.bonus, s.activity.voice),
+            (8, 3, 100)
+        );
+        assert!(set_setting(&mut s, "volume", "11").is_err());
+
+        assert!(reset_setting(&mut s, "ai_persona").is_ok());
+        assert!(reset_setting(&mut s, "log_channel").is_ok());
+        assert_eq!((s.ai_persona.clone(), s.log_channel_id), (None, None));
+        for (key, _) in SETTINGS {
+            assert!(reset_setting(&mut s, key).is_ok());
+            assert!(!show_setting(&s, key).is_empty());
+        }
+        assert_eq!(s, GuildSettings::default());
+    }
+}
@@ -0,0 +1,98 @@
+use serenity::{
+    builder::{CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter, CreateMessage},
+    client::Context,
+    framework::standard::{macros::command, Args, CommandResult},
+    model::prelude::Message,
+};
+
+use super::config::{set_setting, update_guild_settings};
+use crate::{
+    database::WallaceDBClient,
+    discord::{get_db_handler, get_guild_settings},
+    model::DailyClaim,
+};
+
+#[command]
+#[only_in(guilds)]
+#[sub_commands(curve)]
+#[description(
+    "Claim your daily 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻. Claim every day to build a streak for a bigger reward, but miss a day and the streak is lost."
+)]
+async fn daily(ctx: &Context, msg: &Message) -> CommandResult {
+    let gid = msg.guild_id.unwrap().get();
+    let db = get_db_handler(ctx).await;
+    let curve = get_guild_settings(ctx, gid).await.daily;
+    let (streak, reward) = match db.claim_daily(gid, msg.author.id.get(), &curve).await {
+        Ok(DailyClaim::Claimed { streak, reward }) => (streak, reward),
+        Ok(DailyClaim::Cooldown(next)) => {
+            let _ = msg
+                .channel_id
+                .say(
+                    ctx,
+                    format!("Already claimed! Come back <t:{}:R>.", next.timestamp()),
+                )
+                .await;
+            return Ok(());
+        }
+        Err(e) => {
+            let _ = msg.channel_id.say(ctx, e.to_string()).await;
+            return Ok(());
+        }
+    };
+    let _ = msg
+        .channel_id
+        .send_message(
+            ctx,
+            CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .author(
+                        CreateEmbedAuthor::new(format!("+{reward} 𝓚"))
+                            .icon_url("https://cdn.7tv.app/emote/60edf43ba60faa2a91cfb082/1x.gif"),
+                    )
+                    .title(format!(
+                        "🔥 {streak} day{} streak",
+                        if streak == 1 { "" } else { "s" }
+                    ))
+                    .footer(CreateEmbedFooter::new(format!(
+                        "Tomorrow: {} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻",
+                        curve.reward(streak + 1)
+                    ))),
+            ),
+        )
+        .await;
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[num_args(3)]
+#[required_permissions("ADMINISTRATOR")]
+#[description(
+    "Set the daily reward in this server: the reward on the first day, what each day of the streak adds, and after how many days the streak stops adding."
+)]
+#[usage("<base> <bonus> <max streak>")]
+#[example("1 1 7")]
+async fn curve(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let (base, bonus, max_streak) = (
+        args.single::<String>().unwrap(),
+        args.single::<String>().unwrap(),
+        args.single::<String>().unwrap(),
+    );
+    update_guild_settings(
+        ctx,
+        msg,
+        |s| {
+            set_setting(s, "daily_base", &base)?;
+            set_setting(s, "daily_bonus", &bonus)?;
+            set_setting(s, "daily_max_streak", &max_streak)
+        },
+        |s| {
+            format!(
+                "set the daily curve to {} {} {}",
+                s.daily.base, s.daily.bonus, s.daily.max_streak
+            )
+        },
+    )
+    .await;
+    Ok(())
+}
@@ -0,0 +1,342 @@
+use std::fmt::Write;
+
+use rand::{rngs::StdRng, Rng, RngCore, SeedableRng};
+use serenity::{
+    builder::{CreateEmbed, CreateEmbedAuthor, CreateMessage},
+    client::Context,
+    framework::standard::{macros::command, Args, CommandResult},
+    model::prelude::Message,
+};
+use sha2::{Digest, Sha256};
+
+use super::{
+    roulette::replay_roulette,
+    slots::{replay_slots, SlotRules},
+    spells::replay_gamba,
+};
+use crate::{
+    database::WallaceDBClient,
+    discord::{get_db_handler, get_prefix},
+};
+
+/// Random bytes for a spin, made from SHA-256 hashes of
+/// `<server seed>:<client seed>:<nonce>:<round>` for rounds 0, 1, 2...
+pub struct FairRng {
+    key: String,
+    round: u64,
+    block: [u8; 32],
+    pos: usize,
+}
+impl FairRng {
+    pub fn new(server_seed: &str, client_seed: &str, nonce: i32) -> Self {
+        Self {
+            key: format!("{server_seed}:{client_seed}:{nonce}"),
+            round: 0,
+            block: [0; 32],
+            pos: 32,
+        }
+    }
+}
+impl RngCore for FairRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut b = [0; 4];
+        self.fill_bytes(&mut b);
+        u32::from_le_bytes(b)
+    }
+    fn next_u64(&mut self) -> u64 {
+        let mut b = [0; 8];
+        self.fill_bytes(&mut b);
+        u64::from_le_bytes(b)
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for d in dest {
+            if self.pos == self.block.len() {
+                self.block = Sha256::digest(format!("{}:{}", self.key, self.round)).into();
+                self.round += 1;
+                self.pos = 0;
+            }
+            *d = self.block[self.pos];
+            self.pos += 1;
+        }
+    }
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// The hash of a server seed, that is shown before the seed is revealed.
+pub fn hash_seed(server_seed: &str) -> String {
+    hex::encode(Sha256::digest(server_seed))
+}
+
+fn new_seeds() -> (String, String) {
+    let mut rng: StdRng = SeedableRng::from_entropy();
+    (
+        hex::encode(rng.gen::<[u8; 32]>()),
+        hex::encode(rng.gen::<[u8; 8]>()),
+    )
+}
+
+/// Recompute the outcome of a spin of `game`.
+/// Spins of `slots <table>` need the rules of that slot table.
+fn replay(game: &str, rng: &mut FairRng, rules: Option<&SlotRules>) -> Option<String> {
+    let mut parts = game.split(' ');
+    match (parts.next()?, parts.next()) {
+        ("slots", None) => Some(replay_slots(&SlotRules::default(), rng)),
+        ("slots", Some(_)) => Some(replay_slots(rules?, rng)),
+        ("roulette", None) => Some(replay_roulette(rng)),
+        ("gamba", Some(chance)) => {
+            let chance = chance.parse().ok().filter(|c| *c <= 100)?;
+            Some(replay_gamba(rng, chance))
+        }
+        _ => None,
+    }
+}
+
+/// A spin of a game, rolled with the seeds of a user.
+pub struct Spin {
+    id: i32,
+    pub rng: FairRng,
+}
+
+/// Start a spin of `game` for a user. `game` includes any parameters that affect the outcome.
+pub async fn fair_spin(ctx: &Context, uid: u64, game: String) -> anyhow::Result<Spin> {
+    let db = get_db_handler(ctx).await;
+    let (server_seed, client_seed) = new_seeds();
+    let spin = db
+        .create_fair_spin(uid, game, server_seed, client_seed)
+        .await?;
+    Ok(Spin {
+        id: spin.id,
+        rng: FairRng::new(&spin.server_seed, &spin.client_seed, spin.nonce),
+    })
+}
+
+/// Record the outcome of a spin, so that it can be compared when verifying.
+pub async fn record_outcome(ctx: &Context, spin: &Spin, outcome: String) {
+    let db = get_db_handler(ctx).await;
+    let _ = db.set_fair_spin_outcome(spin.id, outcome).await;
+}
+
+#[command]
+#[sub_commands(reveal, verify)]
+#[description(
+    "Show the hash of your server seed, your client seed, and your latest spins.
+    Every spin of `slots`, `roulette` and `gamba` is rolled from SHA-256 of `<server seed>:<client seed>:<nonce>:<round>`.
+    Reveal the server seed to check that your past spins were not rigged."
+)]
+async fn fair(ctx: &Context, msg: &Message) -> CommandResult {
+    let db = get_db_handler(ctx).await;
+    let uid = msg.author.id.get();
+    let (server_seed, client_seed) = new_seeds();
+    let seed = db
+        .get_or_create_fair_seed(uid, server_seed, client_seed)
+        .await?;
+    let mut s = String::new();
+    for spin in db.get_fair_spins(uid, 5).await? {
+        writeln!(
+            s,
+            "`#{}` {}: {} <t:{}:R>",
+            spin.id,
+            spin.game,
+            spin.outcome.as_deref().unwrap_or("-"),
+            spin.created_at.timestamp()
+        )
+        .unwrap();
+    }
+    msg.channel_id
+        .send_message(
+            ctx,
+            CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .author(CreateEmbedAuthor::new(format!(
+                        "Seeds for {}:",
+                        msg.author.name
+                    )))
+                    .field(
+                        "Server seed hash",
+                        format!("`{}`", hash_seed(&seed.server_seed)),
+                        false,
+                    )
+                    .field("Client seed", format!("`{}`", seed.client_seed), true)
+                    .field("Next nonce", seed.nonce.to_string(), true)
+                    .field(
+                        "Latest spins",
+                        if s.is_empty() { "None".into() } else { s },
+                        false,
+                    ),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+#[command]
+#[max_args(1)]
+#[description(
+    "Reveal your server seed so that your spins with it can be verified, and start using new seeds. Optionally choose your new client seed."
+)]
+#[usage("[client seed]")]
+#[example("")]
+#[example("lucky123")]
+async fn reveal(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let (server_seed, mut client_seed) = new_seeds();
+    if let Some(c) = args.current() {
+        if c.len() > 64 {
+            let _ = msg
+                .channel_id
+                .say(ctx, "Client seed can be at most 64 characters.")
+                .await;
+            return Ok(());
+        }
+        client_seed = c.to_owned();
+    }
+    let db = get_db_handler(ctx).await;
+    let new_hash = hash_seed(&server_seed);
+    let old = db
+        .reveal_fair_seed(msg.author.id.get(), server_seed, client_seed)
+        .await?;
+    let mut embed = CreateEmbed::new()
+        .author(CreateEmbedAuthor::new(format!(
+            "Seeds for {}:",
+            msg.author.name
+        )))
+        .field("New server seed hash", format!("`{new_hash}`"), false);
+    if let Some(old) = old {
+        embed = embed
+            .field(
+                "Revealed server seed",
+                format!("`{}`", old.server_seed),
+                false,
+            )
+            .field("Client seed", format!("`{}`", old.client_seed), true)
+            .field("Spins", old.nonce.to_string(), true);
+    }
+    msg.channel_id
+        .send_message(ctx, CreateMessage::new().add_embed(embed))
+        .await?;
+    Ok(())
+}
+
+#[command]
+#[num_args(1)]
+#[description("Recompute the outcome of a spin, if its server seed has been revealed.")]
+#[usage("<spin>")]
+#[example("123")]
+async fn verify(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let id: i32 = args
+        .current()
+        .unwrap()
+        .trim_start_matches('#')
+        .parse()
+        .map_err(|_| "Invalid spin")?;
+    let db = get_db_handler(ctx).await;
+    let Some(spin) = db.get_fair_spin(id).await? else {
+        let _ = msg.channel_id.say(ctx, "Spin not found").await;
+        return Ok(());
+    };
+    if db
+        .get_fair_seed(spin.user_id as u64)
+        .await?
+        .is_some_and(|s| s.server_seed == spin.server_seed)
+    {
+        let prefix = get_prefix(ctx, msg.guild_id).await;
+        let _ = msg
+            .channel_id
+            .say(
+                ctx,
+                format!(
+                    "The server seed of this spin is still secret. <@{}> can reveal it with `{prefix}fair reveal`.",
+                    spin.user_id
+                ),
+            )
+            .await;
+        return Ok(());
+    }
+    let rules = match spin.game.strip_prefix("slots ").map(str::parse) {
+        Some(Ok(table)) => db
+            .get_slot_table(table)
+            .await?
+            .and_then(|t| SlotRules::from_json(&t.rules).ok()),
+        _ => None,
+    };
+    let mut rng = FairRng::new(&spin.server_seed, &spin.client_seed, spin.nonce);
+    let replayed = replay(&spin.game, &mut rng, rules.as_ref());
+    let ok = replayed.is_some() && replayed == spin.outcome;
+    msg.channel_id
+        .send_message(
+            ctx,
+            CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .author(CreateEmbedAuthor::new(format!(
+                        "Spin #{}: {}",
+                        spin.id, spin.game
+                    )))
+                    .title(if ok { "✅ Verified" } else { "❌ Mismatch" })
+                    .field("Server seed", format!("`{}`", spin.server_seed), false)
+                    .field(
+                        "Server seed hash",
+                        format!("`{}`", hash_seed(&spin.server_seed)),
+                        false,
+                    )
+                    .field("Client seed", format!("`{}`", spin.client_seed), true)
+                    .field("Nonce", spin.nonce.to_string(), true)
+                    .field("Outcome", spin.outcome.unwrap_or_else(|| "-".into()), true)
+                    .field("Recomputed", replayed.unwrap_or_else(|| "-".into()), true),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash() {
+        assert_eq!(
+            hash_seed("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn rng() {
+        let mut a = FairRng::new("server", "client", 0);
+        let mut b = FairRng::new("server", "client", 0);
+        let mut c = FairRng::new("server", "client", 1);
+        let a: Vec<u64> = (0..10).map(|_| a.next_u64()).collect();
+        let b: Vec<u64> = (0..10).map(|_| b.next_u64()).collect();
+        let c: Vec<u64> = (0..10).map(|_| c.next_u64()).collect();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        // The first bytes are the hash of round 0
+        let mut r = FairRng::new("server", "client", 0);
+        let mut bytes = [0; 32];
+        r.fill_bytes(&mut bytes);
+        assert_eq!(hex::encode(bytes), hash_seed("server:client:0:0"));
+    }
+
+    #[test]
+    fn replays() {
+        for game in ["slots", "roulette", "gamba 35"] {
+            let mut a = FairRng::new("server", "client", 7);
+            let mut b = FairRng::new("server", "client", 7);
+            assert_eq!(replay(game, &mut a, None), replay(game, &mut b, None));
+            assert!(replay(game, &mut a, None).is_some());
+        }
+        let rules = SlotRules::default();
+        let mut a = FairRng::new("server", "client", 7);
+        let mut b = FairRng::new("server", "client", 7);
+        assert_eq!(
+            replay("slots", &mut a, None),
+            replay("slots 3", &mut b, Some(&rules))
+        );
+        let mut r = FairRng::new("server", "client", 7);
+        assert_eq!(replay("slots 3", &mut r, None), None);
+        assert_eq!(replay("blackjack", &mut r, None), None);
+        assert_eq!(replay("gamba", &mut r, None), None);
+    }
+}
@@ -0,0 +1,237 @@
+use std::fmt::Write;
+
+use chrono::Duration;
+use serenity::{
+    builder::{CreateEmbed, CreateEmbedAuthor, CreateMessage},
+    client::Context,
+    framework::standard::{
+        macros::{command, group},
+        Args, Command, CommandResult,
+    },
+    model::prelude::Message,
+};
+
+use super::{
+    market::{MARKET_COMMAND, TRADE_COMMAND},
+    spells::HAMMER_COMMAND,
+};
+use crate::{
+    database::WallaceDBClient,
+    discord::{get_db_handler, get_prefix},
+};
+
+#[group]
+#[commands(buy, inventory, use, market, trade)]
+struct Items;
+
+pub enum ItemKind {
+    /// Consumed by a command.
+    Item(&'static Command),
+    /// Gives a buff for this many seconds when used.
+    Buff(i64),
+}
+
+pub struct ShopItem {
+    /// Used to refer to the item in commands and in the database.
+    pub id: &'static str,
+    pub name: &'static str,
+    pub desc: &'static str,
+    pub price: i64,
+    pub kind: ItemKind,
+}
+
+pub const WALLACE_HAMMER: ShopItem = ShopItem {
+    id: "hammer",
+    name: "Wallace Hammer",
+    desc: "Bonk a user. No gamba required. Breaks even if the bonk is blocked.",
+    price: 25,
+    kind: ItemKind::Item(&HAMMER_COMMAND),
+};
+pub const BONK_SHIELD: ShopItem = ShopItem {
+    id: "shield",
+    name: "Bonk Shield",
+    desc: "Blocks bonks from others for an hour.",
+    price: 15,
+    kind: ItemKind::Buff(3600),
+};
+pub const COUNTER_SPELL: ShopItem = ShopItem {
+    id: "counter",
+    name: "Counter-spell",
+    desc: "Reflects bonks back at the caster for 10 minutes.",
+    price: 30,
+    kind: ItemKind::Buff(600),
+};
+pub const SLOTS_LUCK: ShopItem = ShopItem {
+    id: "luck",
+    name: "Slots Luck",
+    desc: "Doubles slots wins for 10 minutes.",
+    price: 10,
+    kind: ItemKind::Buff(600),
+};
+pub static SHOP_ITEMS: &[&ShopItem] = &[&WALLACE_HAMMER, &BONK_SHIELD, &COUNTER_SPELL, &SLOTS_LUCK];
+
+pub fn find_item(s: &str) -> Option<&'static ShopItem> {
+    SHOP_ITEMS
+        .iter()
+        .find(|i| i.id.eq_ignore_ascii_case(s) || i.name.eq_ignore_ascii_case(s))
+        .copied()
+}
+
+pub fn item_name(id: &str) -> &str {
+    find_item(id).map(|i| i.name).unwrap_or(id)
+}
+
+#[command]
+#[only_in(guilds)]
+#[min_args(1)]
+#[max_args(2)]
+#[description("Buy an item or buff from the shop.")]
+#[usage("<item> [amount]")]
+#[example("hammer")]
+#[example("luck 3")]
+async fn buy(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    args.quoted();
+    let prefix = get_prefix(ctx, msg.guild_id).await;
+    let Some(item) = find_item(args.current().unwrap()) else {
+        let _ = msg
+            .channel_id
+            .say(ctx, format!("No such item. See `{prefix}shop`."))
+            .await;
+        return Ok(());
+    };
+    args.advance();
+    let amount: i32 = match args.current() {
+        Some(a) => a.parse().map_err(|_| "Invalid amount")?,
+        None => 1,
+    };
+    let db = get_db_handler(ctx).await;
+    let gid = msg.guild_id.unwrap().get();
+    let uid = msg.author.id.get();
+    let price = item.price * amount as i64;
+    if let Err(e) = db.buy_item(gid, uid, item.id, amount, price).await {
+        let _ = msg.channel_id.say(ctx, e.to_string()).await;
+        return Ok(());
+    }
+    let _ = msg
+        .channel_id
+        .send_message(
+            ctx,
+            CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .author(
+                        CreateEmbedAuthor::new(format!("-{price} 𝓚"))
+                            .icon_url("https://cdn.7tv.app/emote/60edf43ba60faa2a91cfb082/1x.gif"),
+                    )
+                    .title(format!("Bought {amount}x {}", item.name)),
+            ),
+        )
+        .await;
+    Ok(())
+}
+
+#[command]
+#[aliases(inv)]
+#[only_in(guilds)]
+#[description("Show your items and active buffs in this server.")]
+async fn inventory(ctx: &Context, msg: &Message) -> CommandResult {
+    let db = get_db_handler(ctx).await;
+    let gid = msg.guild_id.unwrap().get();
+    let uid = msg.author.id.get();
+    let items = db
+        .get_inventory(gid, uid)
+        .await?
+        .into_iter()
+        .fold(String::new(), |mut s, i| {
+            writeln!(&mut s, "**{}x** {}", i.amount, item_name(&i.item)).unwrap();
+            s
+        });
+    let buffs = db
+        .get_active_buffs(gid, uid)
+        .await?
+        .into_iter()
+        .fold(String::new(), |mut s, b| {
+            writeln!(
+                &mut s,
+                "{} (expires <t:{}:R>)",
+                item_name(&b.buff),
+                b.expires_at.timestamp()
+            )
+            .unwrap();
+            s
+        });
+    let uname = &msg.author.name;
+    msg.channel_id
+        .send_message(
+            ctx,
+            CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .author(
+                        CreateEmbedAuthor::new(format!("Inventory for {uname}:"))
+                            .icon_url("https://cdn.7tv.app/emote/60edf43ba60faa2a91cfb082/1x.gif"),
+                    )
+                    .field("Buffs", buffs, false)
+                    .field("Items", items, false),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[num_args(1)]
+#[description("Use an item from your inventory.")]
+#[usage("<item>")]
+#[example("shield")]
+async fn r#use(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    args.quoted();
+    let prefix = get_prefix(ctx, msg.guild_id).await;
+    let Some(item) = find_item(args.current().unwrap()) else {
+        let _ = msg
+            .channel_id
+            .say(ctx, format!("No such item. See `{prefix}shop`."))
+            .await;
+        return Ok(());
+    };
+    let secs = match item.kind {
+        ItemKind::Buff(secs) => secs,
+        ItemKind::Item(c) => {
+            let _ = msg
+                .channel_id
+                .say(
+                    ctx,
+                    format!(
+                        "The {} is used with `{prefix}{}`.",
+                        item.name, c.options.names[0]
+                    ),
+                )
+                .await;
+            return Ok(());
+        }
+    };
+    let db = get_db_handler(ctx).await;
+    let gid = msg.guild_id.unwrap().get();
+    let uid = msg.author.id.get();
+    let expires_at = match db
+        .use_buff_item(gid, uid, item.id, Duration::try_seconds(secs).unwrap())
+        .await
+    {
+        Ok(t) => t,
+        Err(e) => {
+            let _ = msg.channel_id.say(ctx, e.to_string()).await;
+            return Ok(());
+        }
+    };
+    let _ = msg
+        .channel_id
+        .say(
+            ctx,
+            format!(
+                "{} active until <t:{}:t> ✨",
+                item.name,
+                expires_at.timestamp()
+            ),
+        )
+        .await;
+    Ok(())
+}
@@ -0,0 +1,181 @@
+use chrono::{Duration, Utc};
+use serenity::{
+    builder::{CreateEmbed, CreateEmbedAuthor, CreateMessage},
+    client::Context,
+    framework::standard::{macros::command, Args, CommandResult},
+    model::prelude::Message,
+};
+
+use crate::{
+    database::WallaceDBClient,
+    discord::{get_db_handler, get_guild_settings},
+};
+
+/// Interest added to loans every day.
+pub const LOAN_INTEREST_PERCENT: i64 = 2;
+/// How long until a loan is defaulted.
+const LOAN_TERM: Duration = Duration::weeks(4);
+
+/// The interest added to a debt in a day. Rounded to the nearest 𝓚, like in the database,
+/// so that small debts are never charged much more than the advertised rate.
+fn interest(debt: i64) -> i64 {
+    (debt * LOAN_INTEREST_PERCENT + 50) / 100
+}
+
+#[command]
+#[only_in(guilds)]
+#[sub_commands(borrow, repay)]
+#[description(
+    "Show your loan from the bank. Loans have daily interest, and are repaid from the weekly payout.
+    Loans that are not repaid in time are defaulted, which blocks gambling until they are repaid."
+)]
+async fn loan(ctx: &Context, msg: &Message) -> CommandResult {
+    let gid = msg.guild_id.unwrap().get();
+    let db = get_db_handler(ctx).await;
+    let loan = db.get_loan(gid, msg.author.id.get()).await?;
+    let settings = get_guild_settings(ctx, gid).await;
+    let credit_limit = settings.credit_limit;
+    let mut embed = CreateEmbed::new()
+        .author(CreateEmbedAuthor::new(format!(
+            "Loan of {}:",
+            msg.author.name
+        )))
+        .field("Debt", format!("{} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻", loan.debt), true)
+        .field(
+            "Credit left",
+            format!("{} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻", (credit_limit - loan.debt).max(0)),
+            true,
+        )
+        .field(
+            "Interest",
+            format!(
+                "{LOAN_INTEREST_PERCENT}% daily, {} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻 tomorrow",
+                interest(loan.debt)
+            ),
+            true,
+        );
+    if loan.debt > 0 {
+        embed = embed.field(
+            "Due",
+            if loan.defaulted {
+                format!(
+                    "⚠️ Defaulted! Repay with `{}loan repay` to gamble again.",
+                    settings.prefix
+                )
+            } else {
+                format!("<t:{}:R>", loan.due_at.timestamp())
+            },
+            false,
+        );
+    }
+    msg.channel_id
+        .send_message(ctx, CreateMessage::new().add_embed(embed))
+        .await?;
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[num_args(1)]
+#[description(
+    "Borrow 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻 from the bank, up to the credit limit of the server (50 by default). A new loan must be repaid within 4 weeks."
+)]
+#[usage("<amount>")]
+#[example("20")]
+async fn borrow(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let amount: i64 = args
+        .current()
+        .unwrap()
+        .parse()
+        .map_err(|_| "Invalid amount")?;
+    let gid = msg.guild_id.unwrap().get();
+    let uid = msg.author.id.get();
+    let db = get_db_handler(ctx).await;
+    if db.get_loan(gid, uid).await?.defaulted {
+        let _ = msg
+            .channel_id
+            .say(ctx, "Repay your defaulted loan first 🧐")
+            .await;
+        return Ok(());
+    }
+    if let Err(e) = db
+        .take_loan(
+            gid,
+            uid,
+            amount,
+            get_guild_settings(ctx, gid).await.credit_limit,
+            Utc::now() + LOAN_TERM,
+        )
+        .await
+    {
+        let _ = msg.channel_id.say(ctx, e.to_string()).await;
+        return Ok(());
+    }
+    let _ = msg
+        .channel_id
+        .send_message(
+            ctx,
+            CreateMessage::new().add_embed(
+                CreateEmbed::new().author(
+                    CreateEmbedAuthor::new(format!("+{amount} 𝓚"))
+                        .icon_url("https://cdn.7tv.app/emote/60edf43ba60faa2a91cfb082/1x.gif"),
+                ),
+            ),
+        )
+        .await;
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[max_args(1)]
+#[description("Repay some of your loan, or all of it.")]
+#[usage("[amount]")]
+#[example("")]
+#[example("10")]
+async fn repay(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let amount: Option<i64> = match args.current() {
+        Some(a) => Some(a.parse().map_err(|_| "Invalid amount")?),
+        None => None,
+    };
+    let db = get_db_handler(ctx).await;
+    match db
+        .repay_loan(msg.guild_id.unwrap().get(), msg.author.id.get(), amount)
+        .await
+    {
+        Ok(paid) => {
+            let _ =
+                msg.channel_id
+                    .send_message(
+                        ctx,
+                        CreateMessage::new().add_embed(CreateEmbed::new().author(
+                            CreateEmbedAuthor::new(format!("-{paid} 𝓚")).icon_url(
+                                "https://cdn.7tv.app/emote/60edf43ba60faa2a91cfb082/1x.gif",
+                            ),
+                        )),
+                    )
+                    .await;
+        }
+        Err(e) => {
+            let _ = msg.channel_id.say(ctx, e.to_string()).await;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interest_rounds_to_nearest() {
+        assert_eq!(interest(0), 0);
+        assert_eq!(interest(1), 0);
+        assert_eq!(interest(24), 0);
+        assert_eq!(interest(25), 1);
+        assert_eq!(interest(74), 1);
+        assert_eq!(interest(75), 2);
+        assert_eq!(interest(100), 2);
+        assert_eq!(interest(1000), 20);
+    }
+}
@@ -0,0 +1,617 @@
+use std::{collections::HashMap, fmt::Write};
+
+use chrono::{DateTime, Duration, Utc};
+use serenity::{
+    all::{ButtonStyle, ComponentInteraction},
+    builder::{
+        CreateActionRow, CreateButton, CreateEmbed, CreateEmbedAuthor, CreateInteractionResponse,
+        CreateInteractionResponseMessage, CreateMessage,
+    },
+    client::Context,
+    framework::standard::{macros::command, Args, CommandResult},
+    model::prelude::{Message, MessageId},
+    utils::parse_user_mention,
+};
+
+use super::items::{find_item, item_name};
+use crate::{
+    database::WallaceDBClient,
+    discord::{get_db_handler, get_prefix, get_trade_offers},
+    model::TradeGoods,
+};
+
+/// How long a trade offer can be accepted.
+const TRADE_TIMEOUT: Duration = Duration::minutes(10);
+
+#[command]
+#[only_in(guilds)]
+#[sub_commands(sell, list, buy, cancel)]
+#[description(
+    "The market, where items are sold between players. Items for sale are held by the market until they are bought, or the listing is cancelled."
+)]
+async fn market(ctx: &Context, msg: &Message) -> CommandResult {
+    show_listings(ctx, msg).await
+}
+
+#[command]
+#[only_in(guilds)]
+#[min_args(2)]
+#[max_args(3)]
+#[description("Put items from your inventory up for sale. The price is for all of them.")]
+#[usage("<item> <price> [amount]")]
+#[example("hammer 20")]
+#[example("shield 40 3")]
+async fn sell(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    args.quoted();
+    let prefix = get_prefix(ctx, msg.guild_id).await;
+    let Some(item) = find_item(args.current().unwrap()) else {
+        let _ = msg
+            .channel_id
+            .say(ctx, format!("No such item. See `{prefix}shop`."))
+            .await;
+        return Ok(());
+    };
+    args.advance();
+    let price: i64 = args
+        .current()
+        .unwrap()
+        .parse()
+        .map_err(|_| "Invalid price")?;
+    args.advance();
+    let amount: i32 = match args.current() {
+        Some(a) => a.parse().map_err(|_| "Invalid amount")?,
+        None => 1,
+    };
+    let db = get_db_handler(ctx).await;
+    let id = match db
+        .create_market_listing(
+            msg.guild_id.unwrap().get(),
+            msg.author.id.get(),
+            item.id,
+            amount,
+            price,
+        )
+        .await
+    {
+        Ok(id) => id,
+        Err(e) => {
+            let _ = msg.channel_id.say(ctx, e.to_string()).await;
+            return Ok(());
+        }
+    };
+    let _ = msg
+        .channel_id
+        .say(
+            ctx,
+            format!(
+                "Listed {amount}x {} for {price} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻 as **#{id}**. Buy it with `{prefix}market buy {id}`.",
+                item.name
+            ),
+        )
+        .await;
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[description("Show the items for sale.")]
+async fn list(ctx: &Context, msg: &Message) -> CommandResult {
+    show_listings(ctx, msg).await
+}
+
+async fn show_listings(ctx: &Context, msg: &Message) -> CommandResult {
+    let db = get_db_handler(ctx).await;
+    let listings = db.get_market_listings(msg.guild_id.unwrap().get()).await?;
+    let s = if listings.is_empty() {
+        let prefix = get_prefix(ctx, msg.guild_id).await;
+        format!("Nothing for sale. Sell items with `{prefix}market sell`.")
+    } else {
+        listings.into_iter().fold(String::new(), |mut s, l| {
+            writeln!(
+                &mut s,
+                "**#{}** {}x {} for **{}** 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻 by <@{}>",
+                l.id,
+                l.amount,
+                item_name(&l.item),
+                l.price,
+                l.seller_id
+            )
+            .unwrap();
+            s
+        })
+    };
+    msg.channel_id
+        .send_message(
+            ctx,
+            CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .author(
+                        CreateEmbedAuthor::new("Market")
+                            .icon_url("https://cdn.7tv.app/emote/60edf43ba60faa2a91cfb082/1x.gif"),
+                    )
+                    .description(s),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[num_args(1)]
+#[description("Buy a listing from the market.")]
+#[usage("<listing>")]
+#[example("7")]
+async fn buy(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let id: i32 = args
+        .current()
+        .unwrap()
+        .trim_start_matches('#')
+        .parse()
+        .map_err(|_| "Invalid listing")?;
+    let db = get_db_handler(ctx).await;
+    let listing = match db
+        .buy_market_listing(msg.guild_id.unwrap().get(), msg.author.id.get(), id)
+        .await
+    {
+        Ok(l) => l,
+        Err(e) => {
+            let _ = msg.channel_id.say(ctx, e.to_string()).await;
+            return Ok(());
+        }
+    };
+    let _ = msg
+        .channel_id
+        .send_message(
+            ctx,
+            CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .author(
+                        CreateEmbedAuthor::new(format!("-{} 𝓚", listing.price))
+                            .icon_url("https://cdn.7tv.app/emote/60edf43ba60faa2a91cfb082/1x.gif"),
+                    )
+                    .title(format!(
+                        "Bought {}x {}",
+                        listing.amount,
+                        item_name(&listing.item)
+                    )),
+            ),
+        )
+        .await;
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[num_args(1)]
+#[description("Take down one of your listings, and get the items back.")]
+#[usage("<listing>")]
+#[example("7")]
+async fn cancel(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let id: i32 = args
+        .current()
+        .unwrap()
+        .trim_start_matches('#')
+        .parse()
+        .map_err(|_| "Invalid listing")?;
+    let db = get_db_handler(ctx).await;
+    if let Err(e) = db
+        .cancel_market_listing(msg.guild_id.unwrap().get(), msg.author.id.get(), id)
+        .await
+    {
+        let _ = msg.channel_id.say(ctx, e.to_string()).await;
+        return Ok(());
+    }
+    let _ = msg.react(ctx, '🫡').await;
+    Ok(())
+}
+
+/// Parse what one side of a trade gives: 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻 as a number, and items by name with an optional amount before.
+/// `nothing` gives nothing.
+fn parse_goods(words: &[&str]) -> Result<TradeGoods, String> {
+    let mut goods = TradeGoods::default();
+    if let [w] = words {
+        if w.eq_ignore_ascii_case("nothing") {
+            return Ok(goods);
+        }
+    }
+    let mut words = words.iter().peekable();
+    while let Some(w) = words.next() {
+        let (amount, name) = match w.parse::<i64>() {
+            Ok(n) => match words.peek().and_then(|w| find_item(w)) {
+                Some(_) => (n, *words.next().unwrap()),
+                None => {
+                    if n <= 0 {
+                        return Err("Amounts must be positive".into());
+                    }
+                    goods.kapsyler = goods.kapsyler.checked_add(n).ok_or("Too many 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻")?;
+                    continue;
+                }
+            },
+            Err(_) => (1, *w),
+        };
+        let Some(item) = find_item(name) else {
+            return Err(format!("No such item: {name}"));
+        };
+        let amount = i32::try_from(amount)
+            .ok()
+            .filter(|a| *a > 0)
+            .ok_or("Amounts must be positive")?;
+        match goods.items.iter_mut().find(|(i, _)| i == item.id) {
+            Some((_, a)) => *a = a.checked_add(amount).ok_or("Too many items")?,
+            None => goods.items.push((item.id.to_owned(), amount)),
+        }
+    }
+    Ok(goods)
+}
+
+/// Parse both sides of a trade, separated by `for`.
+fn parse_trade(s: &str) -> Result<(TradeGoods, TradeGoods), String> {
+    let words: Vec<&str> = s.split_whitespace().collect();
+    let Some(i) = words.iter().position(|w| w.eq_ignore_ascii_case("for")) else {
+        return Err("Separate what you give and what you want with `for`".into());
+    };
+    let gives = parse_goods(&words[..i])?;
+    let wants = parse_goods(&words[i + 1..])?;
+    if gives.is_empty() && wants.is_empty() {
+        return Err("That's not much of a trade".into());
+    }
+    Ok((gives, wants))
+}
+
+fn print_goods(goods: &TradeGoods) -> String {
+    let mut s = String::new();
+    if goods.kapsyler > 0 {
+        writeln!(&mut s, "{} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻", goods.kapsyler).unwrap();
+    }
+    for (item, amount) in &goods.items {
+        writeln!(&mut s, "**{amount}x** {}", item_name(item)).unwrap();
+    }
+    if s.is_empty() {
+        s.push_str("Nothing");
+    }
+    s
+}
+
+/// A trade that is waiting for both sides to accept.
+pub struct TradeOffer {
+    guild_id: u64,
+    from: (u64, String),
+    to: (u64, String),
+    gives: TradeGoods,
+    wants: TradeGoods,
+    from_accepted: bool,
+    to_accepted: bool,
+    created_at: DateTime<Utc>,
+}
+impl TradeOffer {
+    fn embed(&self, status: &str) -> CreateEmbed {
+        let check = |b| if b { " ✅" } else { "" };
+        CreateEmbed::new()
+            .title("Trade offer 🤝")
+            .description(status)
+            .field(
+                format!("{} gives{}", self.from.1, check(self.from_accepted)),
+                print_goods(&self.gives),
+                true,
+            )
+            .field(
+                format!("{} gives{}", self.to.1, check(self.to_accepted)),
+                print_goods(&self.wants),
+                true,
+            )
+    }
+}
+
+/// Trade offers by message.
+pub type TradeOffers = HashMap<MessageId, TradeOffer>;
+
+/// Forget offers that can no longer be accepted.
+fn remove_expired(offers: &mut TradeOffers, now: DateTime<Utc>) {
+    offers.retain(|_, o| now <= o.created_at + TRADE_TIMEOUT);
+}
+
+/// What a button press on a trade offer did.
+enum Press {
+    /// The offer is not around anymore.
+    Over,
+    NotYours,
+    Expired(TradeOffer),
+    Declined(TradeOffer),
+    /// Still waiting for the other side.
+    Waiting(Box<CreateEmbed>),
+    /// Both sides have accepted, and the trade can be made.
+    Accepted(TradeOffer),
+}
+
+/// Press a button on the offer in message `id`. Finished offers are taken out of `offers`.
+fn press(
+    offers: &mut TradeOffers,
+    id: MessageId,
+    uid: u64,
+    accept: bool,
+    now: DateTime<Utc>,
+) -> Press {
+    let Some(offer) = offers.get_mut(&id) else {
+        return Press::Over;
+    };
+    if uid != offer.from.0 && uid != offer.to.0 {
+        return Press::NotYours;
+    }
+    if now > offer.created_at + TRADE_TIMEOUT {
+        return Press::Expired(offers.remove(&id).unwrap());
+    }
+    if !accept {
+        return Press::Declined(offers.remove(&id).unwrap());
+    }
+    if uid == offer.from.0 {
+        offer.from_accepted = true;
+    } else {
+        offer.to_accepted = true;
+    }
+    if !(offer.from_accepted && offer.to_accepted) {
+        return Press::Waiting(Box::new(offer.embed("Waiting for both sides to accept.")));
+    }
+    Press::Accepted(offers.remove(&id).unwrap())
+}
+
+fn trade_buttons() -> Vec<CreateActionRow> {
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new("trade:accept")
+            .label("Accept")
+            .style(ButtonStyle::Success),
+        CreateButton::new("trade:decline")
+            .label("Decline")
+            .style(ButtonStyle::Danger),
+    ])]
+}
+
+#[command]
+#[only_in(guilds)]
+#[min_args(3)]
+#[description(
+    "Offer someone a trade of 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻 and items. Nothing changes hands until both of you accept, and then everything changes hands at once."
+)]
+#[usage("<user> <what you give> for <what you want>")]
+#[example("@Yxaria 2 shield for hammer")]
+#[example("@Yxaria 20 for luck")]
+#[example("@Yxaria hammer for nothing")]
+async fn trade(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let target_uid = parse_user_mention(args.current().unwrap()).ok_or("Invalid user tag")?;
+    args.advance();
+    let (gives, wants) = match parse_trade(args.rest()) {
+        Ok(t) => t,
+        Err(e) => {
+            let _ = msg.channel_id.say(ctx, e).await;
+            return Ok(());
+        }
+    };
+    if target_uid == msg.author.id {
+        let _ = msg.channel_id.say(ctx, "Can't trade with self").await;
+        return Ok(());
+    }
+    let gid = msg.guild_id.unwrap();
+    let Ok(target) = gid.member(ctx, target_uid).await else {
+        let _ = msg.channel_id.say(ctx, "No such member").await;
+        return Ok(());
+    };
+    if target.user.bot {
+        let _ = msg.channel_id.say(ctx, "Bots don't trade 🤖").await;
+        return Ok(());
+    }
+    let offer = TradeOffer {
+        guild_id: gid.get(),
+        from: (msg.author.id.get(), msg.author.name.clone()),
+        to: (target_uid.get(), target.display_name().to_owned()),
+        gives,
+        wants,
+        from_accepted: false,
+        to_accepted: false,
+        created_at: Utc::now(),
+    };
+    let m = msg
+        .channel_id
+        .send_message(
+            ctx,
+            CreateMessage::new()
+                .content(format!("<@{target_uid}>"))
+                .embed(offer.embed("Waiting for both sides to accept."))
+                .components(trade_buttons()),
+        )
+        .await?;
+    let offers = get_trade_offers(ctx).await;
+    let mut offers = offers.lock().await;
+    remove_expired(&mut offers, Utc::now());
+    offers.insert(m.id, offer);
+    Ok(())
+}
+
+async fn update_offer(
+    ctx: &Context,
+    c: &ComponentInteraction,
+    embed: CreateEmbed,
+    buttons: Vec<CreateActionRow>,
+) -> CommandResult {
+    c.create_response(
+        ctx,
+        CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new()
+                .embed(embed)
+                .components(buttons),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+async fn reply_ephemeral(ctx: &Context, c: &ComponentInteraction, content: &str) -> CommandResult {
+    let _ = c
+        .create_response(
+            ctx,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .ephemeral(true),
+            ),
+        )
+        .await;
+    Ok(())
+}
+
+/// Handle a button press on a trade offer.
+pub async fn trade_component(ctx: &Context, c: &ComponentInteraction) -> CommandResult {
+    let accept = match c.data.custom_id.as_str() {
+        "trade:accept" => true,
+        "trade:decline" => false,
+        _ => return Ok(()),
+    };
+    let press = {
+        let offers = get_trade_offers(ctx).await;
+        let mut offers = offers.lock().await;
+        press(
+            &mut offers,
+            c.message.id,
+            c.user.id.get(),
+            accept,
+            Utc::now(),
+        )
+    };
+    let offer = match press {
+        Press::Over => return reply_ephemeral(ctx, c, "This offer is over.").await,
+        Press::NotYours => return reply_ephemeral(ctx, c, "This is not your trade 😠").await,
+        Press::Expired(offer) => {
+            return update_offer(ctx, c, offer.embed("Expired ⌛"), vec![]).await;
+        }
+        Press::Declined(offer) => {
+            return update_offer(
+                ctx,
+                c,
+                offer.embed(&format!("Declined by {} ❌", c.user.name)),
+                vec![],
+            )
+            .await;
+        }
+        Press::Waiting(embed) => return update_offer(ctx, c, *embed, trade_buttons()).await,
+        Press::Accepted(offer) => offer,
+    };
+    let db = get_db_handler(ctx).await;
+    let status = match db
+        .trade(
+            offer.guild_id,
+            offer.from.0,
+            &offer.gives,
+            offer.to.0,
+            &offer.wants,
+        )
+        .await
+    {
+        Ok(()) => "Trade complete 🤝".to_owned(),
+        Err(e) => format!("Trade failed: {e}"),
+    };
+    update_offer(ctx, c, offer.embed(&status), vec![]).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn goods(kapsyler: i64, items: &[(&str, i32)]) -> TradeGoods {
+        TradeGoods {
+            kapsyler,
+            items: items.iter().map(|(i, a)| (i.to_string(), *a)).collect(),
+        }
+    }
+
+    #[test]
+    fn trades() {
+        assert_eq!(
+            parse_trade("2 shield 10 for hammer"),
+            Ok((goods(10, &[("shield", 2)]), goods(0, &[("hammer", 1)])))
+        );
+        assert_eq!(
+            parse_trade("20 for luck luck 5 5"),
+            Ok((goods(20, &[]), goods(10, &[("luck", 2)])))
+        );
+        assert_eq!(
+            parse_trade("hammer FOR nothing"),
+            Ok((goods(0, &[("hammer", 1)]), goods(0, &[])))
+        );
+        assert_eq!(parse_trade("for 5"), Ok((goods(0, &[]), goods(5, &[]))));
+        assert!(parse_trade("nothing for nothing").is_err());
+        assert!(parse_trade("hammer shield").is_err());
+        assert!(parse_trade("sword for 5").is_err());
+        assert!(parse_trade("0 hammer for 5").is_err());
+        assert!(parse_trade("-5 for hammer").is_err());
+        assert!(parse_trade(&format!("{} 1 for hammer", i64::MAX)).is_err());
+        assert!(parse_trade(&format!("{} luck luck for 5", i32::MAX)).is_err());
+    }
+
+    fn offer(created_at: DateTime<Utc>) -> TradeOffer {
+        TradeOffer {
+            guild_id: 1,
+            from: (1, "a".into()),
+            to: (2, "b".into()),
+            gives: goods(5, &[]),
+            wants: goods(0, &[("hammer", 1)]),
+            from_accepted: false,
+            to_accepted: false,
+            created_at,
+        }
+    }
+
+    #[test]
+    fn presses() {
+        let now = Utc::now();
+        let id = MessageId::new(1);
+        let mut offers = TradeOffers::from([(id, offer(now))]);
+        assert!(matches!(
+            press(&mut offers, id, 3, true, now),
+            Press::NotYours
+        ));
+        assert!(matches!(
+            press(&mut offers, id, 2, true, now),
+            Press::Waiting(_)
+        ));
+        // Accepting again still waits for the other side
+        assert!(matches!(
+            press(&mut offers, id, 2, true, now),
+            Press::Waiting(_)
+        ));
+        assert!(matches!(
+            press(&mut offers, id, 1, true, now),
+            Press::Accepted(_)
+        ));
+        assert!(matches!(press(&mut offers, id, 1, true, now), Press::Over));
+
+        offers.insert(id, offer(now));
+        assert!(matches!(
+            press(&mut offers, id, 2, false, now),
+            Press::Declined(_)
+        ));
+        assert!(offers.is_empty());
+
+        offers.insert(id, offer(now));
+        let later = now + TRADE_TIMEOUT + Duration::seconds(1);
+        assert!(matches!(
+            press(&mut offers, id, 1, true, later),
+            Press::Expired(_)
+        ));
+    }
+
+    #[test]
+    fn expiry() {
+        let now = Utc::now();
+        let mut offers = TradeOffers::from([
+            (
+                MessageId::new(1),
+                offer(now - TRADE_TIMEOUT - Duration::seconds(1)),
+            ),
+            (MessageId::new(2), offer(now - TRADE_TIMEOUT)),
+            (MessageId::new(3), offer(now)),
+        ]);
+        remove_expired(&mut offers, now);
+        let mut ids: Vec<u64> = offers.keys().map(|id| id.get()).collect();
+        ids.sort();
+        assert_eq!(ids, [2, 3]);
+    }
+}
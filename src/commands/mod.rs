@@ -1,8 +1,20 @@
+pub mod activity;
 pub mod ai_voice;
 pub mod bank;
+pub mod bets;
+pub mod blackjack;
+pub mod config;
 pub mod cooltext;
+pub mod daily;
 pub mod emote;
+pub mod fair;
 pub mod general;
+pub mod items;
+pub mod loans;
+pub mod market;
 pub mod riot;
+pub mod roulette;
 pub mod scheduling;
+pub mod slash;
+pub mod slots;
 pub mod spells;
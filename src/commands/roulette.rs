@@ -0,0 +1,545 @@
+use std::{collections::HashMap, fmt, fmt::Write, str::FromStr, time::Duration};
+
+use chrono::Utc;
+use rand::Rng;
+use serenity::{
+    builder::{CreateEmbed, CreateEmbedAuthor, CreateMessage, EditMessage},
+    client::Context,
+    framework::standard::{macros::command, Args, CommandResult},
+    model::prelude::{ChannelId, Message},
+};
+use tokio::time::sleep;
+use tracing::warn;
+
+use super::{
+    bank::DELAY_BETWEEN_EDITS,
+    fair::{fair_spin, record_outcome},
+};
+use crate::{
+    database::WallaceDBClient,
+    discord::{get_db_handler, get_prefix, get_roulette_tables},
+    model::TransactionReason,
+    services::{check_can_gamble, payment_message},
+};
+
+/// How long a roulette table takes bets before the wheel is spun.
+const BETTING_WINDOW: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RouletteColor {
+    Black,
+    Red,
+    Green,
+}
+impl RouletteColor {
+    fn emoji(&self) -> char {
+        match self {
+            RouletteColor::Black => '⬛',
+            RouletteColor::Red => '🟥',
+            RouletteColor::Green => '🟩',
+        }
+    }
+}
+const ROULETTE_WHEEL_ITEMS: i8 = 17;
+const ROULETTE_WHEEL: [RouletteColor; ROULETTE_WHEEL_ITEMS as usize] = [
+    RouletteColor::Green,
+    RouletteColor::Black,
+    RouletteColor::Red,
+    RouletteColor::Black,
+    RouletteColor::Red,
+    RouletteColor::Black,
+    RouletteColor::Red,
+    RouletteColor::Black,
+    RouletteColor::Red,
+    RouletteColor::Black,
+    RouletteColor::Red,
+    RouletteColor::Black,
+    RouletteColor::Red,
+    RouletteColor::Black,
+    RouletteColor::Red,
+    RouletteColor::Black,
+    RouletteColor::Red,
+];
+/// The numbers on the pockets of [`ROULETTE_WHEEL`].
+/// Mixed up so that odd/even and the halves don't follow the colors.
+const ROULETTE_NUMBERS: [i8; ROULETTE_WHEEL_ITEMS as usize] =
+    [0, 11, 6, 4, 13, 15, 2, 8, 9, 1, 16, 14, 3, 7, 12, 10, 5];
+fn print_roulette(i: i8, locked: bool) -> String {
+    let w = "▫";
+    format!(
+        "{}                                        {}\n{}{}🔽{}{}\n{}{}{}{}{}",
+        ROULETTE_WHEEL[((i - 4).rem_euclid(ROULETTE_WHEEL_ITEMS)) as usize].emoji(),
+        ROULETTE_WHEEL[((i + 4).rem_euclid(ROULETTE_WHEEL_ITEMS)) as usize].emoji(),
+        ROULETTE_WHEEL[((i - 3).rem_euclid(ROULETTE_WHEEL_ITEMS)) as usize].emoji(),
+        if locked { w } else { "             " },
+        if locked { w } else { "             " },
+        ROULETTE_WHEEL[((i + 3).rem_euclid(ROULETTE_WHEEL_ITEMS)) as usize].emoji(),
+        ROULETTE_WHEEL[((i - 2).rem_euclid(ROULETTE_WHEEL_ITEMS)) as usize].emoji(),
+        ROULETTE_WHEEL[((i - 1).rem_euclid(ROULETTE_WHEEL_ITEMS)) as usize].emoji(),
+        ROULETTE_WHEEL[i as usize].emoji(),
+        ROULETTE_WHEEL[((i + 1).rem_euclid(ROULETTE_WHEEL_ITEMS)) as usize].emoji(),
+        ROULETTE_WHEEL[((i + 2).rem_euclid(ROULETTE_WHEEL_ITEMS)) as usize].emoji(),
+    )
+}
+fn pocket_name(i: usize) -> String {
+    format!("{} {}", ROULETTE_WHEEL[i].emoji(), ROULETTE_NUMBERS[i])
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RouletteBet {
+    Color(RouletteColor),
+    Number(i8),
+    Odd,
+    Even,
+    /// 1-8
+    Low,
+    /// 9-16
+    High,
+}
+impl FromStr for RouletteBet {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_ascii_lowercase().as_str() {
+            "b" | "black" => Self::Color(RouletteColor::Black),
+            "r" | "red" => Self::Color(RouletteColor::Red),
+            "g" | "green" => Self::Color(RouletteColor::Green),
+            "odd" => Self::Odd,
+            "even" => Self::Even,
+            "low" | "1-8" => Self::Low,
+            "high" | "9-16" => Self::High,
+            n => match n.parse() {
+                Ok(n) if (0..ROULETTE_WHEEL_ITEMS).contains(&n) => Self::Number(n),
+                _ => return Err("Choose B, R, G, a number 0-16, odd, even, low, or high."),
+            },
+        })
+    }
+}
+impl fmt::Display for RouletteBet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Color(c) => write!(f, "{c:?}"),
+            Self::Number(n) => write!(f, "{n}"),
+            Self::Odd => write!(f, "Odd"),
+            Self::Even => write!(f, "Even"),
+            Self::Low => write!(f, "1-8"),
+            Self::High => write!(f, "9-16"),
+        }
+    }
+}
+impl RouletteBet {
+    /// If the bet wins when the ball lands in pocket `i`.
+    /// 0 is neither odd, even, low, nor high.
+    fn covers(&self, i: usize) -> bool {
+        let n = ROULETTE_NUMBERS[i];
+        match self {
+            Self::Color(c) => ROULETTE_WHEEL[i] == *c,
+            Self::Number(m) => n == *m,
+            Self::Odd => n % 2 == 1,
+            Self::Even => n != 0 && n % 2 == 0,
+            Self::Low => (1..=8).contains(&n),
+            Self::High => n > 8,
+        }
+    }
+    /// Bets pay in proportion to how many pockets they cover,
+    /// so the house edge is always one pocket.
+    fn multiplier(&self) -> i64 {
+        let covered = (0..ROULETTE_WHEEL_ITEMS as usize)
+            .filter(|i| self.covers(*i))
+            .count();
+        (ROULETTE_WHEEL_ITEMS - 1) as i64 / covered as i64
+    }
+    /// What a bet of `amount` pays back when the ball lands in pocket `i`.
+    fn payout(&self, amount: i64, i: usize) -> i64 {
+        if self.covers(i) {
+            self.multiplier() * amount
+        } else {
+            0
+        }
+    }
+}
+
+pub struct TableBet {
+    user: u64,
+    amount: i64,
+    bet: RouletteBet,
+    /// Where the bet is held until the wheel is spun.
+    held_bet: i32,
+}
+/// The bets on the open roulette table in each channel.
+pub type RouletteTables = HashMap<ChannelId, Vec<TableBet>>;
+
+/// The total payout for each user with a winning bet, in the order they first bet.
+fn table_payouts(bets: &[TableBet], i: usize) -> Vec<(u64, i64)> {
+    let mut payouts: Vec<(u64, i64)> = vec![];
+    for b in bets {
+        let amount = b.bet.payout(b.amount, i);
+        if amount == 0 {
+            continue;
+        }
+        match payouts.iter_mut().find(|(u, _)| *u == b.user) {
+            Some((_, a)) => *a += amount,
+            None => payouts.push((b.user, amount)),
+        }
+    }
+    payouts
+}
+
+/// Pick where the wheel starts and how far it spins.
+fn roll_roulette(rng: &mut impl Rng) -> (i8, u32) {
+    (rng.gen_range(0..ROULETTE_WHEEL_ITEMS), rng.gen_range(7..12))
+}
+fn roulette_pocket((start, steps): (i8, u32)) -> usize {
+    (start as i64 + steps as i64).rem_euclid(ROULETTE_WHEEL_ITEMS as i64) as usize
+}
+pub(super) fn replay_roulette(rng: &mut impl Rng) -> String {
+    pocket_name(roulette_pocket(roll_roulette(rng)))
+}
+
+/// Spin the wheel in `channel` and return the pocket the ball lands in.
+async fn spin(ctx: &Context, channel: ChannelId, (start, steps): (i8, u32)) -> usize {
+    let mut counter = start;
+    let mut m = channel.say(ctx, print_roulette(counter, false)).await.ok();
+    for _ in 0..steps {
+        sleep(DELAY_BETWEEN_EDITS).await;
+        counter = (counter + 1).rem_euclid(ROULETTE_WHEEL_ITEMS);
+        if let Some(m) = m.as_mut() {
+            let _ = m
+                .edit(
+                    ctx,
+                    EditMessage::new().content(print_roulette(counter, false)),
+                )
+                .await;
+        }
+    }
+
+    sleep(DELAY_BETWEEN_EDITS).await;
+    if let Some(m) = m.as_mut() {
+        let _ = m
+            .edit(
+                ctx,
+                EditMessage::new().content(print_roulette(counter, true)),
+            )
+            .await;
+    }
+    counter as usize
+}
+
+/// Parse the `<amount> <bet>` arguments and check that the user is allowed to gamble.
+async fn parse_bet(
+    ctx: &Context,
+    msg: &Message,
+    mut args: Args,
+) -> CommandResult<Option<(i64, RouletteBet)>> {
+    let amount: i64 = args
+        .current()
+        .unwrap()
+        .parse()
+        .map_err(|_| "Invalid amount")?;
+    args.advance();
+    let bet: RouletteBet = match args.current().unwrap().parse() {
+        Ok(b) => b,
+        Err(e) => {
+            let _ = msg.channel_id.say(ctx, e).await;
+            return Ok(None);
+        }
+    };
+    if !check_can_gamble(ctx, msg).await {
+        return Ok(None);
+    }
+    Ok(Some((amount, bet)))
+}
+
+#[command]
+#[bucket = "slots"]
+#[only_in(guilds)]
+#[num_args(2)]
+#[aliases(roll)]
+#[sub_commands(table)]
+#[description(
+    "Scuffed roulette! Bet on a color, a number, odd/even, or low/high. Win up to 16x your bet! User must be marked mature to get access."
+)]
+#[usage("<amount> <B|R|G|0-16|odd|even|low|high>")]
+#[example("1 b")]
+#[example("2 odd")]
+async fn roulette(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let Some((amount, bet)) = parse_bet(ctx, msg, args).await? else {
+        return Ok(());
+    };
+    let gid = msg.guild_id.unwrap().get();
+    let uid = msg.author.id.get();
+    let db = get_db_handler(ctx).await;
+    // Checked before the spin is started, but only taken together with the payout
+    if let Err(e) = db.has_bank_account_balance(gid, uid, amount).await {
+        let _ = msg.channel_id.say(ctx, e.to_string()).await;
+        return Ok(());
+    }
+    let mut fair = fair_spin(ctx, uid, "roulette".into()).await?;
+    let roll = roll_roulette(&mut fair.rng);
+    let i = roulette_pocket(roll);
+    let payout = bet.payout(amount, i);
+    if let Err(e) = db
+        .play_bet(
+            gid,
+            uid,
+            amount,
+            payout,
+            TransactionReason::Roulette,
+            Some(false),
+        )
+        .await
+    {
+        let _ = msg.channel_id.say(ctx, e.to_string()).await;
+        return Ok(());
+    }
+    let _ = msg
+        .channel_id
+        .send_message(ctx, payment_message(amount))
+        .await;
+    spin(ctx, msg.channel_id, roll).await;
+    record_outcome(ctx, &fair, pocket_name(i)).await;
+    if payout == 0 {
+        return Ok(());
+    }
+    let _ = msg
+        .channel_id
+        .send_message(
+            ctx,
+            CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .author(
+                        CreateEmbedAuthor::new("Win!")
+                            .icon_url("https://cdn.7tv.app/emote/628d8b64ed0a40a5ec5f4810/1x.gif"),
+                    )
+                    .title(format!("{}! Gained {amount} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻!", pocket_name(i))),
+            ),
+        )
+        .await;
+    Ok(())
+}
+
+#[command]
+#[bucket = "slots"]
+#[only_in(guilds)]
+#[num_args(2)]
+#[description(
+    "Bet at the roulette table in this channel. The first bet opens the table, and the wheel is spun for everyone 30 seconds later.
+    The wheel is rolled with the `fair` seeds of the user who opened the table."
+)]
+#[usage("<amount> <B|R|G|0-16|odd|even|low|high>")]
+#[example("5 red")]
+async fn table(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let Some((amount, bet)) = parse_bet(ctx, msg, args).await? else {
+        return Ok(());
+    };
+    let gid = msg.guild_id.unwrap().get();
+    let uid = msg.author.id.get();
+    // Held in the database, so that the bet is given back if the table is lost in a restart
+    let db = get_db_handler(ctx).await;
+    let held_bet = match db
+        .hold_bet(gid, uid, amount, TransactionReason::Roulette)
+        .await
+    {
+        Ok(id) => id,
+        Err(e) => {
+            let _ = msg.channel_id.say(ctx, e.to_string()).await;
+            return Ok(());
+        }
+    };
+    let tables = get_roulette_tables(ctx).await;
+    let mut tables = tables.lock().await;
+    let bets = tables.entry(msg.channel_id).or_default();
+    let opened = bets.is_empty();
+    bets.push(TableBet {
+        user: uid,
+        amount,
+        bet,
+        held_bet,
+    });
+    drop(tables);
+    if !opened {
+        let _ = msg.react(ctx, '🫡').await;
+        return Ok(());
+    }
+    let channel = msg.channel_id;
+    let c = ctx.clone();
+    tokio::spawn(async move {
+        if let Err(e) = spin_table(&c, channel).await {
+            warn!("Roulette table in {channel} failed: {e:?}");
+        }
+    });
+    let prefix = get_prefix(ctx, msg.guild_id).await;
+    let _ = msg
+        .channel_id
+        .say(
+            ctx,
+            format!(
+                "🎰 The roulette table is open! Place your bets with `{prefix}roulette table <amount> <bet>`. The wheel spins <t:{}:R>.",
+                (Utc::now() + BETTING_WINDOW).timestamp()
+            ),
+        )
+        .await;
+    Ok(())
+}
+
+/// Close the table in `channel` after the betting window, spin, and pay out all winners.
+async fn spin_table(ctx: &Context, channel: ChannelId) -> CommandResult {
+    sleep(BETTING_WINDOW).await;
+    let bets = get_roulette_tables(ctx)
+        .await
+        .lock()
+        .await
+        .remove(&channel)
+        .unwrap_or_default();
+    let Some(opener) = bets.first().map(|b| b.user) else {
+        return Ok(());
+    };
+    let db = get_db_handler(ctx).await;
+    // The table is rolled with the seeds of the user who opened it
+    let mut fair = match fair_spin(ctx, opener, "roulette".into()).await {
+        Ok(f) => f,
+        Err(e) => {
+            let ids: Vec<i32> = bets.iter().map(|b| b.held_bet).collect();
+            db.refund_held_bets(Some(&ids)).await?;
+            channel
+                .say(ctx, format!("{e}. All bets were given back."))
+                .await?;
+            return Ok(());
+        }
+    };
+    let i = spin(ctx, channel, roll_roulette(&mut fair.rng)).await;
+    record_outcome(ctx, &fair, pocket_name(i)).await;
+    let mut s = String::new();
+    let mut failed = vec![];
+    for b in &bets {
+        let payout = b.bet.payout(b.amount, i);
+        if let Err(e) = db.settle_held_bet(b.held_bet, payout, Some(false)).await {
+            writeln!(s, "<@{}>: {e}", b.user).unwrap();
+            failed.push(b.user);
+        }
+    }
+    for (uid, amount) in table_payouts(&bets, i) {
+        if !failed.contains(&uid) {
+            writeln!(s, "<@{uid}> gained {amount} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻!").unwrap();
+        }
+    }
+    if s.is_empty() {
+        s.push_str("Nobody won 😔");
+    }
+    channel
+        .send_message(
+            ctx,
+            CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .author(
+                        CreateEmbedAuthor::new(format!("{}!", pocket_name(i)))
+                            .icon_url("https://cdn.7tv.app/emote/628d8b64ed0a40a5ec5f4810/1x.gif"),
+                    )
+                    .title(format!("{} bets on the table", bets.len()))
+                    .description(s),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bets() -> Vec<RouletteBet> {
+        let mut v: Vec<RouletteBet> = ["b", "r", "g", "odd", "even", "low", "high"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        v.extend((0..ROULETTE_WHEEL_ITEMS).map(RouletteBet::Number));
+        v
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!("G".parse(), Ok(RouletteBet::Color(RouletteColor::Green)));
+        assert_eq!("9-16".parse(), Ok(RouletteBet::High));
+        assert_eq!("16".parse(), Ok(RouletteBet::Number(16)));
+        assert!("17".parse::<RouletteBet>().is_err());
+        assert!("-1".parse::<RouletteBet>().is_err());
+        assert!("blue".parse::<RouletteBet>().is_err());
+    }
+
+    #[test]
+    fn numbers() {
+        let mut n = ROULETTE_NUMBERS;
+        n.sort();
+        assert_eq!(n, core::array::from_fn(|i| i as i8));
+    }
+
+    #[test]
+    fn payouts() {
+        // Green pocket
+        assert_eq!(RouletteBet::Color(RouletteColor::Green).payout(3, 0), 48);
+        assert_eq!(RouletteBet::Number(0).payout(3, 0), 48);
+        for b in [
+            RouletteBet::Odd,
+            RouletteBet::Even,
+            RouletteBet::Low,
+            RouletteBet::High,
+        ] {
+            assert_eq!(b.payout(3, 0), 0);
+        }
+        // Black 11
+        assert_eq!(RouletteBet::Color(RouletteColor::Black).payout(3, 1), 6);
+        assert_eq!(RouletteBet::Color(RouletteColor::Red).payout(3, 1), 0);
+        assert_eq!(RouletteBet::Number(11).payout(3, 1), 48);
+        assert_eq!(RouletteBet::Odd.payout(3, 1), 6);
+        assert_eq!(RouletteBet::High.payout(3, 1), 6);
+        assert_eq!(RouletteBet::Low.payout(3, 1), 0);
+    }
+
+    #[test]
+    fn house_edge() {
+        // Every bet returns 16 out of 17 bets over a full turn of the wheel.
+        for b in bets() {
+            let won: i64 = (0..ROULETTE_WHEEL_ITEMS as usize)
+                .map(|i| b.payout(1, i))
+                .sum();
+            assert_eq!(won, (ROULETTE_WHEEL_ITEMS - 1) as i64, "{b}");
+        }
+    }
+
+    #[test]
+    fn roll() {
+        assert_eq!(roulette_pocket((15, 7)), 5);
+        assert_eq!(roulette_pocket((0, 11)), 11);
+    }
+
+    #[test]
+    fn table() {
+        let bets = vec![
+            TableBet {
+                user: 1,
+                amount: 2,
+                bet: RouletteBet::Color(RouletteColor::Black),
+                held_bet: 1,
+            },
+            TableBet {
+                user: 2,
+                amount: 5,
+                bet: RouletteBet::Even,
+                held_bet: 2,
+            },
+            TableBet {
+                user: 1,
+                amount: 1,
+                bet: RouletteBet::Number(11),
+                held_bet: 3,
+            },
+            TableBet {
+                user: 3,
+                amount: 4,
+                bet: RouletteBet::High,
+                held_bet: 4,
+            },
+        ];
+        assert_eq!(table_payouts(&bets, 1), vec![(1, 20), (3, 8)]);
+        assert_eq!(table_payouts(&bets, 0), vec![]);
+    }
+}
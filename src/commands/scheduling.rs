@@ -1,51 +1,206 @@
-use std::{fmt::Write, str::FromStr};
+use std::{collections::HashSet, fmt::Write, str::FromStr};
 
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
 use serenity::{
+    builder::{CreateEmbed, CreateMessage},
     client::Context,
     framework::standard::{
         macros::{command, group},
         Args, CommandResult,
     },
-    model::prelude::Message,
+    model::prelude::{ChannelId, GuildId, Message},
 };
+use strum::VariantNames;
 
+use super::config::change_setting;
 use crate::{
     database::WallaceDBClient,
-    discord::{get_db_handler, get_task_signal, ScheduleTask},
+    discord::{
+        channel_is_gone, get_db_handler, get_scheduler, guild_timezone, is_owner, is_schedulable,
+        remove_channel_tasks, run_task, task_timezone, ScheduleTask,
+    },
+    model::{CatchUp, Task},
+    scheduler::SchedulerMsg,
 };
 
 #[group]
-#[commands(tasks)]
+#[commands(tasks, remind)]
 struct Scheduling;
 
 #[command]
-#[sub_commands(add, remove)]
+#[sub_commands(
+    add, edit, remove, pause, resume, info, run, timezone, catchup, all, global, cleanup
+)]
 #[description("List all tasks in this channel.")]
 async fn tasks(ctx: &Context, msg: &Message) -> CommandResult {
     let db = get_db_handler(ctx).await;
-    let s = format!(
-        "**Tasks in <#{}>:**\nID: (`cron schedule`) command \"argument\"\n---------------------------\n{}",
-        msg.channel_id,
-        db.get_all_tasks_in_channel(msg.channel_id.get())
-            .await
-            .map(|tasks| {
-                tasks
-                    .iter()
-                    .fold(String::new(), |mut s, t| {
-                        writeln!(
-                            &mut s,
-                            "{}: (`{}`) {} \"{}\"",
-                            t.id,
-                            t.cron,
-                            t.cmd,
-                            t.arg.clone().unwrap_or_default()
-                        ).unwrap();
-                        s
-                    })
+    let tasks = match db.get_all_tasks_in_channel(msg.channel_id.get()).await {
+        Ok(tasks) => tasks,
+        Err(e) => {
+            let _ = msg.channel_id.say(ctx, e.to_string()).await;
+            return Ok(());
+        }
+    };
+    let mut s = format!("**Tasks in <#{}>:**\n{TASKS_HEADER}", msg.channel_id);
+    for t in tasks {
+        s.push_str(&task_line(ctx, t).await);
+    }
+    say_lines(ctx, msg, s).await;
+    Ok(())
+}
+
+const TASKS_HEADER: &str =
+    "ID: (`cron schedule` timezone) command \"argument\" next run\n---------------------------\n";
+
+/// A task in a list of tasks.
+async fn task_line(ctx: &Context, t: Task) -> String {
+    let tz = task_timezone(ctx, &t).await;
+    let next = if t.enabled {
+        cron::Schedule::from_str(&t.cron)
+            .ok()
+            .and_then(|s| s.upcoming(tz).next())
+            .map(|n| {
+                format!(
+                    "{} ({} UTC)",
+                    n.format("%Y-%m-%d %H:%M %Z"),
+                    n.with_timezone(&Utc).format("%H:%M")
+                )
             })
-            .unwrap_or_else(|e| e.to_string())
+            .unwrap_or_default()
+    } else {
+        "⏸️ paused".to_owned()
+    };
+    let mut s = format!(
+        "{}: (`{}` {}) {} \"{}\" {}",
+        t.id,
+        t.cron,
+        tz,
+        t.cmd,
+        t.arg.unwrap_or_default(),
+        next,
     );
-    let _ = msg.channel_id.say(ctx, s).await;
+    match t.description {
+        Some(d) => writeln!(&mut s, " — {d}").unwrap(),
+        None => writeln!(&mut s).unwrap(),
+    }
+    s
+}
+
+/// Send a message that might be too long for one message, split on lines.
+async fn say_lines(ctx: &Context, msg: &Message, s: String) {
+    let mut chunk = String::new();
+    for line in s.lines() {
+        if chunk.len() + line.len() >= 2000 {
+            let _ = msg.channel_id.say(ctx, &chunk).await;
+            chunk.clear();
+        }
+        let line: String = line.chars().take(1999).collect();
+        writeln!(&mut chunk, "{line}").unwrap();
+    }
+    if !chunk.is_empty() {
+        let _ = msg.channel_id.say(ctx, chunk).await;
+    }
+}
+
+#[command]
+#[only_in(guilds)]
+#[required_permissions("ADMINISTRATOR")]
+#[description("List all tasks in this server.")]
+async fn all(ctx: &Context, msg: &Message) -> CommandResult {
+    let Some((name, channels)) = msg.guild(&ctx.cache).map(|g| {
+        let channels: Vec<u64> = g
+            .channels
+            .keys()
+            .chain(g.threads.iter().map(|t| &t.id))
+            .map(|c| c.get())
+            .collect();
+        (g.name.clone(), channels)
+    }) else {
+        return Err("Failed to get guild".into());
+    };
+    let db = get_db_handler(ctx).await;
+    let tasks = match db.get_all_tasks_in_channels(channels).await {
+        Ok(tasks) => tasks,
+        Err(e) => {
+            let _ = msg.channel_id.say(ctx, e.to_string()).await;
+            return Ok(());
+        }
+    };
+    let mut s = format!("**Tasks in {name}:**\n{TASKS_HEADER}");
+    let mut channel = None;
+    for t in tasks {
+        if channel != Some(t.channel_id) {
+            channel = Some(t.channel_id);
+            writeln!(&mut s, "<#{}>", t.channel_id).unwrap();
+        }
+        s.push_str(&task_line(ctx, t).await);
+    }
+    say_lines(ctx, msg, s).await;
+    Ok(())
+}
+
+#[command]
+#[owners_only]
+#[description("List all tasks in all servers.")]
+async fn global(ctx: &Context, msg: &Message) -> CommandResult {
+    let db = get_db_handler(ctx).await;
+    let tasks = match db.get_all_tasks().await {
+        Ok(tasks) => tasks,
+        Err(e) => {
+            let _ = msg.channel_id.say(ctx, e.to_string()).await;
+            return Ok(());
+        }
+    };
+    let mut tasks: Vec<(String, Task)> = tasks
+        .into_iter()
+        .map(|t| (task_place(ctx, &t), t))
+        .collect();
+    tasks.sort_by(|(a, t1), (b, t2)| a.cmp(b).then(t1.id.cmp(&t2.id)));
+    let mut s = format!("**Tasks in all servers:**\n{TASKS_HEADER}");
+    let mut place = None;
+    for (p, t) in tasks {
+        if place.as_ref() != Some(&p) {
+            writeln!(&mut s, "{p}").unwrap();
+            place = Some(p);
+        }
+        s.push_str(&task_line(ctx, t).await);
+    }
+    say_lines(ctx, msg, s).await;
+    Ok(())
+}
+
+/// The server and channel of a task.
+fn task_place(ctx: &Context, t: &Task) -> String {
+    let Some(c) = ctx.cache.channel(t.channel_id as u64) else {
+        return format!("<#{}> (not in a server)", t.channel_id);
+    };
+    let guild = c.guild_id.name(&ctx.cache).unwrap_or_else(|| "?".into());
+    format!("**{guild}** #{}", c.name)
+}
+
+#[command]
+#[owners_only]
+#[description("Remove the tasks in channels that no longer exist.")]
+async fn cleanup(ctx: &Context, msg: &Message) -> CommandResult {
+    let db = get_db_handler(ctx).await;
+    let channels: HashSet<i64> = db
+        .get_all_tasks()
+        .await?
+        .into_iter()
+        .map(|t| t.channel_id)
+        .collect();
+    let mut removed = 0;
+    for id in channels {
+        let id = ChannelId::new(id as u64);
+        if channel_is_gone(ctx, id).await {
+            removed += remove_channel_tasks(ctx, id).await;
+        }
+    }
+    let _ = msg
+        .channel_id
+        .say(ctx, format!("Removed {removed} tasks."))
+        .await;
     Ok(())
 }
 
@@ -55,15 +210,20 @@ async fn tasks(ctx: &Context, msg: &Message) -> CommandResult {
 #[required_permissions("ADMINISTRATOR")]
 #[description(
     "Add a scheduled task to trigger according to a schedule.
-    Use a cron schedule string in the format \"second minute hour day-of-month month day-of-week year\" (UTC based).
+    Use a cron schedule string in the format \"second minute hour day-of-month month day-of-week year\".
+    Schedules follow the timezone of the server (see the 'timezone' sub-command), unless prefixed with \"CRON_TZ=<timezone>\".
     Tasks with a schedule that expire are cleaned up automatically. Use the 'remove' sub-command to remove tasks.
-    Availible commands: say, defaultname, randomname, lolweekly."
+    Runs missed while the bot is down are run once when it is back (see the 'catchup' sub-command).
+    Built-in tasks: say, defaultname, randomname, lolweekly.
+    Any other command can be scheduled too, and is run as you."
 )]
 #[usage("<cron_schedule> <command> [argument]")]
-#[example(r#""0 9 20 4 10 * 2023" say "This message is sent at 8:09 PM UTC on Oct 4th 2023.""#)]
-#[example(r#""0 0 8 * * Mon *" say "This message is sent every monday morning at 8 AM UTC.""#)]
+#[example(r#""0 9 20 4 10 * 2023" say "This message is sent at 8:09 PM on Oct 4th 2023.""#)]
+#[example(r#""0 0 8 * * Mon *" say "This message is sent every monday morning at 8 AM.""#)]
 #[example(r#""0 */5 * * * * *" say "This message is sent every 5th minute.""#)]
-#[example(r#""0 0 0 * Jan-Jun Mon *" say "This message is sent at midnight UTC every monday in the first half of the year.""#)]
+#[example(r#""0 0 0 * Jan-Jun Mon *" say "This message is sent at midnight every monday in the first half of the year.""#)]
+#[example(r#""CRON_TZ=America/New_York 0 30 9 * * Mon-Fri *" say "The stock market is open!""#)]
+#[example(r#""0 0 18 * * Fri *" account top"#)]
 async fn add(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     args.quoted();
     let cron = args.current().unwrap().trim().to_owned();
@@ -72,26 +232,120 @@ async fn add(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     args.advance();
     let arg = args.current().map(|s| s.to_owned());
 
+    let (timezone, cron) = split_cron_timezone(&cron);
+    if timezone
+        .as_deref()
+        .is_some_and(|tz| tz.parse::<Tz>().is_err())
+    {
+        let _ = msg.channel_id.say(ctx, "Invalid timezone").await;
+        return Ok(());
+    }
     if cron::Schedule::from_str(&cron).is_err() {
         let _ = msg.channel_id.say(ctx, "Invalid cron format").await;
         return Ok(());
     }
-    if cmd.parse::<ScheduleTask>().is_err() {
+    if !is_schedulable(&cmd) {
         let _ = msg.channel_id.say(ctx, "Invalid command to schedule").await;
         return Ok(());
     }
 
     let db = get_db_handler(ctx).await;
-    let tx = get_task_signal(ctx).await;
-    let res = db.create_task(cron, cmd, arg, msg.channel_id.get()).await;
-    if res.is_ok() && tx.capacity() > 0 {
-        tx.send(()).await.expect("channel to be open");
+    let scheduler = get_scheduler(ctx).await;
+    let res = db
+        .create_task(
+            cron,
+            cmd,
+            arg,
+            msg.channel_id.get(),
+            msg.author.id.get(),
+            timezone,
+        )
+        .await;
+    if let Ok(ref t) = res {
+        scheduler
+            .send(SchedulerMsg::Add(t.clone()))
+            .expect("scheduler to be running");
+    }
+    let _ = msg
+        .channel_id
+        .say(
+            ctx,
+            res.map(|t| format!("Added task {}.", t.id))
+                .unwrap_or_else(|e| e.to_string()),
+        )
+        .await;
+    Ok(())
+}
+
+#[command]
+#[min_args(2)]
+#[max_args(3)]
+#[required_permissions("ADMINISTRATOR")]
+#[description(
+    "Change the schedule, command, argument or description of a task.
+    The schedule is set like in the 'add' sub-command. Leave out the value to clear the argument or description."
+)]
+#[usage("<task_id> <cron|command|argument|description> [value]")]
+#[example(r#"20 cron "0 0 9 * * Mon *""#)]
+#[example(r#"20 argument "Good morning!""#)]
+#[example(r#"20 description "Weekly greeting""#)]
+async fn edit(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    args.quoted();
+    let Some(mut t) = find_task(ctx, msg, args.current().unwrap()).await else {
+        let _ = msg.channel_id.say(ctx, "No such task").await;
+        return Ok(());
+    };
+    args.advance();
+    let field = args.current().unwrap().to_lowercase();
+    args.advance();
+    let value = args.current().map(|s| s.trim().to_owned());
+    match (field.as_str(), value) {
+        ("cron" | "schedule", Some(v)) => {
+            let (timezone, cron) = split_cron_timezone(&v);
+            if timezone
+                .as_deref()
+                .is_some_and(|tz| tz.parse::<Tz>().is_err())
+            {
+                let _ = msg.channel_id.say(ctx, "Invalid timezone").await;
+                return Ok(());
+            }
+            if cron::Schedule::from_str(&cron).is_err() {
+                let _ = msg.channel_id.say(ctx, "Invalid cron format").await;
+                return Ok(());
+            }
+            t.cron = cron;
+            t.timezone = timezone;
+        }
+        ("command" | "cmd", Some(v)) => {
+            if !is_schedulable(&v) {
+                let _ = msg.channel_id.say(ctx, "Invalid command to schedule").await;
+                return Ok(());
+            }
+            t.cmd = v;
+        }
+        ("argument" | "arg", v) => t.arg = v,
+        ("description" | "desc", v) => t.description = v,
+        _ => {
+            let _ = msg
+                .channel_id
+                .say(ctx, "Can only edit cron, command, argument or description")
+                .await;
+            return Ok(());
+        }
+    }
+    let db = get_db_handler(ctx).await;
+    let scheduler = get_scheduler(ctx).await;
+    let res = db.update_task(&t).await;
+    if let Ok(ref t) = res {
+        scheduler
+            .send(SchedulerMsg::Update(t.clone()))
+            .expect("scheduler to be running");
     }
     let _ = msg
         .channel_id
         .say(
             ctx,
-            res.map(|_| "Added task.".to_owned())
+            res.map(|_| format!("Updated task {}.", t.id))
                 .unwrap_or_else(|e| e.to_string()),
         )
         .await;
@@ -105,16 +359,18 @@ async fn add(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
 #[usage("<task_id>")]
 #[example("20")]
 async fn remove(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    let id = args.current().unwrap().parse::<i32>();
-    if id.is_err() {
-        let _ = msg.channel_id.say(ctx, "Invalid task ID").await;
+    let Some(t) = find_task(ctx, msg, args.current().unwrap()).await else {
+        let _ = msg.channel_id.say(ctx, "No such task").await;
         return Ok(());
-    }
+    };
     let db = get_db_handler(ctx).await;
-    let tx = get_task_signal(ctx).await;
-    let res = db.delete_task(id.unwrap()).await;
-    if res.is_ok() && tx.capacity() > 0 {
-        tx.send(()).await.expect("channel to be open");
+    let scheduler = get_scheduler(ctx).await;
+    let id = t.id;
+    let res = db.delete_task(id).await;
+    if res.is_ok() {
+        scheduler
+            .send(SchedulerMsg::Remove(id))
+            .expect("scheduler to be running");
     }
     let _ = msg
         .channel_id
@@ -126,3 +382,483 @@ async fn remove(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
         .await;
     Ok(())
 }
+
+#[command]
+#[num_args(1)]
+#[required_permissions("ADMINISTRATOR")]
+#[description("Pause a task. Tasks that fail are paused automatically.")]
+#[usage("<task_id>")]
+#[example("20")]
+async fn pause(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    set_enabled(ctx, msg, args, false).await
+}
+
+#[command]
+#[num_args(1)]
+#[required_permissions("ADMINISTRATOR")]
+#[description("Resume a paused task. Runs missed while paused are skipped.")]
+#[usage("<task_id>")]
+#[example("20")]
+async fn resume(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    set_enabled(ctx, msg, args, true).await
+}
+
+async fn set_enabled(ctx: &Context, msg: &Message, args: Args, enabled: bool) -> CommandResult {
+    let Some(t) = find_task(ctx, msg, args.current().unwrap()).await else {
+        let _ = msg.channel_id.say(ctx, "No such task").await;
+        return Ok(());
+    };
+    let db = get_db_handler(ctx).await;
+    let scheduler = get_scheduler(ctx).await;
+    let res = db.set_task_enabled(t.id, enabled).await;
+    if let Ok(ref t) = res {
+        scheduler
+            .send(SchedulerMsg::Update(t.clone()))
+            .expect("scheduler to be running");
+    }
+    let _ = msg
+        .channel_id
+        .say(
+            ctx,
+            res.map(|_| {
+                format!(
+                    "{} task {}.",
+                    if enabled { "Resumed" } else { "Paused" },
+                    t.id
+                )
+            })
+            .unwrap_or_else(|e| e.to_string()),
+        )
+        .await;
+    Ok(())
+}
+
+#[command]
+#[num_args(1)]
+#[description("Show the details of a task and its next runs.")]
+#[usage("<task_id>")]
+#[example("20")]
+async fn info(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let Some(t) = find_task(ctx, msg, args.current().unwrap()).await else {
+        let _ = msg.channel_id.say(ctx, "No such task").await;
+        return Ok(());
+    };
+    let tz = task_timezone(ctx, &t).await;
+    let next = match (t.enabled, cron::Schedule::from_str(&t.cron)) {
+        (true, Ok(s)) => s.upcoming(tz).take(5).fold(String::new(), |mut s, n| {
+            writeln!(&mut s, "<t:{}:f>", n.timestamp()).unwrap();
+            s
+        }),
+        (false, _) => "⏸️ Paused".to_owned(),
+        (_, Err(_)) => "Invalid schedule".to_owned(),
+    };
+    let last = match (&t.last_result, t.last_result_at) {
+        (Some(r), Some(at)) => format!("<t:{}:R>: {r}", at.timestamp()),
+        _ => "Never".to_owned(),
+    };
+    let mut embed = CreateEmbed::new()
+        .title(format!("Task {}", t.id))
+        .field("Schedule", format!("`{}` {tz}", t.cron), true)
+        .field(
+            "Command",
+            format!("`{} {}`", t.cmd, t.arg.as_deref().unwrap_or_default()),
+            true,
+        )
+        .field("Channel", format!("<#{}>", t.channel_id), true)
+        .field(
+            "Creator",
+            t.creator_id
+                .map(|id| format!("<@{id}>"))
+                .unwrap_or_else(|| "?".to_owned()),
+            true,
+        )
+        .field(
+            "Created",
+            format!("<t:{}:f>", t.created_at.timestamp()),
+            true,
+        )
+        .field("Catch-up", &t.catch_up, true)
+        .field("Last run", last, false)
+        .field("Next runs", next, false);
+    if let Some(ref d) = t.description {
+        embed = embed.description(d);
+    }
+    msg.channel_id
+        .send_message(ctx, CreateMessage::new().add_embed(embed))
+        .await?;
+    Ok(())
+}
+
+#[command]
+#[num_args(1)]
+#[required_permissions("ADMINISTRATOR")]
+#[description("Run a task now, to test it. Does not affect its schedule.")]
+#[usage("<task_id>")]
+#[example("20")]
+async fn run(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let Some(t) = find_task(ctx, msg, args.current().unwrap()).await else {
+        let _ = msg.channel_id.say(ctx, "No such task").await;
+        return Ok(());
+    };
+    // The schedule goes on from its last run, so that missed runs are still caught up on
+    let res = run_task(ctx, &t).await;
+    let _ = msg
+        .channel_id
+        .say(
+            ctx,
+            res.map(|_| format!("Ran task {}.", t.id))
+                .unwrap_or_else(|e| format!("Task {} failed: {e}", t.id)),
+        )
+        .await;
+    Ok(())
+}
+
+/// Find a task in this server, or in this channel outside of servers. Bot owners can find any task.
+async fn find_task(ctx: &Context, msg: &Message, id: &str) -> Option<Task> {
+    let id = id.parse::<i32>().ok()?;
+    let t = get_db_handler(ctx).await.get_task(id).await.ok()??;
+    let channel = ChannelId::new(t.channel_id as u64);
+    let guild = ctx.cache.channel(channel).map(|c| c.guild_id);
+    let here = is_owner(ctx, msg.author.id).await
+        || is_task_here(channel, guild, msg.channel_id, msg.guild_id);
+    here.then_some(t)
+}
+
+/// If a task in `channel` of `guild` can be managed from `here` in `here_guild`.
+fn is_task_here(
+    channel: ChannelId,
+    guild: Option<GuildId>,
+    here: ChannelId,
+    here_guild: Option<GuildId>,
+) -> bool {
+    channel == here || here_guild.is_some_and(|g| guild == Some(g))
+}
+
+#[command]
+#[aliases("catch-up")]
+#[only_in(guilds)]
+#[num_args(2)]
+#[required_permissions("ADMINISTRATOR")]
+#[description(
+    "Set what a task does with runs that were missed while the bot was down.
+    skip: don't run them. once: run once (default). all: run each of them (max 10)."
+)]
+#[usage("<task_id> <skip|once|all>")]
+#[example("20 skip")]
+async fn catchup(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let Some(t) = find_task(ctx, msg, &args.single::<String>().unwrap()).await else {
+        let _ = msg.channel_id.say(ctx, "No such task").await;
+        return Ok(());
+    };
+    let Ok(catch_up) = args.current().unwrap().to_lowercase().parse::<CatchUp>() else {
+        let _ = msg
+            .channel_id
+            .say(
+                ctx,
+                format!("Catch-up must be one of: {}", CatchUp::VARIANTS.join(", ")),
+            )
+            .await;
+        return Ok(());
+    };
+    let db = get_db_handler(ctx).await;
+    let _ = msg
+        .channel_id
+        .say(
+            ctx,
+            db.set_task_catch_up(t.id, catch_up)
+                .await
+                .map(|_| format!("Task {} catches up: {catch_up}", t.id))
+                .unwrap_or_else(|e| e.to_string()),
+        )
+        .await;
+    Ok(())
+}
+
+#[command]
+#[aliases(tz)]
+#[max_args(1)]
+#[only_in(guilds)]
+#[required_permissions("ADMINISTRATOR")]
+#[description(
+    "Show or set the timezone that tasks and reminders in this server follow.
+    Use an IANA timezone name, or 'reset' to go back to UTC."
+)]
+#[usage("[timezone]")]
+#[example("Europe/Stockholm")]
+async fn timezone(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let gid = msg.guild_id.unwrap();
+    let Some(a) = args.current() else {
+        let tz = guild_timezone(ctx, Some(gid)).await;
+        let _ = msg
+            .channel_id
+            .say(ctx, format!("Tasks in this server follow {tz}"))
+            .await;
+        return Ok(());
+    };
+    let value = if a.eq_ignore_ascii_case("reset") {
+        "none"
+    } else {
+        a
+    };
+    if change_setting(ctx, msg, "timezone", value).await {
+        let tz = guild_timezone(ctx, Some(gid)).await;
+        let _ = msg
+            .channel_id
+            .say(ctx, format!("Tasks in this server now follow {tz}"))
+            .await;
+    }
+    Ok(())
+}
+
+#[command]
+#[aliases(remindme)]
+#[min_args(2)]
+#[description(
+    "Get pinged in this channel at a later time, in the timezone of the server.
+    Use a relative time like \"in 2h30m\" or \"45m\", or an absolute time like \"18:00\", \"tomorrow 18:00\" or \"2026-11-01 09:00\"."
+)]
+#[usage("<when> <text>")]
+#[example("in 2h30m take out the pizza")]
+#[example("tomorrow 18:00 call mom")]
+#[example("2026-11-01 09:00 renew the domain")]
+async fn remind(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let tz = guild_timezone(ctx, msg.guild_id).await;
+    let (when, text) = match parse_when(args.rest(), Utc::now().with_timezone(&tz)) {
+        Some((when, text)) if !text.is_empty() => (when.with_timezone(&Utc), text.to_owned()),
+        _ => {
+            let _ = msg
+                .channel_id
+                .say(ctx, "Invalid time. Try `in 2h30m` or `tomorrow 18:00`.")
+                .await;
+            return Ok(());
+        }
+    };
+    if when <= Utc::now() {
+        let _ = msg
+            .channel_id
+            .say(ctx, "That time has already passed")
+            .await;
+        return Ok(());
+    }
+    // A cron schedule with a year only fires once, and is then cleaned up by the scheduler
+    let cron = when.format("%-S %-M %-H %-d %-m * %Y").to_string();
+
+    let db = get_db_handler(ctx).await;
+    let scheduler = get_scheduler(ctx).await;
+    let res = db
+        .create_task(
+            cron,
+            ScheduleTask::Remind.to_string(),
+            Some(text),
+            msg.channel_id.get(),
+            msg.author.id.get(),
+            Some(Tz::UTC.name().to_owned()),
+        )
+        .await;
+    if let Ok(ref t) = res {
+        scheduler
+            .send(SchedulerMsg::Add(t.clone()))
+            .expect("scheduler to be running");
+    }
+    let _ = msg
+        .channel_id
+        .say(
+            ctx,
+            res.map(|_| format!("I will remind you <t:{}:R> ⏰", when.timestamp()))
+                .unwrap_or_else(|e| e.to_string()),
+        )
+        .await;
+    Ok(())
+}
+
+/// Split off a leading "CRON_TZ=<timezone>" from a cron schedule.
+fn split_cron_timezone(s: &str) -> (Option<String>, String) {
+    let (first, rest) = split_word(s);
+    match first
+        .strip_prefix("CRON_TZ=")
+        .or_else(|| first.strip_prefix("TZ="))
+    {
+        Some(tz) => (Some(tz.to_owned()), rest.to_owned()),
+        None => (None, s.to_owned()),
+    }
+}
+
+/// Parse a time like "in 2h30m", "tomorrow 18:00" or "2026-11-01 09:00"
+/// in the timezone of `now` from the start of `s`. Returns the time and the rest of `s`.
+fn parse_when(s: &str, now: DateTime<Tz>) -> Option<(DateTime<Tz>, &str)> {
+    let tz = now.timezone();
+    let (first, rest) = split_word(s);
+    match first.to_ascii_lowercase().as_str() {
+        "in" => {
+            let (d, rest) = split_word(rest);
+            Some((now.checked_add_signed(parse_duration(d)?)?, rest))
+        }
+        day @ ("today" | "tomorrow") => {
+            let (t, rest) = split_word(rest);
+            let time = NaiveTime::parse_from_str(t, "%H:%M").ok()?;
+            let mut date = now.date_naive();
+            if day == "tomorrow" {
+                date = date.succ_opt()?;
+            }
+            Some((
+                tz.from_local_datetime(&date.and_time(time)).earliest()?,
+                rest,
+            ))
+        }
+        _ => {
+            if let Some(d) = parse_duration(first) {
+                return now.checked_add_signed(d).map(|t| (t, rest));
+            }
+            if let Ok(time) = NaiveTime::parse_from_str(first, "%H:%M") {
+                // The next time the clock shows this time
+                let mut date = now.date_naive();
+                if date.and_time(time) <= now.naive_local() {
+                    date = date.succ_opt()?;
+                }
+                return Some((
+                    tz.from_local_datetime(&date.and_time(time)).earliest()?,
+                    rest,
+                ));
+            }
+            let date = NaiveDate::parse_from_str(first, "%Y-%m-%d").ok()?;
+            let (t, rest) = split_word(rest);
+            let time = NaiveTime::parse_from_str(t, "%H:%M").ok()?;
+            Some((
+                tz.from_local_datetime(&date.and_time(time)).earliest()?,
+                rest,
+            ))
+        }
+    }
+}
+
+fn split_word(s: &str) -> (&str, &str) {
+    let s = s.trim_start();
+    s.split_once(char::is_whitespace)
+        .map(|(w, rest)| (w, rest.trim_start()))
+        .unwrap_or((s, ""))
+}
+
+/// Parse a duration like "2h30m", "1d" or "90s".
+fn parse_duration(s: &str) -> Option<Duration> {
+    let mut secs: i64 = 0;
+    let mut n: Option<i64> = None;
+    for c in s.to_ascii_lowercase().chars() {
+        if let Some(d) = c.to_digit(10) {
+            n = Some(n.unwrap_or(0).checked_mul(10)?.checked_add(d as i64)?);
+            continue;
+        }
+        let unit = match c {
+            'd' => 86400,
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        secs = secs.checked_add(n.take()?.checked_mul(unit)?)?;
+    }
+    if n.is_some() || secs == 0 {
+        return None;
+    }
+    Duration::try_seconds(secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Tz> {
+        Tz::UTC.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn relative() {
+        let (t, rest) = parse_when("in 2h30m take out the pizza", now()).unwrap();
+        assert_eq!(
+            t,
+            Tz::UTC.with_ymd_and_hms(2026, 10, 18, 14, 30, 0).unwrap()
+        );
+        assert_eq!(rest, "take out the pizza");
+        let (t, rest) = parse_when("45m  tea", now()).unwrap();
+        assert_eq!(
+            t,
+            Tz::UTC.with_ymd_and_hms(2026, 10, 18, 12, 45, 0).unwrap()
+        );
+        assert_eq!(rest, "tea");
+        let (t, _) = parse_when("in 1d", now()).unwrap();
+        assert_eq!(t, Tz::UTC.with_ymd_and_hms(2026, 10, 19, 12, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn absolute() {
+        let (t, rest) = parse_when("tomorrow 18:00 call mom", now()).unwrap();
+        assert_eq!(t, Tz::UTC.with_ymd_and_hms(2026, 10, 19, 18, 0, 0).unwrap());
+        assert_eq!(rest, "call mom");
+        let (t, _) = parse_when("Today 13:15 x", now()).unwrap();
+        assert_eq!(
+            t,
+            Tz::UTC.with_ymd_and_hms(2026, 10, 18, 13, 15, 0).unwrap()
+        );
+        let (t, _) = parse_when("2026-11-01 09:00 renew", now()).unwrap();
+        assert_eq!(t, Tz::UTC.with_ymd_and_hms(2026, 11, 1, 9, 0, 0).unwrap());
+        // Already passed today
+        let (t, _) = parse_when("11:00 x", now()).unwrap();
+        assert_eq!(t, Tz::UTC.with_ymd_and_hms(2026, 10, 19, 11, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn local() {
+        let tz: Tz = "Europe/Stockholm".parse().unwrap();
+        let now = tz.with_ymd_and_hms(2026, 10, 24, 12, 0, 0).unwrap();
+        // Summer time ends during the night
+        let (t, _) = parse_when("tomorrow 18:00 x", now).unwrap();
+        assert_eq!(t, Tz::UTC.with_ymd_and_hms(2026, 10, 25, 17, 0, 0).unwrap());
+        let (t, _) = parse_when("in 1d x", now).unwrap();
+        assert_eq!(t, Tz::UTC.with_ymd_and_hms(2026, 10, 25, 10, 0, 0).unwrap());
+        let (t, _) = parse_when("13:00 x", now).unwrap();
+        assert_eq!(t, Tz::UTC.with_ymd_and_hms(2026, 10, 24, 11, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn task_scope() {
+        let (c1, c2, c3) = (ChannelId::new(1), ChannelId::new(2), ChannelId::new(3));
+        let (g1, g2) = (Some(GuildId::new(1)), Some(GuildId::new(2)));
+        assert!(is_task_here(c1, g1, c1, g1));
+        // Another channel in the same server
+        assert!(is_task_here(c1, g1, c2, g1));
+        // Another server
+        assert!(!is_task_here(c1, g1, c3, g2));
+        // A channel that is gone, or not in the cache
+        assert!(!is_task_here(c1, None, c3, g2));
+        // DMs only reach their own channel
+        assert!(is_task_here(c1, None, c1, None));
+        assert!(!is_task_here(c1, g1, c2, None));
+    }
+
+    #[test]
+    fn cron_timezone() {
+        assert_eq!(
+            split_cron_timezone("CRON_TZ=Europe/Stockholm 0 0 8 * * Mon *"),
+            (
+                Some("Europe/Stockholm".to_owned()),
+                "0 0 8 * * Mon *".to_owned()
+            )
+        );
+        assert_eq!(
+            split_cron_timezone("0 0 8 * * Mon *"),
+            (None, "0 0 8 * * Mon *".to_owned())
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(parse_when("", now()).is_none());
+        assert!(parse_when("soon x", now()).is_none());
+        assert!(parse_when("in 2 hours", now()).is_none());
+        assert!(parse_when("2h3 x", now()).is_none());
+        assert!(parse_when("0m x", now()).is_none());
+        assert!(parse_when("tomorrow evening", now()).is_none());
+        assert!(parse_when("2026-13-01 09:00", now()).is_none());
+        assert!(parse_when("in 100000000000d x", now()).is_none());
+        assert!(parse_when("100000000000d x", now()).is_none());
+    }
+}
@@ -0,0 +1,1108 @@
+use serenity::{
+    all::{
+        Command as ApplicationCommand, CommandInteraction, CommandOptionType, ResolvedOption,
+        ResolvedValue,
+    },
+    builder::{
+        AutocompleteChoice, CreateAllowedMentions, CreateAutocompleteResponse, CreateCommand,
+        CreateCommandOption, CreateInteractionResponse, CreateInteractionResponseMessage,
+    },
+    client::Context,
+    framework::standard::CommandResult,
+};
+use strum::VariantNames;
+use tracing::info;
+
+use super::items::SHOP_ITEMS;
+use crate::{
+    discord::{dispatch_as, ScheduleTask, GROUPS, PREFIX},
+    model::CatchUp,
+};
+
+/// An application command that is translated to a prefix command line
+/// and dispatched through the standard framework.
+pub struct SlashCommand {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// The prefix command line (without prefix) that options are appended to.
+    pub route: &'static str,
+    pub options: &'static [SlashOption],
+    pub sub_commands: &'static [SlashCommand],
+}
+
+pub struct SlashOption {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub kind: SlashKind,
+    pub required: bool,
+}
+
+pub enum SlashKind {
+    User,
+    Integer,
+    Boolean,
+    /// A single argument. Passed in quotes.
+    String,
+    /// The rest of the message. Passed as is.
+    Text,
+    Choice(&'static [&'static str]),
+    Autocomplete(fn(&str) -> Vec<String>),
+}
+
+const fn cmd(
+    name: &'static str,
+    description: &'static str,
+    route: &'static str,
+    options: &'static [SlashOption],
+) -> SlashCommand {
+    SlashCommand {
+        name,
+        description,
+        route,
+        options,
+        sub_commands: &[],
+    }
+}
+const fn group(
+    name: &'static str,
+    description: &'static str,
+    sub_commands: &'static [SlashCommand],
+) -> SlashCommand {
+    SlashCommand {
+        name,
+        description,
+        route: "",
+        options: &[],
+        sub_commands,
+    }
+}
+const fn opt(
+    name: &'static str,
+    description: &'static str,
+    kind: SlashKind,
+    required: bool,
+) -> SlashOption {
+    SlashOption {
+        name,
+        description,
+        kind,
+        required,
+    }
+}
+
+const USER: SlashOption = opt("user", "The target user", SlashKind::User, true);
+const SPELL: SlashOption = opt(
+    "spell",
+    "Spell",
+    SlashKind::Choice(&["gamba", "unbonk", "nickname", "defaultname", "servername"]),
+    true,
+);
+const SETTING_KEYS: &[&str] = &[
+    "prefix",
+    "disabled_groups",
+    "react_to_name",
+    "ai_persona",
+    "tts_voice",
+    "weekly_payout",
+    "credit_limit",
+    "log_channel",
+    "locale",
+];
+const SETTING: SlashOption = opt("setting", "Setting", SlashKind::Choice(SETTING_KEYS), true);
+const AMOUNT: SlashOption = opt("amount", "Amount of 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻", SlashKind::Integer, true);
+const ROULETTE_BET: SlashOption = opt(
+    "bet",
+    "B, R, G, a number 0-16, odd, even, low, or high",
+    SlashKind::String,
+    true,
+);
+
+pub static SLASH_COMMANDS: &[SlashCommand] = &[
+    // General
+    cmd(
+        "help",
+        "Show all commands, or details about one.",
+        "help",
+        &[opt(
+            "command",
+            "Command to show details about",
+            SlashKind::Autocomplete(command_names),
+            false,
+        )],
+    ),
+    cmd("ping", "Check if I'm alive.", "ping", &[]),
+    cmd("version", "Check my IQ!", "version", &[]),
+    cmd("riddle", "My hammer has a riddle for you.", "riddle", &[]),
+    // AI and Voice
+    group(
+        "ai",
+        "Chat with Wallace.",
+        &[
+            cmd(
+                "chat",
+                "Ask me anything!",
+                "ai",
+                &[opt("text", "Your message", SlashKind::Text, true)],
+            ),
+            cmd(
+                "reset",
+                "Reset the context of the conversation.",
+                "ai reset",
+                &[],
+            ),
+        ],
+    ),
+    cmd(
+        "dalle",
+        "Make a DALL-E image. Costs 10 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻.",
+        "dalle",
+        &[opt("prompt", "What to draw", SlashKind::Text, true)],
+    ),
+    cmd(
+        "say",
+        "Read out text in your voice channel.",
+        "say",
+        &[opt("text", "Text to read", SlashKind::Text, true)],
+    ),
+    cmd(
+        "tts",
+        "Produce an ogg file with TTS.",
+        "tts",
+        &[opt("text", "Text to read", SlashKind::Text, true)],
+    ),
+    // Bank and Gambling
+    group(
+        "account",
+        "Manage your bank account.",
+        &[
+            cmd("balance", "Show your account balance.", "account", &[]),
+            cmd("open", "Open a bank account.", "account open", &[]),
+            cmd("close", "Close your account.", "account close", &[]),
+            cmd(
+                "top",
+                "See the top 𝓚𝓪𝓹𝓼𝔂𝓵 holders in this guild.",
+                "account top",
+                &[],
+            ),
+            cmd(
+                "history",
+                "Show your recent transactions.",
+                "account history",
+                &[opt("page", "Page", SlashKind::Integer, false)],
+            ),
+        ],
+    ),
+    cmd(
+        "shop",
+        "Show available buffs, items, and spells to purchase.",
+        "shop",
+        &[],
+    ),
+    group(
+        "slots",
+        "Try your luck at the casino.",
+        &[
+            cmd("spin", "Spin the slot machine.", "slots", &[]),
+            cmd(
+                "rules",
+                "Show the reel and payout table of the slot machine.",
+                "slots rules",
+                &[],
+            ),
+            cmd(
+                "propose",
+                "Propose a slot table and simulate its return to player.",
+                "slots propose",
+                &[opt("json", "Slot table as JSON", SlashKind::Text, true)],
+            ),
+            cmd(
+                "activate",
+                "Use a proposed slot table.",
+                "slots activate",
+                &[opt("table", "Slot table ID", SlashKind::Integer, true)],
+            ),
+            cmd(
+                "reset",
+                "Go back to the default slot table.",
+                "slots reset",
+                &[],
+            ),
+        ],
+    ),
+    // Items
+    cmd(
+        "buy",
+        "Buy an item or buff from the shop.",
+        "buy",
+        &[
+            opt(
+                "item",
+                "Item to buy",
+                SlashKind::Autocomplete(shop_items),
+                true,
+            ),
+            opt("amount", "How many", SlashKind::Integer, false),
+        ],
+    ),
+    cmd(
+        "inventory",
+        "Show your items and active buffs.",
+        "inventory",
+        &[],
+    ),
+    cmd(
+        "use",
+        "Use an item from your inventory.",
+        "use",
+        &[opt(
+            "item",
+            "Item to use",
+            SlashKind::Autocomplete(shop_items),
+            true,
+        )],
+    ),
+    group(
+        "market",
+        "Buy and sell items between players.",
+        &[
+            cmd("list", "Show the items for sale.", "market list", &[]),
+            cmd(
+                "sell",
+                "Put items from your inventory up for sale.",
+                "market sell",
+                &[
+                    opt(
+                        "item",
+                        "Item to sell",
+                        SlashKind::Autocomplete(shop_items),
+                        true,
+                    ),
+                    opt("price", "Price for all of them", SlashKind::Integer, true),
+                    opt("amount", "How many", SlashKind::Integer, false),
+                ],
+            ),
+            cmd(
+                "buy",
+                "Buy a listing.",
+                "market buy",
+                &[opt("listing", "Listing number", SlashKind::Integer, true)],
+            ),
+            cmd(
+                "cancel",
+                "Take down one of your listings.",
+                "market cancel",
+                &[opt("listing", "Listing number", SlashKind::Integer, true)],
+            ),
+        ],
+    ),
+    cmd(
+        "trade",
+        "Offer someone a trade of 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻 and items.",
+        "trade",
+        &[
+            USER,
+            opt(
+                "offer",
+                "What you give for what you want, like: 2 shield for hammer",
+                SlashKind::Text,
+                true,
+            ),
+        ],
+    ),
+    group(
+        "roulette",
+        "Scuffed roulette! Win up to 16x your bet!",
+        &[
+            cmd(
+                "spin",
+                "Spin the wheel alone.",
+                "roulette",
+                &[AMOUNT, ROULETTE_BET],
+            ),
+            cmd(
+                "table",
+                "Bet at the roulette table in this channel. The wheel spins after 30 seconds.",
+                "roulette table",
+                &[AMOUNT, ROULETTE_BET],
+            ),
+        ],
+    ),
+    cmd(
+        "blackjack",
+        "Play blackjack against the dealer. Blackjack pays 3:2.",
+        "blackjack",
+        &[AMOUNT],
+    ),
+    group(
+        "jackpot",
+        "The progressive jackpot.",
+        &[
+            cmd(
+                "show",
+                "Show the jackpot and the last winner.",
+                "jackpot",
+                &[],
+            ),
+            cmd(
+                "share",
+                "Set how many percent of lost bets go into the jackpot.",
+                "jackpot share",
+                &[opt("percent", "0-50", SlashKind::Integer, true)],
+            ),
+        ],
+    ),
+    group(
+        "bet",
+        "Bet 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻 against each other.",
+        &[
+            cmd(
+                "show",
+                "Show a bet, or list the bets that are not resolved yet.",
+                "bet",
+                &[opt("bet", "Bet ID", SlashKind::Integer, false)],
+            ),
+            cmd(
+                "create",
+                "Start a bet.",
+                "bet create",
+                &[
+                    opt(
+                        "time",
+                        "How long it is open, like 30m, 2h or 1d",
+                        SlashKind::String,
+                        true,
+                    ),
+                    opt("question", "What to bet on", SlashKind::String, true),
+                    opt(
+                        "options",
+                        "The options, with quotes around options with spaces",
+                        SlashKind::Text,
+                        true,
+                    ),
+                ],
+            ),
+            cmd(
+                "place",
+                "Bet on an option.",
+                "bet place",
+                &[
+                    opt("bet", "Bet ID", SlashKind::Integer, true),
+                    opt("option", "Option name or number", SlashKind::String, true),
+                    AMOUNT,
+                ],
+            ),
+            cmd(
+                "resolve",
+                "Pay out a bet to the winners once it has closed.",
+                "bet resolve",
+                &[
+                    opt("bet", "Bet ID", SlashKind::Integer, true),
+                    opt("option", "The winning option", SlashKind::String, true),
+                ],
+            ),
+            cmd(
+                "cancel",
+                "Cancel a bet and refund everyone.",
+                "bet cancel",
+                &[opt("bet", "Bet ID", SlashKind::Integer, true)],
+            ),
+        ],
+    ),
+    group(
+        "fair",
+        "Check that the casino is not rigged.",
+        &[
+            cmd(
+                "seeds",
+                "Show your server seed hash, client seed and latest spins.",
+                "fair",
+                &[],
+            ),
+            cmd(
+                "reveal",
+                "Reveal your server seed and start using new seeds.",
+                "fair reveal",
+                &[opt(
+                    "client_seed",
+                    "Your new client seed",
+                    SlashKind::String,
+                    false,
+                )],
+            ),
+            cmd(
+                "verify",
+                "Recompute the outcome of a spin.",
+                "fair verify",
+                &[opt("spin", "Spin number", SlashKind::Integer, true)],
+            ),
+        ],
+    ),
+    group(
+        "daily",
+        "Daily 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻.",
+        &[
+            cmd("claim", "Claim your daily 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻.", "daily", &[]),
+            cmd(
+                "curve",
+                "Set the daily reward in this server.",
+                "daily curve",
+                &[
+                    opt("base", "Reward on the first day", SlashKind::Integer, true),
+                    opt(
+                        "bonus",
+                        "Added for each day of the streak",
+                        SlashKind::Integer,
+                        true,
+                    ),
+                    opt(
+                        "max_streak",
+                        "Days until the streak stops adding",
+                        SlashKind::Integer,
+                        true,
+                    ),
+                ],
+            ),
+        ],
+    ),
+    group(
+        "activity",
+        "𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻 from chatting and voice time.",
+        &[
+            cmd(
+                "show",
+                "Show what chatting and voice time earn.",
+                "activity",
+                &[],
+            ),
+            cmd(
+                "rates",
+                "Set what chatting and voice time earn in this server.",
+                "activity rates",
+                &[
+                    opt(
+                        "message",
+                        "Hundredths of a 𝓚𝓪𝓹𝓼𝔂𝓵 per message",
+                        SlashKind::Integer,
+                        true,
+                    ),
+                    opt(
+                        "voice",
+                        "Hundredths of a 𝓚𝓪𝓹𝓼𝔂𝓵 per minute in voice",
+                        SlashKind::Integer,
+                        true,
+                    ),
+                ],
+            ),
+        ],
+    ),
+    cmd("give", "Give 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻 to someone.", "give", &[AMOUNT, USER]),
+    group(
+        "loan",
+        "Borrow 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻 from the bank.",
+        &[
+            cmd("show", "Show your loan.", "loan", &[]),
+            cmd("borrow", "Borrow from the bank.", "loan borrow", &[AMOUNT]),
+            cmd(
+                "repay",
+                "Repay some of your loan, or all of it.",
+                "loan repay",
+                &[opt(
+                    "amount",
+                    "Amount of 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻",
+                    SlashKind::Integer,
+                    false,
+                )],
+            ),
+        ],
+    ),
+    cmd("mint", "Make 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻. 🤨", "mint", &[AMOUNT]),
+    cmd(
+        "setmature",
+        "Mark a user as mature.",
+        "setmature",
+        &[
+            USER,
+            opt("mature", "Mature or not", SlashKind::Boolean, true),
+        ],
+    ),
+    // Spells
+    cmd("bonk", "Bonk a user.", "bonk", &[USER]),
+    cmd(
+        "gamba",
+        "Summon mods in chat to start the GAMBA.",
+        "gamba",
+        &[
+            opt(
+                "size",
+                "Bonk size",
+                SlashKind::Choice(&["S", "M", "L", "XL", "XXL"]),
+                false,
+            ),
+            opt("amount", "Bet", SlashKind::Integer, false),
+            USER,
+        ],
+    ),
+    cmd(
+        "hammer",
+        "Bonk a user. Requires a Wallace Hammer.",
+        "hammer",
+        &[USER],
+    ),
+    cmd(
+        "bonkstats",
+        "Show bonk stats and who gets bonked the most.",
+        "bonkstats",
+        &[opt("user", "The target user", SlashKind::User, false)],
+    ),
+    cmd("unbonk", "Unbonk a user.", "unbonk", &[USER]),
+    cmd(
+        "nickname",
+        "Set the server nickname of the target user.",
+        "nickname",
+        &[USER, opt("name", "New nickname", SlashKind::Text, true)],
+    ),
+    group(
+        "defaultname",
+        "The default server name.",
+        &[
+            cmd(
+                "apply",
+                "Set the server name to the default.",
+                "defaultname",
+                &[],
+            ),
+            cmd(
+                "set",
+                "Set what the default server name is.",
+                "defaultname set",
+                &[opt("name", "Default server name", SlashKind::String, true)],
+            ),
+        ],
+    ),
+    cmd(
+        "servername",
+        "Set the server name.",
+        "servername",
+        &[opt("name", "New server name", SlashKind::Text, true)],
+    ),
+    group(
+        "randomname",
+        "Random server names.",
+        &[
+            cmd(
+                "apply",
+                "Set the server name to a random one.",
+                "randomname",
+                &[],
+            ),
+            cmd("list", "List subjects and objects.", "randomname list", &[]),
+            cmd(
+                "add_subject",
+                "Add a subject.",
+                "randomname add_subject",
+                &[opt("value", "Subject", SlashKind::Text, true)],
+            ),
+            cmd(
+                "add_object",
+                "Add an object.",
+                "randomname add_object",
+                &[opt("value", "Object", SlashKind::Text, true)],
+            ),
+        ],
+    ),
+    // Config
+    group(
+        "config",
+        "Configure Wallace in this server.",
+        &[
+            cmd("get", "Show a setting.", "config get", &[SETTING]),
+            cmd(
+                "set",
+                "Change a setting.",
+                "config set",
+                &[SETTING, opt("value", "New value", SlashKind::Text, true)],
+            ),
+            cmd("list", "Show all settings.", "config list", &[]),
+            cmd(
+                "reset",
+                "Change a setting back to the default, or all of them.",
+                "config reset",
+                &[opt(
+                    "setting",
+                    "Setting",
+                    SlashKind::Choice(SETTING_KEYS),
+                    false,
+                )],
+            ),
+            cmd(
+                "spells",
+                "Show the spell prices and restrictions.",
+                "config spells",
+                &[],
+            ),
+            cmd(
+                "spell_price",
+                "Set the price of a spell.",
+                "config spells price",
+                &[
+                    SPELL,
+                    opt(
+                        "price",
+                        "Price, or smallest bet for gamba",
+                        SlashKind::Integer,
+                        true,
+                    ),
+                ],
+            ),
+            cmd(
+                "spell_disable",
+                "Stop a spell from being cast.",
+                "config spells disable",
+                &[SPELL],
+            ),
+            cmd(
+                "spell_enable",
+                "Allow a disabled spell to be cast again.",
+                "config spells enable",
+                &[SPELL],
+            ),
+            cmd(
+                "gamba_size",
+                "Set the biggest gamba size allowed.",
+                "config spells gambasize",
+                &[opt(
+                    "size",
+                    "Biggest size",
+                    SlashKind::Choice(&["S", "M", "L", "XL", "XXL"]),
+                    true,
+                )],
+            ),
+        ],
+    ),
+    // Emotes
+    cmd(
+        "emote",
+        "Search and post one or more emotes from 7TV.",
+        "emote",
+        &[opt(
+            "query",
+            "Search strings or emote ids",
+            SlashKind::Text,
+            true,
+        )],
+    ),
+    // Cool Text
+    cmd(
+        "cooltext",
+        "Make some cool text in one of a few different fonts.",
+        "cooltext",
+        &[
+            opt(
+                "font",
+                "Font",
+                SlashKind::Choice(&[
+                    "boldfraktur",
+                    "bold",
+                    "bolditalic",
+                    "boldscript",
+                    "monospace",
+                ]),
+                true,
+            ),
+            opt("text", "Text", SlashKind::Text, true),
+        ],
+    ),
+    // Scheduling
+    group(
+        "tasks",
+        "Scheduled tasks.",
+        &[
+            cmd("list", "List all tasks in this channel.", "tasks", &[]),
+            cmd("all", "List all tasks in this server.", "tasks all", &[]),
+            cmd(
+                "global",
+                "List all tasks in all servers.",
+                "tasks global",
+                &[],
+            ),
+            cmd(
+                "cleanup",
+                "Remove the tasks in channels that no longer exist.",
+                "tasks cleanup",
+                &[],
+            ),
+            cmd(
+                "add",
+                "Add a scheduled task.",
+                "tasks add",
+                &[
+                    opt(
+                        "cron",
+                        "sec min hour day-of-month month day-of-week year",
+                        SlashKind::String,
+                        true,
+                    ),
+                    opt(
+                        "command",
+                        "Command to run",
+                        SlashKind::Autocomplete(schedulable_commands),
+                        true,
+                    ),
+                    opt("argument", "Argument", SlashKind::String, false),
+                ],
+            ),
+            cmd(
+                "edit",
+                "Change a scheduled task.",
+                "tasks edit",
+                &[
+                    opt("id", "Task ID", SlashKind::Integer, true),
+                    opt(
+                        "field",
+                        "What to change",
+                        SlashKind::Choice(&["cron", "command", "argument", "description"]),
+                        true,
+                    ),
+                    opt(
+                        "value",
+                        "New value. Empty to clear.",
+                        SlashKind::String,
+                        false,
+                    ),
+                ],
+            ),
+            cmd(
+                "remove",
+                "Remove a scheduled task.",
+                "tasks remove",
+                &[opt("id", "Task ID", SlashKind::Integer, true)],
+            ),
+            cmd(
+                "pause",
+                "Pause a scheduled task.",
+                "tasks pause",
+                &[opt("id", "Task ID", SlashKind::Integer, true)],
+            ),
+            cmd(
+                "resume",
+                "Resume a paused task.",
+                "tasks resume",
+                &[opt("id", "Task ID", SlashKind::Integer, true)],
+            ),
+            cmd(
+                "info",
+                "Show the details of a task and its next runs.",
+                "tasks info",
+                &[opt("id", "Task ID", SlashKind::Integer, true)],
+            ),
+            cmd(
+                "run",
+                "Run a task now, to test it.",
+                "tasks run",
+                &[opt("id", "Task ID", SlashKind::Integer, true)],
+            ),
+            cmd(
+                "timezone",
+                "Show or set the timezone of tasks in this server.",
+                "tasks timezone",
+                &[opt(
+                    "timezone",
+                    "IANA timezone, e.g. Europe/Stockholm",
+                    SlashKind::String,
+                    false,
+                )],
+            ),
+            cmd(
+                "catchup",
+                "Set what a task does with runs missed while the bot was down.",
+                "tasks catchup",
+                &[
+                    opt("id", "Task ID", SlashKind::Integer, true),
+                    opt(
+                        "policy",
+                        "What to do with missed runs",
+                        SlashKind::Choice(CatchUp::VARIANTS),
+                        true,
+                    ),
+                ],
+            ),
+        ],
+    ),
+    cmd(
+        "remind",
+        "Get pinged in this channel at a later time.",
+        "remind",
+        &[
+            opt(
+                "when",
+                "\"in 2h30m\", \"tomorrow 18:00\", \"2026-11-01 09:00\"...",
+                SlashKind::Text,
+                true,
+            ),
+            opt("text", "What to remind you about", SlashKind::Text, true),
+        ],
+    ),
+    // LoL and TFT
+    group(
+        "lol",
+        "LoL+TFT playtime.",
+        &[
+            cmd(
+                "playtime",
+                "Calculate LoL+TFT playtime for summoner(s).",
+                "lol playtime",
+                &[opt(
+                    "summoners",
+                    "\"SERVER:name#tag\"...",
+                    SlashKind::Text,
+                    true,
+                )],
+            ),
+            group(
+                "weekly",
+                "Weekly playtime report.",
+                &[
+                    cmd(
+                        "show",
+                        "Show weekly playtime for every added summoner.",
+                        "lol weekly",
+                        &[],
+                    ),
+                    cmd(
+                        "add",
+                        "Add summoners to the weekly report.",
+                        "lol weekly add",
+                        &[
+                            USER,
+                            opt("summoners", "\"SERVER:name#tag\"...", SlashKind::Text, true),
+                        ],
+                    ),
+                    cmd(
+                        "remove",
+                        "Remove all summoners of a user from the weekly report.",
+                        "lol weekly remove",
+                        &[USER],
+                    ),
+                ],
+            ),
+        ],
+    ),
+    group(
+        "tft",
+        "TFT meta analysis.",
+        &[cmd(
+            "analysis",
+            "Calculate TFT stats for the current set.",
+            "tft analysis",
+            &[opt("summoner", "SERVER:name#tag", SlashKind::String, true)],
+        )],
+    ),
+];
+
+fn command_names(partial: &str) -> Vec<String> {
+    GROUPS
+        .iter()
+        .flat_map(|g| g.options.commands.iter())
+        .map(|c| c.options.names[0])
+        .filter(|n| n.starts_with(partial))
+        .map(|n| n.to_owned())
+        .collect()
+}
+
+fn shop_items(partial: &str) -> Vec<String> {
+    SHOP_ITEMS
+        .iter()
+        .map(|i| i.id)
+        .filter(|n| n.starts_with(partial))
+        .map(|n| n.to_owned())
+        .collect()
+}
+
+fn schedulable_commands(partial: &str) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    // Some built-in tasks, like remind, are also commands
+    for n in ScheduleTask::VARIANTS.iter().copied().chain(
+        GROUPS
+            .iter()
+            .flat_map(|g| g.options.commands.iter())
+            .map(|c| c.options.names[0]),
+    ) {
+        if n.starts_with(partial) && !names.iter().any(|m| m == n) {
+            names.push(n.to_owned());
+        }
+    }
+    names
+}
+
+impl SlashCommand {
+    fn create(&self) -> CreateCommand {
+        CreateCommand::new(self.name)
+            .description(self.description)
+            .set_options(self.create_options())
+    }
+
+    fn create_options(&self) -> Vec<CreateCommandOption> {
+        if !self.sub_commands.is_empty() {
+            return self
+                .sub_commands
+                .iter()
+                .map(|c| {
+                    let kind = if c.sub_commands.is_empty() {
+                        CommandOptionType::SubCommand
+                    } else {
+                        CommandOptionType::SubCommandGroup
+                    };
+                    c.create_options().into_iter().fold(
+                        CreateCommandOption::new(kind, c.name, c.description),
+                        CreateCommandOption::add_sub_option,
+                    )
+                })
+                .collect();
+        }
+        // Discord requires the required options to be listed first
+        self.options
+            .iter()
+            .filter(|o| o.required)
+            .chain(self.options.iter().filter(|o| !o.required))
+            .map(SlashOption::create)
+            .collect()
+    }
+
+    /// Follow the sub commands in `options` down to the invoked command.
+    fn resolve<'a, 'b>(
+        &'static self,
+        options: &'b [ResolvedOption<'a>],
+    ) -> Option<(&'static SlashCommand, &'b [ResolvedOption<'a>])> {
+        if let [ResolvedOption {
+            name,
+            value: ResolvedValue::SubCommand(o) | ResolvedValue::SubCommandGroup(o),
+            ..
+        }] = options
+        {
+            return self
+                .sub_commands
+                .iter()
+                .find(|c| c.name == *name)?
+                .resolve(o);
+        }
+        Some((self, options))
+    }
+
+    fn to_prefix_line(&self, options: &[ResolvedOption]) -> String {
+        let mut line = self.route.to_owned();
+        for o in self.options {
+            let Some(v) = options.iter().find(|v| v.name == o.name) else {
+                continue;
+            };
+            let arg = match (&o.kind, &v.value) {
+                (_, ResolvedValue::User(u, _)) => format!("<@{}>", u.id),
+                (_, ResolvedValue::Integer(n)) => n.to_string(),
+                (_, ResolvedValue::Boolean(b)) => b.to_string(),
+                (SlashKind::String, ResolvedValue::String(s)) => format!("\"{s}\""),
+                (_, ResolvedValue::String(s)) => s.to_string(),
+                _ => continue,
+            };
+            line.push(' ');
+            line.push_str(&arg);
+        }
+        line
+    }
+}
+
+impl SlashOption {
+    fn create(&self) -> CreateCommandOption {
+        let kind = match self.kind {
+            SlashKind::User => CommandOptionType::User,
+            SlashKind::Integer => CommandOptionType::Integer,
+            SlashKind::Boolean => CommandOptionType::Boolean,
+            SlashKind::String
+            | SlashKind::Text
+            | SlashKind::Choice(_)
+            | SlashKind::Autocomplete(_) => CommandOptionType::String,
+        };
+        let o = CreateCommandOption::new(kind, self.name, self.description).required(self.required);
+        match self.kind {
+            SlashKind::Choice(choices) => {
+                choices.iter().fold(o, |o, c| o.add_string_choice(*c, *c))
+            }
+            SlashKind::Autocomplete(_) => o.set_autocomplete(true),
+            _ => o,
+        }
+    }
+}
+
+pub async fn register_slash_commands(ctx: &Context) -> CommandResult {
+    let commands = ApplicationCommand::set_global_commands(
+        ctx,
+        SLASH_COMMANDS.iter().map(SlashCommand::create).collect(),
+    )
+    .await?;
+    info!("Registered {} slash commands.", commands.len());
+    Ok(())
+}
+
+/// Translate a slash command to a prefix command and let the framework handle it.
+pub async fn run_slash_command(ctx: &Context, interaction: CommandInteraction) -> CommandResult {
+    let options = interaction.data.options();
+    let (command, options) = SLASH_COMMANDS
+        .iter()
+        .find(|c| c.name == interaction.data.name)
+        .and_then(|c| c.resolve(&options))
+        .ok_or("Unknown slash command")?;
+    let line = format!("{PREFIX}{}", command.to_prefix_line(options));
+    interaction
+        .create_response(
+            ctx,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(format!("`{line}`"))
+                    .allowed_mentions(CreateAllowedMentions::new()),
+            ),
+        )
+        .await?;
+    let msg = interaction.get_response(ctx).await?;
+    dispatch_as(
+        ctx,
+        msg,
+        interaction.user.clone(),
+        interaction.guild_id,
+        line,
+    )
+    .await;
+    Ok(())
+}
+
+pub async fn autocomplete_slash_command(
+    ctx: &Context,
+    interaction: CommandInteraction,
+) -> CommandResult {
+    let Some(focused) = interaction.data.autocomplete() else {
+        return Ok(());
+    };
+    let options = interaction.data.options();
+    let choices = SLASH_COMMANDS
+        .iter()
+        .find(|c| c.name == interaction.data.name)
+        .and_then(|c| c.resolve(&options))
+        .and_then(|(c, _)| c.options.iter().find(|o| o.name == focused.name))
+        .map(|o| match o.kind {
+            SlashKind::Autocomplete(f) => f(focused.value),
+            _ => vec![],
+        })
+        .unwrap_or_default();
+    interaction
+        .create_response(
+            ctx,
+            CreateInteractionResponse::Autocomplete(
+                CreateAutocompleteResponse::new().set_choices(
+                    choices
+                        .into_iter()
+                        .take(25)
+                        .map(|c| AutocompleteChoice::new(c.clone(), c))
+                        .collect(),
+                ),
+            ),
+        )
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,811 @@
+use std::collections::BTreeMap;
+
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+use serenity::{
+    builder::{CreateAttachment, CreateEmbed, CreateEmbedAuthor, CreateMessage, EditMessage},
+    client::Context,
+    framework::standard::{macros::command, Args, CommandResult},
+    model::prelude::Message,
+};
+use tokio::time::sleep;
+use tracing::warn;
+
+use super::{
+    bank::DELAY_BETWEEN_EDITS,
+    fair::{fair_spin, record_outcome},
+    items::SLOTS_LUCK,
+};
+use crate::{
+    database::WallaceDBClient,
+    discord::{get_db_handler, get_guild_settings},
+    model::TransactionReason,
+    services::{check_can_gamble, payment_message},
+};
+
+const MAX_REEL_ITEMS: usize = 50;
+const MAX_PAYOUT: i64 = 10000;
+/// How many spins to simulate when a new slot table is proposed.
+const SIMULATED_SPINS: u64 = 1000000;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SlotItem {
+    item: SlotItemType,
+    material: SlotItemMaterial,
+    color: SlotItemColor,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SlotItemType {
+    Crown,
+    Ring,
+    Hammer,
+    Crab,
+    Cherry,
+    Grapes,
+    Blueberries,
+    Pear,
+    Apple,
+}
+impl SlotItemType {
+    fn emoji(&self) -> char {
+        match self {
+            Self::Crown => '👑',
+            Self::Ring => '💍',
+            Self::Hammer => '🔨',
+            Self::Crab => '🦀',
+            Self::Cherry => '🍒',
+            Self::Grapes => '🍇',
+            Self::Blueberries => '🫐',
+            Self::Pear => '🍐',
+            Self::Apple => '🍏',
+        }
+    }
+    fn plural(&self) -> &'static str {
+        match self {
+            Self::Crown => "Crowns",
+            Self::Ring => "Rings",
+            Self::Hammer => "Hammers",
+            Self::Crab => "Crabs",
+            Self::Cherry => "Cherrys",
+            Self::Grapes => "Grapes",
+            Self::Blueberries => "Blueberries",
+            Self::Pear => "Pears",
+            Self::Apple => "Apples",
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SlotItemMaterial {
+    Metal,
+    Fruit,
+    None,
+}
+impl SlotItemMaterial {
+    fn plural(&self) -> &'static str {
+        match self {
+            Self::Metal => "Shiny Metal Objects",
+            Self::Fruit => "Fruits",
+            Self::None => "",
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SlotItemColor {
+    Red,
+    Purple,
+    Green,
+    None,
+}
+const SLOT_WHEEL_ITEMS: i8 = 13;
+const SLOT_WHEEL: [SlotItem; SLOT_WHEEL_ITEMS as usize] = [
+    SlotItem {
+        item: SlotItemType::Crown,
+        material: SlotItemMaterial::Metal,
+        color: SlotItemColor::None,
+    },
+    SlotItem {
+        item: SlotItemType::Ring,
+        material: SlotItemMaterial::Metal,
+        color: SlotItemColor::None,
+    },
+    SlotItem {
+        item: SlotItemType::Hammer,
+        material: SlotItemMaterial::Metal,
+        color: SlotItemColor::None,
+    },
+    SlotItem {
+        item: SlotItemType::Crab,
+        material: SlotItemMaterial::None,
+        color: SlotItemColor::Red,
+    },
+    SlotItem {
+        item: SlotItemType::Cherry,
+        material: SlotItemMaterial::Fruit,
+        color: SlotItemColor::Red,
+    },
+    SlotItem {
+        item: SlotItemType::Grapes,
+        material: SlotItemMaterial::Fruit,
+        color: SlotItemColor::Purple,
+    },
+    SlotItem {
+        item: SlotItemType::Grapes,
+        material: SlotItemMaterial::Fruit,
+        color: SlotItemColor::Purple,
+    },
+    SlotItem {
+        item: SlotItemType::Blueberries,
+        material: SlotItemMaterial::Fruit,
+        color: SlotItemColor::Purple,
+    },
+    SlotItem {
+        item: SlotItemType::Blueberries,
+        material: SlotItemMaterial::Fruit,
+        color: SlotItemColor::Purple,
+    },
+    SlotItem {
+        item: SlotItemType::Pear,
+        material: SlotItemMaterial::Fruit,
+        color: SlotItemColor::Green,
+    },
+    SlotItem {
+        item: SlotItemType::Pear,
+        material: SlotItemMaterial::Fruit,
+        color: SlotItemColor::Green,
+    },
+    SlotItem {
+        item: SlotItemType::Apple,
+        material: SlotItemMaterial::Fruit,
+        color: SlotItemColor::Green,
+    },
+    SlotItem {
+        item: SlotItemType::Apple,
+        material: SlotItemMaterial::Fruit,
+        color: SlotItemColor::Green,
+    },
+];
+
+/// The reel and payout table of a slot machine. Loaded from JSON, so that it can be tuned per server.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SlotRules {
+    /// The items on each of the three reels, which are shuffled before every spin.
+    reel: Vec<SlotItem>,
+    /// What three of the same item pays.
+    three: BTreeMap<SlotItemType, i64>,
+    /// What three items of the same color pay.
+    colors: BTreeMap<SlotItemColor, i64>,
+    /// What three items of the same material pay.
+    materials: BTreeMap<SlotItemMaterial, i64>,
+    /// Three of this item also win the jackpot. Tables without it can't win the jackpot.
+    #[serde(default)]
+    jackpot: Option<SlotItemType>,
+}
+impl Default for SlotRules {
+    fn default() -> Self {
+        Self {
+            reel: SLOT_WHEEL.to_vec(),
+            three: BTreeMap::from([
+                (SlotItemType::Crown, 333),
+                (SlotItemType::Ring, 66),
+                (SlotItemType::Hammer, 55),
+                (SlotItemType::Crab, 44),
+                (SlotItemType::Cherry, 33),
+                (SlotItemType::Grapes, 14),
+                (SlotItemType::Blueberries, 13),
+                (SlotItemType::Pear, 12),
+                (SlotItemType::Apple, 11),
+            ]),
+            colors: BTreeMap::from([
+                (SlotItemColor::Red, 8),
+                (SlotItemColor::Purple, 4),
+                (SlotItemColor::Green, 2),
+            ]),
+            materials: BTreeMap::from([(SlotItemMaterial::Metal, 7), (SlotItemMaterial::Fruit, 1)]),
+            jackpot: Some(SlotItemType::Crown),
+        }
+    }
+}
+impl SlotRules {
+    pub fn from_json(s: &str) -> Result<Self, String> {
+        let rules: Self = serde_json::from_str(s).map_err(|e| e.to_string())?;
+        rules.validate()?;
+        Ok(rules)
+    }
+    fn validate(&self) -> Result<(), String> {
+        if !(3..=MAX_REEL_ITEMS).contains(&self.reel.len()) {
+            return Err(format!("The reel needs 3 to {MAX_REEL_ITEMS} items"));
+        }
+        if self.colors.contains_key(&SlotItemColor::None)
+            || self.materials.contains_key(&SlotItemMaterial::None)
+        {
+            return Err("Color and material `none` can't pay".into());
+        }
+        let pays = self
+            .three
+            .values()
+            .chain(self.colors.values())
+            .chain(self.materials.values());
+        if pays.clone().any(|p| !(0..=MAX_PAYOUT).contains(p)) {
+            return Err(format!("Payouts must be between 0 and {MAX_PAYOUT}"));
+        }
+        if !pays.clone().any(|p| *p > 0) {
+            return Err("Nothing pays".into());
+        }
+        if let Some(i) = self
+            .three
+            .keys()
+            .find(|k| !self.reel.iter().any(|i| i.item == **k))
+        {
+            return Err(format!("{i:?} pays but is not on the reel"));
+        }
+        if let Some(c) = self
+            .colors
+            .keys()
+            .find(|k| !self.reel.iter().any(|i| i.color == **k))
+        {
+            return Err(format!("{c:?} pays but is not on the reel"));
+        }
+        if let Some(m) = self
+            .materials
+            .keys()
+            .find(|k| !self.reel.iter().any(|i| i.material == **k))
+        {
+            return Err(format!("{m:?} pays but is not on the reel"));
+        }
+        if let Some(j) = self.jackpot {
+            if !self.reel.iter().any(|i| i.item == j) {
+                return Err(format!("The jackpot {j:?} is not on the reel"));
+            }
+        }
+        Ok(())
+    }
+    fn payout(&self, i1: &SlotItem, i2: &SlotItem, i3: &SlotItem) -> (i64, String) {
+        if i1.item == i2.item && i2.item == i3.item {
+            if let Some(p) = self.three.get(&i1.item) {
+                return (*p, format!("Three {}", i1.item.plural()));
+            }
+        }
+        if i1.color == i2.color && i2.color == i3.color {
+            if let Some(p) = self.colors.get(&i1.color) {
+                return (*p, format!("Three {:?}", i1.color));
+            }
+        }
+        if i1.material == i2.material && i2.material == i3.material {
+            if let Some(p) = self.materials.get(&i1.material) {
+                return (*p, format!("Three {}", i1.material.plural()));
+            }
+        }
+        (0, String::new())
+    }
+    fn is_jackpot(&self, i1: &SlotItem, i2: &SlotItem, i3: &SlotItem) -> bool {
+        self.jackpot
+            .is_some_and(|j| [i1, i2, i3].iter().all(|i| i.item == j))
+    }
+}
+
+struct Simulation {
+    spins: u64,
+    loss: u64,
+    neus: u64,
+    wins: u64,
+    /// Paid by the payout table.
+    won: i64,
+    /// Paid by the jackpot, which is fed by the lost spins.
+    jackpot: i64,
+}
+impl Simulation {
+    /// Return to player: how much of the bets is paid back.
+    fn rtp(&self) -> f64 {
+        (self.won + self.jackpot) as f64 / self.spins as f64
+    }
+    /// Return to player with the slots luck buff, which doubles the payouts.
+    fn lucky_rtp(&self) -> f64 {
+        (2 * self.won + self.jackpot) as f64 / self.spins as f64
+    }
+}
+/// Play the slots with a bet of 1. Each reel stops at a random item.
+/// `jackpot_share` percent of every lost bet goes into the jackpot, like in [`WallaceDBClient::play_bet`].
+fn simulate(rules: &SlotRules, spins: u64, jackpot_share: i64, rng: &mut impl Rng) -> Simulation {
+    let mut sim = Simulation {
+        spins,
+        loss: 0,
+        neus: 0,
+        wins: 0,
+        won: 0,
+        jackpot: 0,
+    };
+    // In hundredths, like the jackpot in the database
+    let mut pot = 0;
+    let reel = &rules.reel;
+    for _ in 0..spins {
+        let (i1, i2, i3) = (
+            &reel[rng.gen_range(0..reel.len())],
+            &reel[rng.gen_range(0..reel.len())],
+            &reel[rng.gen_range(0..reel.len())],
+        );
+        let (amount, _) = rules.payout(i1, i2, i3);
+        if amount == 0 {
+            sim.loss += 1;
+            pot += jackpot_share;
+        } else if amount == 1 {
+            sim.neus += 1;
+        } else {
+            sim.wins += 1;
+        }
+        sim.won += amount;
+        if rules.is_jackpot(i1, i2, i3) {
+            sim.jackpot += pot / 100;
+            pot %= 100;
+        }
+    }
+    sim
+}
+
+fn random_wheel(reel: &[SlotItem], rng: &mut impl Rng) -> Vec<SlotItem> {
+    let mut v = reel.to_vec();
+    v.shuffle(rng);
+    v
+}
+type SlotWheels = (Vec<SlotItem>, Vec<SlotItem>, Vec<SlotItem>);
+/// Shuffle the wheels and pick how far each of them spins.
+fn roll_slots(rules: &SlotRules, rng: &mut impl Rng) -> (SlotWheels, [u32; 3]) {
+    let wheels = (
+        random_wheel(&rules.reel, rng),
+        random_wheel(&rules.reel, rng),
+        random_wheel(&rules.reel, rng),
+    );
+    let steps = [
+        rng.gen_range(1..8),
+        rng.gen_range(1..8),
+        rng.gen_range(1..8),
+    ];
+    (wheels, steps)
+}
+/// Where the wheels stop. Wheels keep spinning until the ones to their left have stopped.
+fn slots_counters(steps: &[u32; 3], items: usize) -> [i8; 3] {
+    let mut total = 0;
+    steps.map(|s| {
+        total += s as i64;
+        (-total).rem_euclid(items as i64) as i8
+    })
+}
+fn slots_outcome(wheels: &SlotWheels, counters: &[i8; 3]) -> String {
+    format!(
+        "{}{}{}",
+        wheels.0[counters[0] as usize].item.emoji(),
+        wheels.1[counters[1] as usize].item.emoji(),
+        wheels.2[counters[2] as usize].item.emoji(),
+    )
+}
+pub(super) fn replay_slots(rules: &SlotRules, rng: &mut impl Rng) -> String {
+    let (wheels, steps) = roll_slots(rules, rng);
+    slots_outcome(&wheels, &slots_counters(&steps, rules.reel.len()))
+}
+fn print_slots(vs: &SlotWheels, is: &[i8; 3], locked: u8) -> String {
+    let (v1, v2, v3) = vs;
+    let (i1, i2, i3) = (is[0], is[1], is[2]);
+    let n = v1.len() as i8;
+    let w = '▫';
+    format!(
+        "{}{}{}\n{}{}{}\n{}{}{}",
+        if locked < 1 {
+            v1[((i1 - 1).rem_euclid(n)) as usize].item.emoji()
+        } else {
+            w
+        },
+        if locked < 2 {
+            v2[((i2 - 1).rem_euclid(n)) as usize].item.emoji()
+        } else {
+            w
+        },
+        if locked < 3 {
+            v3[((i3 - 1).rem_euclid(n)) as usize].item.emoji()
+        } else {
+            w
+        },
+        v1[i1 as usize].item.emoji(),
+        v2[i2 as usize].item.emoji(),
+        v3[i3 as usize].item.emoji(),
+        if locked < 1 {
+            v1[((i1 + 1).rem_euclid(n)) as usize].item.emoji()
+        } else {
+            w
+        },
+        if locked < 2 {
+            v2[((i2 + 1).rem_euclid(n)) as usize].item.emoji()
+        } else {
+            w
+        },
+        if locked < 3 {
+            v3[((i3 + 1).rem_euclid(n)) as usize].item.emoji()
+        } else {
+            w
+        },
+    )
+}
+
+/// The slot rules of a guild, and the ID of its slot table if it doesn't use the default.
+async fn guild_slot_rules(ctx: &Context, gid: u64) -> (Option<i32>, SlotRules) {
+    let db = get_db_handler(ctx).await;
+    match db.get_guild_slot_table(gid).await {
+        Ok(Some(t)) => match SlotRules::from_json(&t.rules) {
+            Ok(rules) => (Some(t.id), rules),
+            Err(e) => {
+                warn!("Invalid slot table {}: {e}", t.id);
+                (None, SlotRules::default())
+            }
+        },
+        Ok(None) => (None, SlotRules::default()),
+        Err(e) => {
+            warn!("Failed to get the slot table of {gid}: {e}");
+            (None, SlotRules::default())
+        }
+    }
+}
+
+#[command]
+#[bucket = "slots"]
+#[only_in(guilds)]
+#[sub_commands(rules, propose, activate, reset)]
+#[description(
+    "Try your luck at the casino.
+    Costs 1 𝓚𝓪𝓹𝓼𝔂𝓵, but you can win the jackpot! See what pays with `slots rules`.
+    User must be marked mature to get access."
+)]
+async fn slots(ctx: &Context, msg: &Message) -> CommandResult {
+    let gid = msg.guild_id.unwrap().get();
+    let uid = msg.author.id.get();
+    let db = get_db_handler(ctx).await;
+    if !check_can_gamble(ctx, msg).await {
+        return Ok(());
+    }
+    let (table, rules) = guild_slot_rules(ctx, gid).await;
+    let game = match table {
+        Some(id) => format!("slots {id}"),
+        None => "slots".into(),
+    };
+    // Checked before the spin is started, but only taken together with the payout
+    if let Err(e) = db.has_bank_account_balance(gid, uid, 1).await {
+        let _ = msg.channel_id.say(ctx, e.to_string()).await;
+        return Ok(());
+    }
+    let mut spin = fair_spin(ctx, uid, game).await?;
+    let (wheels, steps) = roll_slots(&rules, &mut spin.rng);
+    let n = rules.reel.len() as i8;
+    let stops = slots_counters(&steps, rules.reel.len());
+    let (i1, i2, i3) = (
+        &wheels.0[stops[0] as usize],
+        &wheels.1[stops[1] as usize],
+        &wheels.2[stops[2] as usize],
+    );
+    let (mut amount, mut result) = rules.payout(i1, i2, i3);
+    if amount > 0
+        && db
+            .has_active_buff(gid, uid, SLOTS_LUCK.id)
+            .await
+            .unwrap_or(false)
+    {
+        amount *= 2;
+        result = format!("🍀 {result}");
+    }
+    let won = match db
+        .play_bet(
+            gid,
+            uid,
+            1,
+            amount,
+            TransactionReason::Slots,
+            Some(rules.is_jackpot(i1, i2, i3)),
+        )
+        .await
+    {
+        Ok(w) => w,
+        Err(e) => {
+            let _ = msg.channel_id.say(ctx, e.to_string()).await;
+            return Ok(());
+        }
+    };
+    let _ = msg.channel_id.send_message(ctx, payment_message(1)).await;
+    let counters: &mut [i8; 3] = &mut [0, 0, 0];
+    let mut m = msg
+        .channel_id
+        .say(ctx, print_slots(&wheels, counters, 0))
+        .await?;
+    for (i, &s) in steps.iter().enumerate() {
+        for _ in 0..s {
+            sleep(DELAY_BETWEEN_EDITS).await;
+            for j in counters.iter_mut().take(3).skip(i) {
+                *j = (*j - 1).rem_euclid(n);
+            }
+            let _ = m
+                .edit(
+                    ctx,
+                    EditMessage::new().content(print_slots(&wheels, counters, i as u8)),
+                )
+                .await;
+        }
+    }
+    sleep(DELAY_BETWEEN_EDITS).await;
+    let _ = m
+        .edit(
+            ctx,
+            EditMessage::new().content(print_slots(&wheels, counters, 3)),
+        )
+        .await;
+    record_outcome(ctx, &spin, slots_outcome(&wheels, counters)).await;
+
+    if amount > 0 {
+        let _ =
+            msg.channel_id
+                .send_message(
+                    ctx,
+                    CreateMessage::new().add_embed(
+                        CreateEmbed::new()
+                            .author(CreateEmbedAuthor::new("Win!").icon_url(
+                                "https://cdn.7tv.app/emote/628d8b64ed0a40a5ec5f4810/1x.gif",
+                            ))
+                            .title(format!("🟩 {result}! Gained {amount} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻!")),
+                    ),
+                )
+                .await;
+    }
+    if won > 0 {
+        let _ =
+            msg.channel_id
+                .send_message(
+                    ctx,
+                    CreateMessage::new().add_embed(
+                        CreateEmbed::new()
+                            .author(CreateEmbedAuthor::new("JACKPOT!").icon_url(
+                                "https://cdn.7tv.app/emote/628d8b64ed0a40a5ec5f4810/1x.gif",
+                            ))
+                            .title(format!(
+                                "👑 {} won the jackpot of {won} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻!",
+                                msg.author.name
+                            )),
+                    ),
+                )
+                .await;
+    }
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[description("Show the reel and payout table of the slot machine in this server, as JSON.")]
+async fn rules(ctx: &Context, msg: &Message) -> CommandResult {
+    let (table, rules) = guild_slot_rules(ctx, msg.guild_id.unwrap().get()).await;
+    let name = match table {
+        Some(id) => format!("Slot table #{id}"),
+        None => "Default slot table".into(),
+    };
+    msg.channel_id
+        .send_message(
+            ctx,
+            CreateMessage::new()
+                .content(name)
+                .add_file(CreateAttachment::bytes(
+                    serde_json::to_string_pretty(&rules)?,
+                    "slots.json",
+                )),
+        )
+        .await?;
+    Ok(())
+}
+
+#[command]
+#[owners_only]
+#[only_in(guilds)]
+#[description(
+    "Propose a slot table for this server, as JSON text or an attached file like the one from `slots rules`.
+    The slots are simulated with it to find the expected return to player, and it can then be activated."
+)]
+#[usage("<json>")]
+async fn propose(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let json = match msg.attachments.first() {
+        Some(a) => String::from_utf8(a.download().await?)?,
+        None => args
+            .rest()
+            .trim()
+            .trim_start_matches("```json")
+            .trim_matches('`')
+            .to_owned(),
+    };
+    let rules = match SlotRules::from_json(&json) {
+        Ok(r) => r,
+        Err(e) => {
+            let _ = msg
+                .channel_id
+                .say(ctx, format!("Invalid slot table: {e}"))
+                .await;
+            return Ok(());
+        }
+    };
+    let _ = msg.react(ctx, '⏳').await;
+    let gid = msg.guild_id.unwrap().get();
+    let db = get_db_handler(ctx).await;
+    let settings = get_guild_settings(ctx, gid).await;
+    let share = settings.jackpot_share as i64;
+    let (rules, sim) = tokio::task::spawn_blocking(move || {
+        let sim = simulate(&rules, SIMULATED_SPINS, share, &mut rand::thread_rng());
+        (rules, sim)
+    })
+    .await?;
+    let id = db
+        .create_slot_table(
+            gid,
+            msg.author.id.get(),
+            serde_json::to_string(&rules)?,
+            sim.rtp(),
+        )
+        .await?;
+    let pct = |n: u64| n as f64 * 100.0 / sim.spins as f64;
+    msg.channel_id
+        .send_message(
+            ctx,
+            CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .title(format!("Slot table #{id}"))
+                    .description(format!(
+                        "Simulated {} spins with a {share}% jackpot share.\nExpected return to player: **{:.2}%** ({:.2}% with 🍀)\nLosses: {:.2}%, Neus: {:.2}%, Wins: {:.2}%\n\nActivate it with `{}slots activate {id}`.",
+                        sim.spins,
+                        sim.rtp() * 100.0,
+                        sim.lucky_rtp() * 100.0,
+                        pct(sim.loss),
+                        pct(sim.neus),
+                        pct(sim.wins),
+                        settings.prefix,
+                    )),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+#[command]
+#[owners_only]
+#[only_in(guilds)]
+#[num_args(1)]
+#[description("Use a proposed slot table in this server.")]
+#[usage("<table>")]
+#[example("3")]
+async fn activate(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let id: i32 = args
+        .current()
+        .unwrap()
+        .trim_start_matches('#')
+        .parse()
+        .map_err(|_| "Invalid table")?;
+    let db = get_db_handler(ctx).await;
+    if let Err(e) = db
+        .set_guild_slot_table(msg.guild_id.unwrap().get(), Some(id))
+        .await
+    {
+        let _ = msg.channel_id.say(ctx, e.to_string()).await;
+        return Ok(());
+    }
+    let _ = msg.react(ctx, '🫡').await;
+    Ok(())
+}
+
+#[command]
+#[owners_only]
+#[only_in(guilds)]
+#[description("Go back to the default slot table in this server.")]
+async fn reset(ctx: &Context, msg: &Message) -> CommandResult {
+    let db = get_db_handler(ctx).await;
+    if let Err(e) = db
+        .set_guild_slot_table(msg.guild_id.unwrap().get(), None)
+        .await
+    {
+        let _ = msg.channel_id.say(ctx, e.to_string()).await;
+        return Ok(());
+    }
+    let _ = msg.react(ctx, '🫡').await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    #[test]
+    fn slots() {
+        let rules = SlotRules::default();
+        let mut rng = StdRng::seed_from_u64(0);
+        let sim = simulate(&rules, 1000000, 0, &mut rng);
+        assert_eq!(sim.spins, sim.loss + sim.neus + sim.wins);
+        assert_eq!(sim.jackpot, 0);
+        // 92.6% from the payout table
+        assert!((0.91..0.94).contains(&sim.rtp()), "{}", sim.rtp());
+        assert!(
+            (1.82..1.88).contains(&sim.lucky_rtp()),
+            "{}",
+            sim.lucky_rtp()
+        );
+        // A share of the 65.3% lost spins is won back through the jackpot
+        let sim = simulate(&rules, 1000000, 10, &mut rng);
+        assert!(sim.jackpot > 0);
+        assert!((0.97..1.01).contains(&sim.rtp()), "{}", sim.rtp());
+    }
+    #[test]
+    fn slots_stop() {
+        // Spin the wheels like the animation does
+        let steps = [3, 7, 1];
+        let mut counters: [i8; 3] = [0; 3];
+        for (i, &n) in steps.iter().enumerate() {
+            for _ in 0..n {
+                for j in counters.iter_mut().skip(i) {
+                    *j = (*j - 1).rem_euclid(SLOT_WHEEL_ITEMS);
+                }
+            }
+        }
+        assert_eq!(slots_counters(&steps, SLOT_WHEEL.len()), counters);
+    }
+    #[test]
+    fn jackpot() {
+        let rules = SlotRules::default();
+        let mut jackpots = 0;
+        for i in &SLOT_WHEEL {
+            for j in &SLOT_WHEEL {
+                for k in &SLOT_WHEEL {
+                    if rules.is_jackpot(i, j, k) {
+                        jackpots += 1;
+                        assert_eq!(rules.payout(i, j, k).0, 333);
+                    }
+                }
+            }
+        }
+        // One in 2197 spins
+        assert_eq!(jackpots, 1);
+    }
+    #[test]
+    fn rules() {
+        let rules = SlotRules::default();
+        let [crown, ring, _, crab, cherry, grapes, ..] = SLOT_WHEEL;
+        assert_eq!(
+            rules.payout(&crown, &crown, &crown),
+            (333, "Three Crowns".into())
+        );
+        assert_eq!(rules.payout(&crab, &cherry, &crab), (8, "Three Red".into()));
+        assert_eq!(
+            rules.payout(&crown, &ring, &crown),
+            (7, "Three Shiny Metal Objects".into())
+        );
+        assert_eq!(
+            rules.payout(&cherry, &grapes, &cherry),
+            (1, "Three Fruits".into())
+        );
+        assert_eq!(rules.payout(&crab, &grapes, &crown).0, 0);
+        // The default rules survive a round trip through JSON
+        let json = serde_json::to_string(&rules).unwrap();
+        assert_eq!(SlotRules::from_json(&json).unwrap().reel, rules.reel);
+    }
+    #[test]
+    fn invalid_rules() {
+        let ok = r#"{"reel": [
+            {"item": "apple", "material": "fruit", "color": "green"},
+            {"item": "pear", "material": "fruit", "color": "green"},
+            {"item": "crab", "material": "none", "color": "red"}
+        ], "three": {"apple": 5}, "colors": {"green": 2}, "materials": {}}"#;
+        assert!(SlotRules::from_json(ok).is_ok());
+        for (from, to) in [
+            (r#""apple": 5"#, r#""crown": 5"#),
+            (r#""apple": 5"#, r#""apple": -5"#),
+            (r#""green": 2"#, r#""none": 2"#),
+            (r#""materials": {}"#, r#""materials": {"metal": 1}"#),
+            (r#""item": "pear""#, r#""item": "banana""#),
+            (r#""materials": {}"#, r#""materials": {}, "extra": 1"#),
+        ] {
+            assert!(SlotRules::from_json(&ok.replace(from, to)).is_err(), "{to}");
+        }
+        let nothing = ok.replace(r#""apple": 5"#, r#""apple": 0"#);
+        assert!(SlotRules::from_json(&nothing.replace(r#""green": 2"#, r#""green": 0"#)).is_err());
+    }
+}
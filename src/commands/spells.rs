@@ -1,4 +1,7 @@
-use std::{fmt::Display, time::Duration};
+use std::{
+    fmt::{Display, Write},
+    time::Duration,
+};
 
 use rand::{rngs::StdRng, Rng, SeedableRng};
 use serenity::{
@@ -12,10 +15,18 @@ use serenity::{
     utils::parse_user_mention,
 };
 
+use super::{
+    fair::{fair_spin, record_outcome},
+    items::{BONK_SHIELD, WALLACE_HAMMER},
+};
 use crate::{
     database::WallaceDBClient,
-    discord::get_db_handler,
-    services::{bonk_user, do_payment, nickname_user, set_server_name, unbonk_user},
+    discord::{get_db_handler, get_guild_settings},
+    model::{SpellSettings, TransactionReason},
+    services::{
+        bonk_defense, bonk_user, check_can_gamble, do_payment, nickname_user, set_server_name,
+        unbonk_user, Bonk, BonkDefense,
+    },
 };
 
 #[group]
@@ -23,6 +34,7 @@ use crate::{
     bonk,
     gamba,
     hammer,
+    bonkstats,
     unbonk,
     nickname,
     defaultname,
@@ -49,14 +61,84 @@ impl Display for SpellPrice {
         )
     }
 }
-pub static SHOPPABLE_SPELLS_AND_PRICES: &[(&Command, SpellPrice)] = &[
-    (&GAMBA_COMMAND, SpellPrice::AtLeast(1)),
-    (&UNBONK_COMMAND, SpellPrice::Cost(UNBONK_COST)),
-    (&NICKNAME_COMMAND, SpellPrice::Cost(NICKNAME_COST)),
-    (&DEFAULTNAME_COMMAND, SpellPrice::Free),
-    (&SERVERNAME_COMMAND, SpellPrice::Cost(SERVERNAME_COST)),
+impl SpellPrice {
+    fn cost(cost: i64) -> Self {
+        if cost == 0 {
+            SpellPrice::Free
+        } else {
+            SpellPrice::Cost(cost)
+        }
+    }
+}
+/// The spells in the shop with their prices in a server. Disabled spells are left out.
+pub fn shoppable_spells(settings: &SpellSettings) -> Vec<(&'static Command, SpellPrice)> {
+    [
+        (&GAMBA_COMMAND, SpellPrice::AtLeast(settings.gamba_min_bet)),
+        (&UNBONK_COMMAND, SpellPrice::cost(settings.unbonk_cost)),
+        (&NICKNAME_COMMAND, SpellPrice::cost(settings.nickname_cost)),
+        (&DEFAULTNAME_COMMAND, SpellPrice::Free),
+        (
+            &SERVERNAME_COMMAND,
+            SpellPrice::cost(settings.servername_cost),
+        ),
+    ]
+    .into_iter()
+    .filter(|(c, _)| !settings.is_disabled(c.options.names[0]))
+    .collect()
+}
+/// The spells that can be disabled in a server.
+pub static CONFIGURABLE_SPELLS: &[&Command] = &[
+    &GAMBA_COMMAND,
+    &UNBONK_COMMAND,
+    &NICKNAME_COMMAND,
+    &DEFAULTNAME_COMMAND,
+    &SERVERNAME_COMMAND,
+];
+/// Gamba sizes with their chance modifier and bonk time in seconds.
+pub const GAMBA_SIZES: &[(&str, f32, u32)] = &[
+    ("S", 1.00, 60),
+    ("M", 0.92, 240),
+    ("L", 0.85, 900),
+    ("XL", 0.75, 3600),
+    ("XXL", 0.54, 4 * 3600),
 ];
 
+/// Find a gamba size by name.
+pub fn gamba_size(name: &str) -> Option<usize> {
+    GAMBA_SIZES
+        .iter()
+        .position(|(s, ..)| s.eq_ignore_ascii_case(name))
+}
+
+/// Get the spell settings of the server, or tell the caster that the spell is disabled.
+async fn spell_settings(ctx: &Context, msg: &Message, spell: &Command) -> Option<SpellSettings> {
+    let guild_settings = get_guild_settings(ctx, msg.guild_id.unwrap().get()).await;
+    let settings = guild_settings.spells.clone();
+    let name = spell.options.names[0];
+    if settings.is_disabled(name) {
+        let _ = msg
+            .channel_id
+            .say(
+                ctx,
+                format!(
+                    "`{}{name}` is disabled in this server 😔",
+                    guild_settings.prefix
+                ),
+            )
+            .await;
+        return None;
+    }
+    Some(settings)
+}
+
+/// Pay for a spell, unless it is free in this server. Returns false if the payment failed.
+async fn pay_spell(ctx: &Context, msg: &Message, cost: i64) -> bool {
+    cost == 0
+        || do_payment(ctx, msg, cost, TransactionReason::Spell)
+            .await
+            .is_ok()
+}
+
 #[command]
 #[aliases(timeout)]
 #[min_args(1)]
@@ -66,7 +148,13 @@ pub static SHOPPABLE_SPELLS_AND_PRICES: &[(&Command, SpellPrice)] = &[
 async fn bonk(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     for arg in args.iter::<String>().map(|a| a.unwrap()) {
         let uid = parse_user_mention(&arg).ok_or("Invalid user tag")?.get();
-        bonk_user(ctx, msg, uid, 60).await?;
+        let bonk = Bonk {
+            target: uid,
+            size: None,
+            chance: None,
+            cost: 0,
+        };
+        bonk_user(ctx, msg, uid, 60, bonk).await?;
     }
     Ok(())
 }
@@ -78,48 +166,93 @@ async fn bonk(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
 #[description(
     "Summon mods in chat to start the GAMBA. Tag a user and they might get bonked. Or you.
     A larger size increases bonk time, but reduces chance of success.
-    A higher bet increases the chance of success."
+    A higher bet increases the chance of success.
+    User must be marked mature to get access."
 )]
 #[usage("[S|M|L|XL|XXL] [amount] <user>")]
 #[example("@Yxaria")]
 #[example("M 5 @Yxaria")]
 #[example("XXL 30 @Yxaria")]
 async fn gamba(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
-    let a = args.current().unwrap();
-    let default: (f32, &str, u32) = (1.00, "S", 60);
-    let stats: Option<(f32, &str, u32)> = match a.to_ascii_uppercase().as_str() {
-        "S" => Some(default),
-        "M" => Some((0.92, "M", 240)),
-        "L" => Some((0.85, "L", 900)),
-        "XL" => Some((0.75, "XL", 3600)),
-        "XXL" => Some((0.54, "XXL", 4 * 3600)),
-        _ => None,
+    let Some(settings) = spell_settings(ctx, msg, &GAMBA_COMMAND).await else {
+        return Ok(());
     };
-    let (modifier, size, duration) = if let Some((m, s, d)) = stats {
-        args.advance();
-        (m, s, d)
-    } else {
-        default
+    let a = args.current().unwrap();
+    let i = match gamba_size(a) {
+        Some(i) => {
+            args.advance();
+            i
+        }
+        None => 0,
     };
+    let max = gamba_size(&settings.max_gamba_size).unwrap_or(GAMBA_SIZES.len() - 1);
+    if i > max {
+        let _ = msg
+            .channel_id
+            .say(
+                ctx,
+                format!(
+                    "The biggest gamba size in this server is {} 😔",
+                    GAMBA_SIZES[max].0
+                ),
+            )
+            .await;
+        return Ok(());
+    }
+    let (size, modifier, duration) = GAMBA_SIZES[i];
     let a = args.current().ok_or("Not enough arguments")?;
     let amount: i64 = match a.parse() {
         Ok(n) => {
             args.advance();
             n
         }
-        Err(_) => 1,
+        Err(_) => settings.gamba_min_bet,
     };
+    if amount < settings.gamba_min_bet {
+        let _ = msg
+            .channel_id
+            .say(
+                ctx,
+                format!(
+                    "The smallest gamba bet in this server is {} 𝓚𝓪𝓹𝓼𝔂𝓵𝓮𝓻",
+                    settings.gamba_min_bet
+                ),
+            )
+            .await;
+        return Ok(());
+    }
 
     let a = args.current().ok_or("Not enough arguments")?;
     let target_uid = parse_user_mention(a).ok_or("Invalid user tag")?.get();
     let uid = msg.author.id.get();
 
-    if do_payment(ctx, msg, amount).await.is_err() {
+    // Don't take bets on bonks that will be blocked. Counter-spells stay a surprise.
+    if target_uid != uid
+        && bonk_defense(ctx, msg.guild_id.unwrap().get(), target_uid).await
+            == Some(BonkDefense::Blocked)
+    {
+        let _ = msg
+            .channel_id
+            .say(
+                ctx,
+                format!("The mods refuse, they have a {} 🛡️", BONK_SHIELD.name),
+            )
+            .await;
         return Ok(());
     }
 
     // m * 35 * a^(1/3)-11 bounded to [1, 100]
     let chance = ((modifier * 35.0 * (amount as f32).powf(1.0 / 3.0) - 11.0) as u32).clamp(1, 100);
+    if !check_can_gamble(ctx, msg).await {
+        return Ok(());
+    }
+    let mut spin = fair_spin(ctx, uid, format!("gamba {chance}")).await?;
+    if do_payment(ctx, msg, amount, TransactionReason::Gamba)
+        .await
+        .is_err()
+    {
+        return Ok(());
+    }
     let _ = msg
         .channel_id
         .send_message(
@@ -135,29 +268,130 @@ async fn gamba(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
         )
         .await;
     tokio::time::sleep(Duration::from_secs(1)).await;
-    let mut rng: StdRng = SeedableRng::from_entropy();
-    let who = if rng.gen_ratio(chance, 100) {
+    let hit = gamba_hits(&mut spin.rng, chance);
+    record_outcome(ctx, &spin, gamba_outcome(hit).into()).await;
+    let who = if hit {
         target_uid // Win
     } else {
         uid // Loss
     };
-    bonk_user(ctx, msg, who, duration).await?;
+    let bonk = Bonk {
+        target: target_uid,
+        size: Some(size),
+        chance: Some(chance),
+        cost: amount,
+    };
+    bonk_user(ctx, msg, who, duration, bonk).await?;
     Ok(())
 }
 
+fn gamba_hits(rng: &mut impl Rng, chance: u32) -> bool {
+    rng.gen_ratio(chance, 100)
+}
+fn gamba_outcome(hit: bool) -> &'static str {
+    if hit {
+        "hit"
+    } else {
+        "backfire"
+    }
+}
+pub(super) fn replay_gamba(rng: &mut impl Rng, chance: u32) -> String {
+    gamba_outcome(gamba_hits(rng, chance)).into()
+}
+
 #[command]
 #[num_args(1)]
 #[only_in(guilds)]
-#[description("Bonk a user. Requires a **Wallace Hammer**.")]
-async fn hammer(ctx: &Context, msg: &Message, _args: Args) -> CommandResult {
-    let _ = msg.channel_id.say(ctx, "You have no hammer 😠").await;
-    return Ok(());
-    // let uid = parse_user_mention(args.current().unwrap()).ok_or("Invalid user tag")?;
-    // bonk_user(ctx, msg, uid, 60).await?;
-    // Ok(())
+#[description(
+    "Bonk a user. Requires a **Wallace Hammer**, which breaks even if the bonk is blocked."
+)]
+#[usage("<user>")]
+#[example("@Yxaria")]
+async fn hammer(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let uid = parse_user_mention(args.current().unwrap())
+        .ok_or("Invalid user tag")?
+        .get();
+    let gid = msg.guild_id.unwrap().get();
+    let db = get_db_handler(ctx).await;
+    // Taken before the bonk, so that one hammer can't be swung twice at once
+    if db
+        .take_inventory_item(gid, msg.author.id.get(), WALLACE_HAMMER.id)
+        .await
+        .is_err()
+    {
+        let _ = msg.channel_id.say(ctx, "You have no hammer 😠").await;
+        return Ok(());
+    }
+    let bonk = Bonk {
+        target: uid,
+        size: None,
+        chance: None,
+        cost: WALLACE_HAMMER.price,
+    };
+    // A blocked bonk still breaks the hammer, but one that could not be done gives it back
+    let res = bonk_user(ctx, msg, uid, 60, bonk).await;
+    if !matches!(res, Ok(true)) {
+        db.give_inventory_item(gid, msg.author.id.get(), WALLACE_HAMMER.id)
+            .await?;
+    }
+    res.map(|_| ())
+}
+
+#[command]
+#[max_args(1)]
+#[only_in(guilds)]
+#[description("Show bonk stats for you or another user, and who gets bonked the most.")]
+#[usage("[user]")]
+#[example("@Yxaria")]
+async fn bonkstats(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let uid = match args.current() {
+        Some(a) => parse_user_mention(a).ok_or("Invalid user tag")?.get(),
+        None => msg.author.id.get(),
+    };
+    let gid = msg.guild_id.unwrap();
+    let db = get_db_handler(ctx).await;
+    let stats = db.get_bonk_stats(gid.get(), uid).await?;
+    let mut top = String::new();
+    for (i, (victim, count)) in db
+        .get_most_bonked_in_guild(gid.get(), 10)
+        .await?
+        .into_iter()
+        .enumerate()
+    {
+        let name = gid
+            .member(ctx, victim as u64)
+            .await
+            .map(|m| m.nick.unwrap_or(m.user.name))
+            .unwrap_or_else(|_| "?".into());
+        writeln!(&mut top, "`{:>2}. {:<20} {:>4}`🔨", i + 1, name, count).unwrap();
+    }
+    let uname = gid
+        .member(ctx, uid)
+        .await
+        .map(|m| m.nick.unwrap_or(m.user.name))
+        .unwrap_or_else(|_| "?".into());
+    msg.channel_id
+        .send_message(
+            ctx,
+            CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .author(
+                        CreateEmbedAuthor::new(format!("Bonk stats for {uname}"))
+                            .icon_url("https://cdn.7tv.app/emote/60aea79d4b1ea4526d9b20a9/1x.gif"),
+                    )
+                    .field("Bonked", stats.bonked.to_string(), true)
+                    .field("Backfired", stats.backfired.to_string(), true)
+                    .field("\u{200b}", "", true)
+                    .field("Timeout dealt", format!("{}s", stats.dealt), true)
+                    .field("Timeout received", format!("{}s", stats.received), true)
+                    .field("\u{200b}", "", true)
+                    .field("Most bonked", top, false),
+            ),
+        )
+        .await?;
+    Ok(())
 }
 
-pub const UNBONK_COST: i64 = 2;
 #[command]
 #[aliases(unhammer, untimeout)]
 #[num_args(1)]
@@ -169,13 +403,15 @@ async fn unbonk(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
     let uid = parse_user_mention(args.current().unwrap())
         .ok_or("Invalid user tag")?
         .get();
-    if do_payment(ctx, msg, UNBONK_COST).await.is_err() {
+    let Some(settings) = spell_settings(ctx, msg, &UNBONK_COMMAND).await else {
+        return Ok(());
+    };
+    if !pay_spell(ctx, msg, settings.unbonk_cost).await {
         return Ok(());
     }
     unbonk_user(ctx, msg, uid).await
 }
 
-pub const NICKNAME_COST: i64 = 1;
 #[command]
 #[aliases(nick)]
 #[min_args(2)]
@@ -193,7 +429,10 @@ async fn nickname(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult
         let _ = msg.channel_id.say(ctx, "Nickname too long").await;
         return Ok(());
     }
-    if do_payment(ctx, msg, NICKNAME_COST).await.is_err() {
+    let Some(settings) = spell_settings(ctx, msg, &NICKNAME_COMMAND).await else {
+        return Ok(());
+    };
+    if !pay_spell(ctx, msg, settings.nickname_cost).await {
         return Ok(());
     }
     nickname_user(ctx, msg, uid, nick.to_owned()).await
@@ -204,6 +443,12 @@ async fn nickname(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult
 #[only_in(guilds)]
 #[description("Set the server name to the default.")]
 async fn defaultname(ctx: &Context, msg: &Message) -> CommandResult {
+    if spell_settings(ctx, msg, &DEFAULTNAME_COMMAND)
+        .await
+        .is_none()
+    {
+        return Ok(());
+    }
     let db = get_db_handler(ctx).await;
     let guild = msg.guild(&ctx.cache).unwrap().to_owned();
     let guild_id = guild.id;
@@ -232,7 +477,6 @@ async fn set(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     Ok(())
 }
 
-pub const SERVERNAME_COST: i64 = 3;
 #[command]
 #[num_args(1)]
 #[only_in(guilds)]
@@ -241,7 +485,10 @@ pub const SERVERNAME_COST: i64 = 3;
 #[example("Cool Chicken Club")]
 async fn servername(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
     let name = args.rest();
-    if do_payment(ctx, msg, SERVERNAME_COST).await.is_err() {
+    let Some(settings) = spell_settings(ctx, msg, &SERVERNAME_COMMAND).await else {
+        return Ok(());
+    };
+    if !pay_spell(ctx, msg, settings.servername_cost).await {
         return Ok(());
     }
     let guild = msg.guild(&ctx.cache).unwrap().to_owned();
@@ -1,8 +1,14 @@
 use anyhow::{anyhow, Error, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use sqlx::{Acquire, PgConnection, PgPool};
 use tracing::warn;
 
-use crate::model::{LoLAccount, Task, User};
+use crate::model::{
+    daily_streak, ActiveBuff, BankAccount, BankTransaction, BetPool, BetWager, BonkEvent,
+    BonkStats, CatchUp, DailyClaim, DailyCurve, FairSeed, FairSpin, GuildSettings, HeldBet,
+    InventoryItem, Jackpot, LoLAccount, Loan, MarketListing, SlotTable, Task, TradeGoods,
+    TransactionReason,
+};
 
 fn log_error(err: impl std::error::Error, msg: &'static str) -> Error {
     warn!("Database error: {err}");
@@ -14,18 +20,179 @@ fn positive(amount: i64) -> Result<()> {
     }
     Ok(())
 }
+/// Change a balance and record it in the ledger in the same transaction.
+async fn change_balance(
+    conn: &mut PgConnection,
+    guild_id: u64,
+    user_id: u64,
+    counterparty_id: Option<u64>,
+    amount: i64,
+    reason: TransactionReason,
+) -> Result<()> {
+    let mut trx = conn.begin().await?;
+    let res = sqlx::query(
+        "UPDATE bank_account SET balance = balance + $1 WHERE guild_id = $2 AND user_id = $3",
+    )
+    .bind(amount)
+    .bind(guild_id as i64)
+    .bind(user_id as i64)
+    .execute(trx.as_mut())
+    .await
+    .map_err(|q| log_error(q, "Failed to update balance"))?;
+    if res.rows_affected() == 0 {
+        return Err(anyhow!("No bank account"));
+    }
+    sqlx::query(
+        "INSERT INTO bank_transaction (guild_id, user_id, counterparty_id, amount, reason) VALUES ($1, $2, $3, $4, $5)",
+    )
+    .bind(guild_id as i64)
+    .bind(user_id as i64)
+    .bind(counterparty_id.map(|id| id as i64))
+    .bind(amount)
+    .bind(reason.to_string())
+    .execute(trx.as_mut())
+    .await
+    .map_err(|q| log_error(q, "Failed to record transaction"))?;
+    trx.commit().await?;
+    Ok(())
+}
+/// Add items to an inventory.
+async fn add_items(
+    conn: &mut PgConnection,
+    guild_id: u64,
+    user_id: u64,
+    item: &str,
+    amount: i32,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO inventory_item (guild_id, user_id, item, amount) VALUES ($1, $2, $3, $4) ON CONFLICT (guild_id, user_id, item) DO UPDATE SET amount = inventory_item.amount + EXCLUDED.amount",
+    )
+    .bind(guild_id as i64)
+    .bind(user_id as i64)
+    .bind(item)
+    .bind(amount)
+    .execute(conn)
+    .await
+    .map(|_| ())
+    .map_err(|q| log_error(q, "Failed to add item"))
+}
+/// Remove items from an inventory, if there are enough of them.
+async fn remove_items(
+    conn: &mut PgConnection,
+    guild_id: u64,
+    user_id: u64,
+    item: &str,
+    amount: i32,
+) -> Result<()> {
+    let mut trx = conn.begin().await?;
+    let res = sqlx::query(
+        "UPDATE inventory_item SET amount = amount - $4 WHERE guild_id = $1 AND user_id = $2 AND item = $3 AND amount >= $4",
+    )
+    .bind(guild_id as i64)
+    .bind(user_id as i64)
+    .bind(item)
+    .bind(amount)
+    .execute(trx.as_mut())
+    .await
+    .map_err(|q| log_error(q, "Failed to update inventory"))?;
+    if res.rows_affected() == 0 {
+        return Err(anyhow!("Not enough of that item"));
+    }
+    sqlx::query(
+        "DELETE FROM inventory_item WHERE guild_id = $1 AND user_id = $2 AND item = $3 AND amount <= 0",
+    )
+    .bind(guild_id as i64)
+    .bind(user_id as i64)
+    .bind(item)
+    .execute(trx.as_mut())
+    .await
+    .map_err(|q| log_error(q, "Failed to update inventory"))?;
+    trx.commit().await?;
+    Ok(())
+}
+/// Lock bank accounts until the end of the transaction, so that balances can't change under it.
+async fn lock_bank_accounts(
+    conn: &mut PgConnection,
+    guild_id: u64,
+    user_ids: &[u64],
+) -> Result<()> {
+    let ids: Vec<i64> = user_ids.iter().map(|id| *id as i64).collect();
+    let locked = sqlx::query(
+        "SELECT 1 FROM bank_account WHERE guild_id = $1 AND user_id = ANY($2) ORDER BY user_id FOR UPDATE",
+    )
+    .bind(guild_id as i64)
+    .bind(&ids)
+    .fetch_all(conn)
+    .await
+    .map_err(|q| log_error(q, "Failed to get bank account"))?;
+    if locked.len() != user_ids.len() {
+        return Err(anyhow!("No bank account"));
+    }
+    Ok(())
+}
+
+/// Pay out a finished bet. With `jackpot` set, a lost bet feeds the jackpot,
+/// and the jackpot is won if it is `Some(true)`. Returns the jackpot won.
+async fn pay_out_bet(
+    conn: &mut PgConnection,
+    guild_id: u64,
+    user_id: u64,
+    bet: i64,
+    payout: i64,
+    reason: TransactionReason,
+    jackpot: Option<bool>,
+) -> Result<i64> {
+    if payout > 0 {
+        change_balance(conn, guild_id, user_id, None, payout, reason).await?;
+    } else if jackpot.is_some() {
+        // The share is in percent, and the pot in hundredths
+        sqlx::query(
+            "INSERT INTO jackpot (guild_id, pot) VALUES ($1, $2 * COALESCE((SELECT jackpot_share FROM guild_settings WHERE guild_id = $1), 10)) ON CONFLICT (guild_id) DO UPDATE SET pot = jackpot.pot + EXCLUDED.pot",
+        )
+        .bind(guild_id as i64)
+        .bind(bet)
+        .execute(&mut *conn)
+        .await
+        .map_err(|q| log_error(q, "Failed to update jackpot"))?;
+    }
+    if jackpot != Some(true) {
+        return Ok(0);
+    }
+    let won = sqlx::query_as::<_, (i64,)>(
+        "UPDATE jackpot SET pot = pot % 100, winner_id = $2, won = pot / 100, won_at = now() WHERE guild_id = $1 AND pot >= 100 RETURNING won",
+    )
+    .bind(guild_id as i64)
+    .bind(user_id as i64)
+    .fetch_optional(&mut *conn)
+    .await
+    .map(|j| j.map(|j| j.0).unwrap_or(0))
+    .map_err(|q| log_error(q, "Failed to update jackpot"))?;
+    if won > 0 {
+        change_balance(
+            conn,
+            guild_id,
+            user_id,
+            None,
+            won,
+            TransactionReason::Jackpot,
+        )
+        .await?;
+    }
+    Ok(won)
+}
 
 pub trait WallaceDBClient {
     async fn upsert_guild(self, id: u64) -> Result<()>;
     async fn set_guild_default_name(self, id: u64, value: String) -> Result<()>;
     async fn get_guild_default_name(self, id: u64) -> Result<String>;
+    async fn set_guild_settings(self, id: u64, settings: &GuildSettings) -> Result<()>;
+    async fn get_guild_settings(self, id: u64) -> Result<GuildSettings>;
     async fn get_guild_random_names(self, id: u64) -> Result<(Vec<String>, Vec<String>)>;
     async fn add_guild_random_name_subject(self, id: u64, value: String) -> Result<()>;
     async fn add_guild_random_name_object(self, id: u64, value: String) -> Result<()>;
     async fn upsert_user(self, id: u64) -> Result<()>;
     async fn get_user_mature(self, id: u64) -> Result<bool>;
     async fn set_user_mature(self, id: u64, mature: bool) -> Result<()>;
-    async fn get_all_users(self) -> Result<Vec<User>>;
     async fn create_lol_account(
         self,
         server: String,
@@ -35,18 +202,234 @@ pub trait WallaceDBClient {
     ) -> Result<()>;
     async fn delete_lol_account(self, server: String, name: String, tag: String) -> Result<()>;
     async fn get_all_lol_accounts_in_user(self, id: u64) -> Result<Vec<LoLAccount>>;
-    async fn create_bank_account(self, user_id: u64) -> Result<()>;
-    async fn delete_bank_account(self, user_id: u64) -> Result<()>;
-    async fn get_bank_account_balance(self, user_id: u64) -> Result<i64>;
-    async fn has_bank_account_balance(self, user_id: u64, amount: i64) -> Result<()>;
-    async fn add_bank_account_balance(self, user_id: u64, amount: i64) -> Result<()>;
-    async fn subtract_bank_account_balance(self, user_id: u64, amount: i64) -> Result<()>;
+    async fn create_bank_account(self, guild_id: u64, user_id: u64) -> Result<()>;
+    async fn delete_bank_account(self, guild_id: u64, user_id: u64) -> Result<()>;
+    async fn get_bank_account_balance(self, guild_id: u64, user_id: u64) -> Result<i64>;
+    async fn has_bank_account_balance(self, guild_id: u64, user_id: u64, amount: i64)
+        -> Result<()>;
+    async fn add_bank_account_balance(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        amount: i64,
+        reason: TransactionReason,
+    ) -> Result<()>;
+    async fn subtract_bank_account_balance(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        amount: i64,
+        reason: TransactionReason,
+    ) -> Result<()>;
     async fn transfer_bank_account_balance(
         self,
+        guild_id: u64,
         from_user_id: u64,
         to_user_id: u64,
         amount: i64,
+        reason: TransactionReason,
+    ) -> Result<()>;
+    async fn get_bank_transactions(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<BankTransaction>>;
+    /// Pay every bank account for the given week, minus what goes to repaying loans.
+    /// Guilds that don't set their own payout get `amount`. Returns false if the week was already paid.
+    async fn pay_weekly_payout(self, week: NaiveDate, amount: i64) -> Result<bool>;
+    /// Claim the daily reward, if a day has passed since the last claim.
+    /// Opens a bank account for users that don't have one yet.
+    async fn claim_daily(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        curve: &DailyCurve,
+    ) -> Result<DailyClaim>;
+    /// Credit a batch of `(guild, user, amount)` activity earnings. Opens bank accounts for users that don't have one yet.
+    async fn add_activity_earnings(self, earnings: &[(u64, u64, i64)]) -> Result<()>;
+    async fn get_loan(self, guild_id: u64, user_id: u64) -> Result<Loan>;
+    /// Borrow from the bank, as long as the debt stays within `limit`.
+    /// A new loan is due at `due_at`.
+    async fn take_loan(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        amount: i64,
+        limit: i64,
+        due_at: DateTime<Utc>,
+    ) -> Result<()>;
+    /// Repay up to `amount` of a loan, or all of it. Returns the amount repaid.
+    async fn repay_loan(self, guild_id: u64, user_id: u64, amount: Option<i64>) -> Result<i64>;
+    /// Add interest to all loans for the given day, and mark overdue loans as defaulted.
+    /// Returns false if interest was already added that day.
+    async fn accrue_loan_interest(self, day: NaiveDate, percent: i64) -> Result<bool>;
+    async fn get_jackpot(self, guild_id: u64) -> Result<Jackpot>;
+    /// Take a bet from the user's balance and pay out `payout` for it, in one transaction.
+    /// With `jackpot` set, a lost bet feeds the jackpot, and the jackpot is won if it is `Some(true)`.
+    /// Returns the jackpot won.
+    async fn play_bet(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        bet: i64,
+        payout: i64,
+        reason: TransactionReason,
+        jackpot: Option<bool>,
+    ) -> Result<i64>;
+    /// Take a bet from the user's balance, and hold it until the game is over. Returns the id of the held bet.
+    async fn hold_bet(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        amount: i64,
+        reason: TransactionReason,
+    ) -> Result<i32>;
+    /// Add more from the user's balance to a held bet.
+    async fn raise_held_bet(self, id: i32, amount: i64) -> Result<()>;
+    /// Stop holding a bet, and pay out `payout` for it.
+    /// With `jackpot` set, a lost bet feeds the jackpot, and the jackpot is won if it is `Some(true)`.
+    /// Returns the jackpot won.
+    async fn settle_held_bet(self, id: i32, payout: i64, jackpot: Option<bool>) -> Result<i64>;
+    /// Give held bets back, or all of them if `ids` is `None`. Returns how many were given back.
+    async fn refund_held_bets(self, ids: Option<&[i32]>) -> Result<usize>;
+    async fn get_top_bank_accounts_in_guild(
+        self,
+        guild_id: u64,
+        limit: i64,
+    ) -> Result<Vec<BankAccount>>;
+    async fn get_fair_seed(self, user_id: u64) -> Result<Option<FairSeed>>;
+    /// Get the seeds of a user, or start using the given ones if there are none.
+    async fn get_or_create_fair_seed(
+        self,
+        user_id: u64,
+        server_seed: String,
+        client_seed: String,
+    ) -> Result<FairSeed>;
+    /// Replace the seeds of a user. Returns the old seeds, which can now be shown.
+    async fn reveal_fair_seed(
+        self,
+        user_id: u64,
+        server_seed: String,
+        client_seed: String,
+    ) -> Result<Option<FairSeed>>;
+    /// Record a spin with the next nonce of the user's seeds.
+    /// The given seeds are used if the user has none.
+    async fn create_fair_spin(
+        self,
+        user_id: u64,
+        game: String,
+        server_seed: String,
+        client_seed: String,
+    ) -> Result<FairSpin>;
+    async fn set_fair_spin_outcome(self, id: i32, outcome: String) -> Result<()>;
+    async fn get_fair_spin(self, id: i32) -> Result<Option<FairSpin>>;
+    async fn get_fair_spins(self, user_id: u64, limit: i64) -> Result<Vec<FairSpin>>;
+    async fn create_slot_table(
+        self,
+        guild_id: u64,
+        creator_id: u64,
+        rules: String,
+        rtp: f64,
+    ) -> Result<i32>;
+    async fn get_slot_table(self, id: i32) -> Result<Option<SlotTable>>;
+    /// The slot table that is active in a guild, if any.
+    async fn get_guild_slot_table(self, guild_id: u64) -> Result<Option<SlotTable>>;
+    /// Activate a slot table of the guild, or go back to the default with `None`.
+    async fn set_guild_slot_table(self, guild_id: u64, id: Option<i32>) -> Result<()>;
+    async fn create_bet_pool(
+        self,
+        guild_id: u64,
+        creator_id: u64,
+        question: String,
+        options: Vec<String>,
+        closes_at: DateTime<Utc>,
+    ) -> Result<i32>;
+    async fn get_bet_pool(self, guild_id: u64, id: i32) -> Result<Option<BetPool>>;
+    async fn get_unresolved_bet_pools(self, guild_id: u64) -> Result<Vec<BetPool>>;
+    async fn get_bet_wagers(self, pool_id: i32) -> Result<Vec<BetWager>>;
+    /// Move a bet from the user's balance into an open pool.
+    /// Betting more on the same option adds to the bet.
+    async fn place_bet_wager(
+        self,
+        guild_id: u64,
+        pool_id: i32,
+        user_id: u64,
+        option: i16,
+        amount: i64,
+    ) -> Result<()>;
+    /// Pay out a pool to the bets on `winner`, or refund everyone with `None`.
+    /// A winner can only be picked after the pool has closed. Returns what each user got.
+    async fn resolve_bet_pool(
+        self,
+        guild_id: u64,
+        pool_id: i32,
+        winner: Option<i16>,
+    ) -> Result<Vec<(i64, i64)>>;
+    async fn buy_item(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        item: &str,
+        amount: i32,
+        price: i64,
     ) -> Result<()>;
+    async fn get_inventory(self, guild_id: u64, user_id: u64) -> Result<Vec<InventoryItem>>;
+    async fn take_inventory_item(self, guild_id: u64, user_id: u64, item: &str) -> Result<()>;
+    async fn give_inventory_item(self, guild_id: u64, user_id: u64, item: &str) -> Result<()>;
+    /// Put items from the inventory up for sale. Returns the listing id.
+    async fn create_market_listing(
+        self,
+        guild_id: u64,
+        seller_id: u64,
+        item: &str,
+        amount: i32,
+        price: i64,
+    ) -> Result<i32>;
+    async fn get_market_listings(self, guild_id: u64) -> Result<Vec<MarketListing>>;
+    /// Pay the seller and move the items to the buyer.
+    async fn buy_market_listing(
+        self,
+        guild_id: u64,
+        buyer_id: u64,
+        id: i32,
+    ) -> Result<MarketListing>;
+    /// Take down a listing and give the items back to the seller.
+    async fn cancel_market_listing(
+        self,
+        guild_id: u64,
+        seller_id: u64,
+        id: i32,
+    ) -> Result<MarketListing>;
+    /// Swap goods between two users. Nothing changes hands unless both sides have what they give.
+    async fn trade(
+        self,
+        guild_id: u64,
+        a_id: u64,
+        a_gives: &TradeGoods,
+        b_id: u64,
+        b_gives: &TradeGoods,
+    ) -> Result<()>;
+    async fn activate_buff(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        buff: &str,
+        duration: Duration,
+    ) -> Result<DateTime<Utc>>;
+    async fn use_buff_item(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        item: &str,
+        duration: Duration,
+    ) -> Result<DateTime<Utc>>;
+    async fn get_active_buffs(self, guild_id: u64, user_id: u64) -> Result<Vec<ActiveBuff>>;
+    async fn has_active_buff(self, guild_id: u64, user_id: u64, buff: &str) -> Result<bool>;
+    async fn create_bonk_event(self, event: BonkEvent) -> Result<()>;
+    async fn get_bonk_stats(self, guild_id: u64, user_id: u64) -> Result<BonkStats>;
+    async fn get_most_bonked_in_guild(self, guild_id: u64, limit: i64) -> Result<Vec<(i64, i64)>>;
     async fn upsert_channel(self, id: u64) -> Result<()>;
     async fn create_task(
         self,
@@ -54,10 +437,22 @@ pub trait WallaceDBClient {
         cmd: String,
         arg: Option<String>,
         channel_id: u64,
-    ) -> Result<()>;
+        creator_id: u64,
+        timezone: Option<String>,
+    ) -> Result<Task>;
+    async fn get_task(self, id: i32) -> Result<Option<Task>>;
+    /// Update the schedule, command and description of a task.
+    async fn update_task(self, task: &Task) -> Result<Task>;
     async fn delete_task(self, id: i32) -> Result<()>;
+    async fn set_task_enabled(self, id: i32, enabled: bool) -> Result<Task>;
+    async fn set_task_result(self, id: i32, result: String) -> Result<()>;
+    async fn set_task_last_run(self, id: i32, last_run: DateTime<Utc>) -> Result<()>;
+    async fn set_task_catch_up(self, id: i32, catch_up: CatchUp) -> Result<()>;
     async fn get_all_tasks(self) -> Result<Vec<Task>>;
     async fn get_all_tasks_in_channel(self, id: u64) -> Result<Vec<Task>>;
+    async fn get_all_tasks_in_channels(self, ids: Vec<u64>) -> Result<Vec<Task>>;
+    /// Delete the tasks in a channel. Returns the IDs of the deleted tasks.
+    async fn delete_tasks_in_channel(self, id: u64) -> Result<Vec<i32>>;
 }
 
 impl WallaceDBClient for &mut PgConnection {
@@ -86,6 +481,59 @@ impl WallaceDBClient for &mut PgConnection {
             .0
             .ok_or_else(|| anyhow!("No default name"))
     }
+    async fn set_guild_settings(self, id: u64, settings: &GuildSettings) -> Result<()> {
+        self.upsert_guild(id).await?;
+        sqlx::query(
+            "INSERT INTO guild_settings (guild_id, prefix, disabled_groups, react_to_name, ai_persona, tts_voice, weekly_payout, credit_limit, log_channel_id, locale, \
+            timezone, blackjack_decks, jackpot_share, daily_base, daily_bonus, daily_max_streak, activity_message_reward, activity_voice_reward, \
+            unbonk_cost, nickname_cost, servername_cost, gamba_min_bet, max_gamba_size, disabled_spells) \
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24) \
+            ON CONFLICT (guild_id) DO UPDATE SET prefix = $2, disabled_groups = $3, react_to_name = $4, ai_persona = $5, tts_voice = $6, weekly_payout = $7, credit_limit = $8, log_channel_id = $9, locale = $10, \
+            timezone = $11, blackjack_decks = $12, jackpot_share = $13, daily_base = $14, daily_bonus = $15, daily_max_streak = $16, activity_message_reward = $17, activity_voice_reward = $18, \
+            unbonk_cost = $19, nickname_cost = $20, servername_cost = $21, gamba_min_bet = $22, max_gamba_size = $23, disabled_spells = $24",
+        )
+        .bind(id as i64)
+        .bind(&settings.prefix)
+        .bind(&settings.disabled_groups)
+        .bind(settings.react_to_name)
+        .bind(&settings.ai_persona)
+        .bind(&settings.tts_voice)
+        .bind(settings.weekly_payout)
+        .bind(settings.credit_limit)
+        .bind(settings.log_channel_id)
+        .bind(&settings.locale)
+        .bind(&settings.timezone)
+        .bind(settings.blackjack_decks)
+        .bind(settings.jackpot_share)
+        .bind(settings.daily.base)
+        .bind(settings.daily.bonus)
+        .bind(settings.daily.max_streak)
+        .bind(settings.activity.message)
+        .bind(settings.activity.voice)
+        .bind(settings.spells.unbonk_cost)
+        .bind(settings.spells.nickname_cost)
+        .bind(settings.spells.servername_cost)
+        .bind(settings.spells.gamba_min_bet)
+        .bind(&settings.spells.max_gamba_size)
+        .bind(&settings.spells.disabled_spells)
+        .execute(self)
+        .await
+        .map(|_| ())
+        .map_err(|q| log_error(q, "Failed to update settings"))
+    }
+    async fn get_guild_settings(self, id: u64) -> Result<GuildSettings> {
+        sqlx::query_as(
+            "SELECT prefix, COALESCE(disabled_groups, '{}') AS disabled_groups, react_to_name, ai_persona, tts_voice, weekly_payout, credit_limit, log_channel_id, locale, \
+            timezone, blackjack_decks, jackpot_share, daily_base, daily_bonus, daily_max_streak, activity_message_reward, activity_voice_reward, \
+            unbonk_cost, nickname_cost, servername_cost, gamba_min_bet, max_gamba_size, COALESCE(disabled_spells, '{}') AS disabled_spells \
+            FROM guild_settings WHERE guild_id = $1",
+        )
+        .bind(id as i64)
+        .fetch_optional(self)
+        .await
+        .map(Option::unwrap_or_default)
+        .map_err(|q| log_error(q, "Failed to get settings"))
+    }
     async fn get_guild_random_names(self, id: u64) -> Result<(Vec<String>, Vec<String>)> {
         let subs = sqlx::query_as::<_, (String,)>(
             "SELECT r.value FROM guild g JOIN rn_subject r ON g.id = r.guild_id WHERE id = $1",
@@ -154,12 +602,6 @@ impl WallaceDBClient for &mut PgConnection {
             .map(|_| ())
             .map_err(|q| log_error(q, "Failed to update user"))
     }
-    async fn get_all_users(self) -> Result<Vec<User>> {
-        sqlx::query_as(r#"SELECT * FROM "user""#)
-            .fetch_all(self)
-            .await
-            .map_err(|q| log_error(q, "Failed to get users"))
-    }
     async fn create_lol_account(
         self,
         server: String,
@@ -195,148 +637,1270 @@ impl WallaceDBClient for &mut PgConnection {
             .await
             .map_err(|q| log_error(q, "Failed to get LoL accounts"))
     }
-    async fn create_bank_account(self, user_id: u64) -> Result<()> {
+    async fn create_bank_account(self, guild_id: u64, user_id: u64) -> Result<()> {
+        self.upsert_guild(guild_id).await?;
         self.upsert_user(user_id).await?;
-        sqlx::query("INSERT INTO bank_account (user_id) VALUES ($1) ON CONFLICT DO NOTHING")
-            .bind(user_id as i64)
-            .execute(self)
-            .await
-            .map(|_| ())
-            .map_err(|q| log_error(q, "Failed to create bank account"))
+        sqlx::query(
+            "INSERT INTO bank_account (guild_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        )
+        .bind(guild_id as i64)
+        .bind(user_id as i64)
+        .execute(self)
+        .await
+        .map(|_| ())
+        .map_err(|q| log_error(q, "Failed to create bank account"))
     }
-    async fn delete_bank_account(self, user_id: u64) -> Result<()> {
-        sqlx::query("DELETE FROM bank_account WHERE user_id = $1")
+    async fn delete_bank_account(self, guild_id: u64, user_id: u64) -> Result<()> {
+        sqlx::query("DELETE FROM bank_account WHERE guild_id = $1 AND user_id = $2")
+            .bind(guild_id as i64)
             .bind(user_id as i64)
             .execute(self)
             .await
             .map(|_| ())
             .map_err(|q| log_error(q, "Failed to delete bank account"))
     }
-    async fn get_bank_account_balance(self, user_id: u64) -> Result<i64> {
-        sqlx::query_as::<_, (i64,)>("SELECT balance FROM bank_account WHERE user_id = $1")
-            .bind(user_id as i64)
-            .fetch_one(self)
-            .await
-            .map(|u| u.0)
-            .map_err(|q| log_error(q, "Failed to get balance"))
+    async fn get_bank_account_balance(self, guild_id: u64, user_id: u64) -> Result<i64> {
+        sqlx::query_as::<_, (i64,)>(
+            "SELECT balance FROM bank_account WHERE guild_id = $1 AND user_id = $2",
+        )
+        .bind(guild_id as i64)
+        .bind(user_id as i64)
+        .fetch_one(self)
+        .await
+        .map(|u| u.0)
+        .map_err(|q| log_error(q, "Failed to get balance"))
     }
-    async fn has_bank_account_balance(self, user_id: u64, amount: i64) -> Result<()> {
+    async fn has_bank_account_balance(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        amount: i64,
+    ) -> Result<()> {
         positive(amount)?;
-        if amount > self.get_bank_account_balance(user_id).await? {
+        if amount > self.get_bank_account_balance(guild_id, user_id).await? {
             return Err(anyhow!("Account balance too low"));
         }
         Ok(())
     }
-    async fn add_bank_account_balance(self, user_id: u64, amount: i64) -> Result<()> {
+    async fn add_bank_account_balance(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        amount: i64,
+        reason: TransactionReason,
+    ) -> Result<()> {
         positive(amount)?;
-        sqlx::query("UPDATE bank_account SET balance = balance + $1 WHERE user_id = $2")
-            .bind(amount)
-            .bind(user_id as i64)
-            .execute(self)
-            .await
-            .map(|_| ())
-            .map_err(|q| log_error(q, "Failed to update balance"))
+        change_balance(self, guild_id, user_id, None, amount, reason).await
     }
-    async fn subtract_bank_account_balance(self, user_id: u64, amount: i64) -> Result<()> {
+    async fn subtract_bank_account_balance(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        amount: i64,
+        reason: TransactionReason,
+    ) -> Result<()> {
         positive(amount)?;
         let mut trx = self.begin().await?;
         trx.as_mut()
-            .has_bank_account_balance(user_id, amount)
+            .has_bank_account_balance(guild_id, user_id, amount)
             .await?;
-        sqlx::query("UPDATE bank_account SET balance = balance - $1 WHERE user_id = $2")
-            .bind(amount)
-            .bind(user_id as i64)
-            .execute(trx.as_mut())
-            .await
-            .map(|_| ())
-            .map_err(|q| log_error(q, "Failed to update balance"))?;
+        change_balance(trx.as_mut(), guild_id, user_id, None, -amount, reason).await?;
         trx.commit().await?;
         Ok(())
     }
     async fn transfer_bank_account_balance(
         self,
+        guild_id: u64,
         from_user_id: u64,
         to_user_id: u64,
         amount: i64,
+        reason: TransactionReason,
     ) -> Result<()> {
         if from_user_id == to_user_id {
             return Err(anyhow!("Can't transfer to self"));
         }
         let mut trx = self.begin().await?;
         trx.as_mut()
-            .subtract_bank_account_balance(from_user_id, amount)
-            .await?;
-        trx.as_mut()
-            .add_bank_account_balance(to_user_id, amount)
+            .has_bank_account_balance(guild_id, from_user_id, amount)
             .await?;
+        change_balance(
+            trx.as_mut(),
+            guild_id,
+            from_user_id,
+            Some(to_user_id),
+            -amount,
+            reason,
+        )
+        .await?;
+        change_balance(
+            trx.as_mut(),
+            guild_id,
+            to_user_id,
+            Some(from_user_id),
+            amount,
+            reason,
+        )
+        .await?;
         trx.commit().await?;
         Ok(())
     }
-    async fn upsert_channel(self, id: u64) -> Result<()> {
-        sqlx::query("INSERT INTO channel VALUES ($1) ON CONFLICT DO NOTHING")
-            .bind(id as i64)
-            .execute(self)
+    async fn get_bank_transactions(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<BankTransaction>> {
+        sqlx::query_as(
+            "SELECT * FROM bank_transaction WHERE guild_id = $1 AND user_id = $2 ORDER BY created_at DESC, id DESC LIMIT $3 OFFSET $4",
+        )
+        .bind(guild_id as i64)
+        .bind(user_id as i64)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self)
+        .await
+        .map_err(|q| log_error(q, "Failed to get transactions"))
+    }
+    async fn pay_weekly_payout(self, week: NaiveDate, amount: i64) -> Result<bool> {
+        positive(amount)?;
+        let mut trx = self.begin().await?;
+        let res =
+            sqlx::query("INSERT INTO weekly_payout (week) VALUES ($1) ON CONFLICT DO NOTHING")
+                .bind(week)
+                .execute(trx.as_mut())
+                .await
+                .map_err(|q| log_error(q, "Failed to record payout"))?;
+        if res.rows_affected() == 0 {
+            return Ok(false);
+        }
+        sqlx::query(
+            "INSERT INTO bank_transaction (guild_id, user_id, amount, reason) SELECT a.guild_id, a.user_id, COALESCE(s.weekly_payout, $1), $2 FROM bank_account a \
+            LEFT JOIN guild_settings s ON s.guild_id = a.guild_id WHERE COALESCE(s.weekly_payout, $1) > 0",
+        )
+        .bind(amount)
+        .bind(TransactionReason::WeeklyPayout.to_string())
+        .execute(trx.as_mut())
+        .await
+        .map_err(|q| log_error(q, "Failed to record transactions"))?;
+        sqlx::query(
+            "UPDATE bank_account a SET balance = a.balance + COALESCE((SELECT weekly_payout FROM guild_settings s WHERE s.guild_id = a.guild_id), $1)",
+        )
+        .bind(amount)
+        .execute(trx.as_mut())
+        .await
+        .map_err(|q| log_error(q, "Failed to update balances"))?;
+        sqlx::query(
+            "WITH r AS (SELECT l.guild_id, l.user_id, LEAST(l.debt, COALESCE(s.weekly_payout, $1)) AS paid FROM loan l LEFT JOIN guild_settings s ON s.guild_id = l.guild_id \
+            WHERE l.debt > 0 AND COALESCE(s.weekly_payout, $1) > 0 FOR UPDATE OF l), \
+            l AS (UPDATE loan SET debt = debt - r.paid, defaulted = defaulted AND debt > r.paid FROM r WHERE loan.guild_id = r.guild_id AND loan.user_id = r.user_id), \
+            b AS (UPDATE bank_account a SET balance = a.balance - r.paid FROM r WHERE a.guild_id = r.guild_id AND a.user_id = r.user_id) \
+            INSERT INTO bank_transaction (guild_id, user_id, amount, reason) SELECT guild_id, user_id, -paid, $2 FROM r",
+        )
+        .bind(amount)
+        .bind(TransactionReason::LoanRepayment.to_string())
+        .execute(trx.as_mut())
+        .await
+        .map_err(|q| log_error(q, "Failed to repay loans"))?;
+        trx.commit().await?;
+        Ok(true)
+    }
+    async fn add_activity_earnings(self, earnings: &[(u64, u64, i64)]) -> Result<()> {
+        let guild_ids: Vec<i64> = earnings.iter().map(|e| e.0 as i64).collect();
+        let user_ids: Vec<i64> = earnings.iter().map(|e| e.1 as i64).collect();
+        let amounts: Vec<i64> = earnings.iter().map(|e| e.2).collect();
+        let mut trx = self.begin().await?;
+        sqlx::query(
+            "INSERT INTO guild (id) SELECT DISTINCT UNNEST($1::BIGINT[]) ON CONFLICT DO NOTHING",
+        )
+        .bind(&guild_ids)
+        .execute(trx.as_mut())
+        .await
+        .map_err(|q| log_error(q, "Failed to upsert guild"))?;
+        sqlx::query(r#"INSERT INTO "user" (id) SELECT DISTINCT UNNEST($1::BIGINT[]) ON CONFLICT DO NOTHING"#)
+            .bind(&user_ids)
+            .execute(trx.as_mut())
             .await
-            .map(|_| ())
-            .map_err(|q| log_error(q, "Failed to upsert channel"))
+            .map_err(|q| log_error(q, "Failed to upsert user"))?;
+        sqlx::query(
+            "INSERT INTO bank_account (guild_id, user_id) SELECT * FROM UNNEST($1::BIGINT[], $2::BIGINT[]) ON CONFLICT DO NOTHING",
+        )
+        .bind(&guild_ids)
+        .bind(&user_ids)
+        .execute(trx.as_mut())
+        .await
+        .map_err(|q| log_error(q, "Failed to create bank account"))?;
+        sqlx::query(
+            "WITH e AS (SELECT * FROM UNNEST($1::BIGINT[], $2::BIGINT[], $3::BIGINT[]) AS e (guild_id, user_id, amount)), \
+            b AS (UPDATE bank_account a SET balance = a.balance + e.amount FROM e WHERE a.guild_id = e.guild_id AND a.user_id = e.user_id RETURNING a.guild_id, a.user_id, e.amount) \
+            INSERT INTO bank_transaction (guild_id, user_id, amount, reason) SELECT guild_id, user_id, amount, $4 FROM b",
+        )
+        .bind(guild_ids)
+        .bind(user_ids)
+        .bind(amounts)
+        .bind(TransactionReason::Activity.to_string())
+        .execute(trx.as_mut())
+        .await
+        .map_err(|q| log_error(q, "Failed to credit activity"))?;
+        trx.commit().await?;
+        Ok(())
     }
-    async fn create_task(
+    async fn claim_daily(
         self,
-        cron: String,
-        cmd: String,
-        arg: Option<String>,
-        channel_id: u64,
-    ) -> Result<()> {
-        self.upsert_channel(channel_id).await?;
-        sqlx::query("INSERT INTO task (cron, cmd, arg, channel_id) VALUES ($1, $2, $3, $4)")
-            .bind(cron)
-            .bind(cmd)
-            .bind(arg)
-            .bind(channel_id as i64)
-            .execute(self)
-            .await
-            .map(|_| ())
-            .map_err(|q| log_error(q, "Failed to create task"))
+        guild_id: u64,
+        user_id: u64,
+        curve: &DailyCurve,
+    ) -> Result<DailyClaim> {
+        let mut trx = self.begin().await?;
+        trx.as_mut().create_bank_account(guild_id, user_id).await?;
+        let (last_claim, streak) = sqlx::query_as::<_, (Option<DateTime<Utc>>, i32)>(
+            "SELECT daily_claimed_at, daily_streak FROM bank_account WHERE guild_id = $1 AND user_id = $2 FOR UPDATE",
+        )
+        .bind(guild_id as i64)
+        .bind(user_id as i64)
+        .fetch_one(trx.as_mut())
+        .await
+        .map_err(|q| log_error(q, "Failed to get bank account"))?;
+        let now = Utc::now();
+        let streak = match daily_streak(last_claim, streak, now) {
+            Ok(s) => s,
+            Err(next) => return Ok(DailyClaim::Cooldown(next)),
+        };
+        let reward = curve.reward(streak);
+        sqlx::query(
+            "UPDATE bank_account SET daily_claimed_at = $3, daily_streak = $4 WHERE guild_id = $1 AND user_id = $2",
+        )
+        .bind(guild_id as i64)
+        .bind(user_id as i64)
+        .bind(now)
+        .bind(streak)
+        .execute(trx.as_mut())
+        .await
+        .map_err(|q| log_error(q, "Failed to claim daily"))?;
+        if reward > 0 {
+            change_balance(
+                trx.as_mut(),
+                guild_id,
+                user_id,
+                None,
+                reward,
+                TransactionReason::Daily,
+            )
+            .await?;
+        }
+        trx.commit().await?;
+        Ok(DailyClaim::Claimed { streak, reward })
     }
-    async fn delete_task(self, id: i32) -> Result<()> {
-        sqlx::query("DELETE FROM task WHERE id = $1")
-            .bind(id as i64)
-            .execute(self)
+    async fn get_loan(self, guild_id: u64, user_id: u64) -> Result<Loan> {
+        sqlx::query_as("SELECT * FROM loan WHERE guild_id = $1 AND user_id = $2")
+            .bind(guild_id as i64)
+            .bind(user_id as i64)
+            .fetch_optional(self)
             .await
-            .map(|_| ())
-            .map_err(|q| log_error(q, "Failed to delete task"))
+            .map(Option::unwrap_or_default)
+            .map_err(|q| log_error(q, "Failed to get loan"))
     }
-    async fn get_all_tasks(self) -> Result<Vec<Task>> {
-        sqlx::query_as("SELECT * FROM task")
-            .fetch_all(self)
+    async fn take_loan(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        amount: i64,
+        limit: i64,
+        due_at: DateTime<Utc>,
+    ) -> Result<()> {
+        positive(amount)?;
+        if amount > limit {
+            return Err(anyhow!("Over the credit limit"));
+        }
+        let mut trx = self.begin().await?;
+        change_balance(
+            trx.as_mut(),
+            guild_id,
+            user_id,
+            None,
+            amount,
+            TransactionReason::Loan,
+        )
+        .await?;
+        let res = sqlx::query(
+            "INSERT INTO loan (guild_id, user_id, debt, due_at) VALUES ($1, $2, $3, $4) ON CONFLICT (guild_id, user_id) DO UPDATE SET debt = loan.debt + EXCLUDED.debt, due_at = CASE WHEN loan.debt = 0 THEN EXCLUDED.due_at ELSE loan.due_at END WHERE NOT loan.defaulted AND loan.debt + EXCLUDED.debt <= $5",
+        )
+        .bind(guild_id as i64)
+        .bind(user_id as i64)
+        .bind(amount)
+        .bind(due_at)
+        .bind(limit)
+        .execute(trx.as_mut())
+        .await
+        .map_err(|q| log_error(q, "Failed to take loan"))?;
+        if res.rows_affected() == 0 {
+            return Err(anyhow!("Over the credit limit"));
+        }
+        trx.commit().await?;
+        Ok(())
+    }
+    async fn repay_loan(self, guild_id: u64, user_id: u64, amount: Option<i64>) -> Result<i64> {
+        let mut trx = self.begin().await?;
+        let debt = sqlx::query_as::<_, (i64,)>(
+            "SELECT debt FROM loan WHERE guild_id = $1 AND user_id = $2 FOR UPDATE",
+        )
+        .bind(guild_id as i64)
+        .bind(user_id as i64)
+        .fetch_optional(trx.as_mut())
+        .await
+        .map_err(|q| log_error(q, "Failed to get loan"))?
+        .map(|l| l.0)
+        .unwrap_or(0);
+        if debt == 0 {
+            return Err(anyhow!("No loan to repay"));
+        }
+        let paid = amount.unwrap_or(debt).min(debt);
+        trx.as_mut()
+            .has_bank_account_balance(guild_id, user_id, paid)
+            .await?;
+        change_balance(
+            trx.as_mut(),
+            guild_id,
+            user_id,
+            None,
+            -paid,
+            TransactionReason::LoanRepayment,
+        )
+        .await?;
+        sqlx::query(
+            "UPDATE loan SET debt = debt - $3, defaulted = defaulted AND debt > $3 WHERE guild_id = $1 AND user_id = $2",
+        )
+        .bind(guild_id as i64)
+        .bind(user_id as i64)
+        .bind(paid)
+        .execute(trx.as_mut())
+        .await
+        .map_err(|q| log_error(q, "Failed to repay loan"))?;
+        trx.commit().await?;
+        Ok(paid)
+    }
+    async fn accrue_loan_interest(self, day: NaiveDate, percent: i64) -> Result<bool> {
+        let mut trx = self.begin().await?;
+        let res = sqlx::query("INSERT INTO loan_interest (day) VALUES ($1) ON CONFLICT DO NOTHING")
+            .bind(day)
+            .execute(trx.as_mut())
             .await
-            .map_err(|q| log_error(q, "Failed to get tasks"))
+            .map_err(|q| log_error(q, "Failed to record interest"))?;
+        if res.rows_affected() == 0 {
+            return Ok(false);
+        }
+        // Rounded to the nearest 𝓚, so small debts can accrue nothing
+        sqlx::query(
+            "UPDATE loan SET debt = debt + (debt * $1 + 50) / 100, defaulted = defaulted OR due_at < now() WHERE debt > 0",
+        )
+        .bind(percent)
+        .execute(trx.as_mut())
+        .await
+        .map_err(|q| log_error(q, "Failed to add interest"))?;
+        trx.commit().await?;
+        Ok(true)
     }
-    async fn get_all_tasks_in_channel(self, id: u64) -> Result<Vec<Task>> {
-        sqlx::query_as("SELECT * FROM task WHERE channel_id = $1")
-            .bind(id as i64)
-            .fetch_all(self)
+    async fn get_jackpot(self, guild_id: u64) -> Result<Jackpot> {
+        sqlx::query_as("SELECT * FROM jackpot WHERE guild_id = $1")
+            .bind(guild_id as i64)
+            .fetch_optional(self)
             .await
-            .map_err(|q| log_error(q, "Failed to get tasks"))
+            .map(Option::unwrap_or_default)
+            .map_err(|q| log_error(q, "Failed to get jackpot"))
     }
-}
-
-impl WallaceDBClient for &PgPool {
-    async fn upsert_guild(self, id: u64) -> Result<()> {
-        let mut conn = self.acquire().await?;
-        conn.upsert_guild(id).await
+    async fn play_bet(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        bet: i64,
+        payout: i64,
+        reason: TransactionReason,
+        jackpot: Option<bool>,
+    ) -> Result<i64> {
+        positive(bet)?;
+        let mut trx = self.begin().await?;
+        trx.as_mut()
+            .has_bank_account_balance(guild_id, user_id, bet)
+            .await?;
+        change_balance(trx.as_mut(), guild_id, user_id, None, -bet, reason).await?;
+        let won = pay_out_bet(
+            trx.as_mut(),
+            guild_id,
+            user_id,
+            bet,
+            payout,
+            reason,
+            jackpot,
+        )
+        .await?;
+        trx.commit().await?;
+        Ok(won)
     }
-    async fn set_guild_default_name(self, id: u64, value: String) -> Result<()> {
-        let mut conn = self.acquire().await?;
-        conn.set_guild_default_name(id, value).await
+    async fn hold_bet(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        amount: i64,
+        reason: TransactionReason,
+    ) -> Result<i32> {
+        positive(amount)?;
+        let mut trx = self.begin().await?;
+        trx.as_mut()
+            .has_bank_account_balance(guild_id, user_id, amount)
+            .await?;
+        change_balance(trx.as_mut(), guild_id, user_id, None, -amount, reason).await?;
+        let (id,) = sqlx::query_as(
+            "INSERT INTO held_bet (guild_id, user_id, amount, reason) VALUES ($1, $2, $3, $4) RETURNING id",
+        )
+        .bind(guild_id as i64)
+        .bind(user_id as i64)
+        .bind(amount)
+        .bind(reason.to_string())
+        .fetch_one(trx.as_mut())
+        .await
+        .map_err(|q| log_error(q, "Failed to hold bet"))?;
+        trx.commit().await?;
+        Ok(id)
     }
-    async fn get_guild_default_name(self, id: u64) -> Result<String> {
-        let mut conn = self.acquire().await?;
-        conn.get_guild_default_name(id).await
+    async fn raise_held_bet(self, id: i32, amount: i64) -> Result<()> {
+        positive(amount)?;
+        let mut trx = self.begin().await?;
+        let bet: HeldBet =
+            sqlx::query_as("UPDATE held_bet SET amount = amount + $2 WHERE id = $1 RETURNING guild_id, user_id, amount, reason")
+                .bind(id)
+                .bind(amount)
+                .fetch_optional(trx.as_mut())
+                .await
+                .map_err(|q| log_error(q, "Failed to raise bet"))?
+                .ok_or(anyhow!("This game is over"))?;
+        let (gid, uid) = (bet.guild_id as u64, bet.user_id as u64);
+        trx.as_mut()
+            .has_bank_account_balance(gid, uid, amount)
+            .await?;
+        change_balance(trx.as_mut(), gid, uid, None, -amount, bet.reason()).await?;
+        trx.commit().await?;
+        Ok(())
     }
-    async fn get_guild_random_names(self, id: u64) -> Result<(Vec<String>, Vec<String>)> {
-        let mut conn = self.acquire().await?;
-        conn.get_guild_random_names(id).await
+    async fn settle_held_bet(self, id: i32, payout: i64, jackpot: Option<bool>) -> Result<i64> {
+        let mut trx = self.begin().await?;
+        let bet: HeldBet = sqlx::query_as(
+            "DELETE FROM held_bet WHERE id = $1 RETURNING guild_id, user_id, amount, reason",
+        )
+        .bind(id)
+        .fetch_optional(trx.as_mut())
+        .await
+        .map_err(|q| log_error(q, "Failed to settle bet"))?
+        // Refunded when the game timed out
+        .ok_or(anyhow!("This game is over"))?;
+        let won = pay_out_bet(
+            trx.as_mut(),
+            bet.guild_id as u64,
+            bet.user_id as u64,
+            bet.amount,
+            payout,
+            bet.reason(),
+            jackpot,
+        )
+        .await?;
+        trx.commit().await?;
+        Ok(won)
+    }
+    async fn refund_held_bets(self, ids: Option<&[i32]>) -> Result<usize> {
+        let mut trx = self.begin().await?;
+        let bets: Vec<HeldBet> = match ids {
+            Some(ids) => {
+                sqlx::query_as("DELETE FROM held_bet WHERE id = ANY($1) RETURNING guild_id, user_id, amount, reason")
+                    .bind(ids)
+                    .fetch_all(trx.as_mut())
+                    .await
+            }
+            None => {
+                sqlx::query_as("DELETE FROM held_bet RETURNING guild_id, user_id, amount, reason")
+                    .fetch_all(trx.as_mut())
+                    .await
+            }
+        }
+        .map_err(|q| log_error(q, "Failed to refund bets"))?;
+        for bet in &bets {
+            change_balance(
+                trx.as_mut(),
+                bet.guild_id as u64,
+                bet.user_id as u64,
+                None,
+                bet.amount,
+                bet.reason(),
+            )
+            .await?;
+        }
+        trx.commit().await?;
+        Ok(bets.len())
+    }
+    async fn get_top_bank_accounts_in_guild(
+        self,
+        guild_id: u64,
+        limit: i64,
+    ) -> Result<Vec<BankAccount>> {
+        sqlx::query_as(
+            "SELECT * FROM bank_account WHERE guild_id = $1 ORDER BY balance DESC LIMIT $2",
+        )
+        .bind(guild_id as i64)
+        .bind(limit)
+        .fetch_all(self)
+        .await
+        .map_err(|q| log_error(q, "Failed to get bank accounts"))
+    }
+    async fn get_fair_seed(self, user_id: u64) -> Result<Option<FairSeed>> {
+        sqlx::query_as("SELECT * FROM fair_seed WHERE user_id = $1")
+            .bind(user_id as i64)
+            .fetch_optional(self)
+            .await
+            .map_err(|q| log_error(q, "Failed to get seed"))
+    }
+    async fn get_or_create_fair_seed(
+        self,
+        user_id: u64,
+        server_seed: String,
+        client_seed: String,
+    ) -> Result<FairSeed> {
+        self.upsert_user(user_id).await?;
+        sqlx::query(
+            "INSERT INTO fair_seed (user_id, server_seed, client_seed) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+        )
+        .bind(user_id as i64)
+        .bind(server_seed)
+        .bind(client_seed)
+        .execute(&mut *self)
+        .await
+        .map_err(|q| log_error(q, "Failed to create seed"))?;
+        self.get_fair_seed(user_id)
+            .await?
+            .ok_or_else(|| anyhow!("Failed to get seed"))
+    }
+    async fn reveal_fair_seed(
+        self,
+        user_id: u64,
+        server_seed: String,
+        client_seed: String,
+    ) -> Result<Option<FairSeed>> {
+        let mut trx = self.begin().await?;
+        let old: Option<FairSeed> =
+            sqlx::query_as("SELECT * FROM fair_seed WHERE user_id = $1 FOR UPDATE")
+                .bind(user_id as i64)
+                .fetch_optional(trx.as_mut())
+                .await
+                .map_err(|q| log_error(q, "Failed to get seed"))?;
+        trx.as_mut().upsert_user(user_id).await?;
+        sqlx::query(
+            "INSERT INTO fair_seed (user_id, server_seed, client_seed) VALUES ($1, $2, $3) ON CONFLICT (user_id) DO UPDATE SET server_seed = $2, client_seed = $3, nonce = 0, created_at = now()",
+        )
+        .bind(user_id as i64)
+        .bind(server_seed)
+        .bind(client_seed)
+        .execute(trx.as_mut())
+        .await
+        .map_err(|q| log_error(q, "Failed to update seed"))?;
+        trx.commit().await?;
+        Ok(old)
+    }
+    async fn create_fair_spin(
+        self,
+        user_id: u64,
+        game: String,
+        server_seed: String,
+        client_seed: String,
+    ) -> Result<FairSpin> {
+        let mut trx = self.begin().await?;
+        trx.as_mut().upsert_user(user_id).await?;
+        // The stored nonce is the one of the next spin
+        let seed: FairSeed = sqlx::query_as(
+            "INSERT INTO fair_seed (user_id, server_seed, client_seed, nonce) VALUES ($1, $2, $3, 1) ON CONFLICT (user_id) DO UPDATE SET nonce = fair_seed.nonce + 1 RETURNING server_seed, client_seed, nonce - 1 AS nonce",
+        )
+        .bind(user_id as i64)
+        .bind(server_seed)
+        .bind(client_seed)
+        .fetch_one(trx.as_mut())
+        .await
+        .map_err(|q| log_error(q, "Failed to update seed"))?;
+        let spin = sqlx::query_as(
+            "INSERT INTO fair_spin (user_id, game, server_seed, client_seed, nonce) VALUES ($1, $2, $3, $4, $5) RETURNING *",
+        )
+        .bind(user_id as i64)
+        .bind(game)
+        .bind(seed.server_seed)
+        .bind(seed.client_seed)
+        .bind(seed.nonce)
+        .fetch_one(trx.as_mut())
+        .await
+        .map_err(|q| log_error(q, "Failed to record spin"))?;
+        trx.commit().await?;
+        Ok(spin)
+    }
+    async fn set_fair_spin_outcome(self, id: i32, outcome: String) -> Result<()> {
+        sqlx::query("UPDATE fair_spin SET outcome = LEFT($1, 255) WHERE id = $2")
+            .bind(outcome)
+            .bind(id)
+            .execute(self)
+            .await
+            .map(|_| ())
+            .map_err(|q| log_error(q, "Failed to record spin"))
+    }
+    async fn get_fair_spin(self, id: i32) -> Result<Option<FairSpin>> {
+        sqlx::query_as("SELECT * FROM fair_spin WHERE id = $1")
+            .bind(id)
+            .fetch_optional(self)
+            .await
+            .map_err(|q| log_error(q, "Failed to get spin"))
+    }
+    async fn get_fair_spins(self, user_id: u64, limit: i64) -> Result<Vec<FairSpin>> {
+        sqlx::query_as(
+            "SELECT * FROM fair_spin WHERE user_id = $1 ORDER BY created_at DESC, id DESC LIMIT $2",
+        )
+        .bind(user_id as i64)
+        .bind(limit)
+        .fetch_all(self)
+        .await
+        .map_err(|q| log_error(q, "Failed to get spins"))
+    }
+    async fn create_slot_table(
+        self,
+        guild_id: u64,
+        creator_id: u64,
+        rules: String,
+        rtp: f64,
+    ) -> Result<i32> {
+        self.upsert_guild(guild_id).await?;
+        sqlx::query_as::<_, (i32,)>(
+            "INSERT INTO slot_table (guild_id, rules, rtp, creator_id) VALUES ($1, $2, $3, $4) RETURNING id",
+        )
+        .bind(guild_id as i64)
+        .bind(rules)
+        .bind(rtp)
+        .bind(creator_id as i64)
+        .fetch_one(self)
+        .await
+        .map(|t| t.0)
+        .map_err(|q| log_error(q, "Failed to create slot table"))
+    }
+    async fn get_slot_table(self, id: i32) -> Result<Option<SlotTable>> {
+        sqlx::query_as("SELECT * FROM slot_table WHERE id = $1")
+            .bind(id)
+            .fetch_optional(self)
+            .await
+            .map_err(|q| log_error(q, "Failed to get slot table"))
+    }
+    async fn get_guild_slot_table(self, guild_id: u64) -> Result<Option<SlotTable>> {
+        sqlx::query_as(
+            "SELECT t.* FROM guild g JOIN slot_table t ON g.slot_table_id = t.id WHERE g.id = $1",
+        )
+        .bind(guild_id as i64)
+        .fetch_optional(self)
+        .await
+        .map_err(|q| log_error(q, "Failed to get slot table"))
+    }
+    async fn set_guild_slot_table(self, guild_id: u64, id: Option<i32>) -> Result<()> {
+        self.upsert_guild(guild_id).await?;
+        let res = sqlx::query(
+            "UPDATE guild SET slot_table_id = $2 WHERE id = $1 AND ($2 IS NULL OR EXISTS (SELECT 1 FROM slot_table WHERE id = $2 AND guild_id = $1))",
+        )
+        .bind(guild_id as i64)
+        .bind(id)
+        .execute(self)
+        .await
+        .map_err(|q| log_error(q, "Failed to update guild"))?;
+        if res.rows_affected() == 0 {
+            return Err(anyhow!("No such slot table"));
+        }
+        Ok(())
+    }
+    async fn create_bet_pool(
+        self,
+        guild_id: u64,
+        creator_id: u64,
+        question: String,
+        options: Vec<String>,
+        closes_at: DateTime<Utc>,
+    ) -> Result<i32> {
+        self.upsert_guild(guild_id).await?;
+        sqlx::query_as::<_, (i32,)>(
+            "INSERT INTO bet_pool (guild_id, creator_id, question, options, closes_at) VALUES ($1, $2, $3, $4, $5) RETURNING id",
+        )
+        .bind(guild_id as i64)
+        .bind(creator_id as i64)
+        .bind(question)
+        .bind(options)
+        .bind(closes_at)
+        .fetch_one(self)
+        .await
+        .map(|p| p.0)
+        .map_err(|q| log_error(q, "Failed to create bet pool"))
+    }
+    async fn get_bet_pool(self, guild_id: u64, id: i32) -> Result<Option<BetPool>> {
+        sqlx::query_as("SELECT * FROM bet_pool WHERE guild_id = $1 AND id = $2")
+            .bind(guild_id as i64)
+            .bind(id)
+            .fetch_optional(self)
+            .await
+            .map_err(|q| log_error(q, "Failed to get bet pool"))
+    }
+    async fn get_unresolved_bet_pools(self, guild_id: u64) -> Result<Vec<BetPool>> {
+        sqlx::query_as(
+            "SELECT * FROM bet_pool WHERE guild_id = $1 AND resolved_at IS NULL ORDER BY closes_at",
+        )
+        .bind(guild_id as i64)
+        .fetch_all(self)
+        .await
+        .map_err(|q| log_error(q, "Failed to get bet pools"))
+    }
+    async fn get_bet_wagers(self, pool_id: i32) -> Result<Vec<BetWager>> {
+        sqlx::query_as("SELECT * FROM bet_wager WHERE pool_id = $1 ORDER BY amount DESC, user_id")
+            .bind(pool_id)
+            .fetch_all(self)
+            .await
+            .map_err(|q| log_error(q, "Failed to get bets"))
+    }
+    async fn place_bet_wager(
+        self,
+        guild_id: u64,
+        pool_id: i32,
+        user_id: u64,
+        option: i16,
+        amount: i64,
+    ) -> Result<()> {
+        positive(amount)?;
+        let mut trx = self.begin().await?;
+        // Lock the pool so that it can't be resolved while the bet is placed
+        sqlx::query(
+            "SELECT 1 FROM bet_pool WHERE guild_id = $1 AND id = $2 AND resolved_at IS NULL AND closes_at > now() AND $3 >= 0 AND $3 < cardinality(options) FOR UPDATE",
+        )
+        .bind(guild_id as i64)
+        .bind(pool_id)
+        .bind(option)
+        .fetch_optional(trx.as_mut())
+        .await
+        .map_err(|q| log_error(q, "Failed to get bet pool"))?
+        .ok_or_else(|| anyhow!("Betting is closed"))?;
+        // Lock the balance so that concurrent bets can't overdraw it
+        let balance = sqlx::query_as::<_, (i64,)>(
+            "SELECT balance FROM bank_account WHERE guild_id = $1 AND user_id = $2 FOR UPDATE",
+        )
+        .bind(guild_id as i64)
+        .bind(user_id as i64)
+        .fetch_optional(trx.as_mut())
+        .await
+        .map_err(|q| log_error(q, "Failed to get balance"))?
+        .ok_or_else(|| anyhow!("No bank account"))?
+        .0;
+        if amount > balance {
+            return Err(anyhow!("Account balance too low"));
+        }
+        let res = sqlx::query(
+            "INSERT INTO bet_wager (pool_id, guild_id, user_id, option, amount) VALUES ($1, $2, $3, $4, $5) ON CONFLICT (pool_id, user_id) DO UPDATE SET amount = bet_wager.amount + EXCLUDED.amount WHERE bet_wager.option = EXCLUDED.option",
+        )
+        .bind(pool_id)
+        .bind(guild_id as i64)
+        .bind(user_id as i64)
+        .bind(option)
+        .bind(amount)
+        .execute(trx.as_mut())
+        .await
+        .map_err(|q| log_error(q, "Failed to place bet"))?;
+        if res.rows_affected() == 0 {
+            return Err(anyhow!("Already bet on another option"));
+        }
+        change_balance(
+            trx.as_mut(),
+            guild_id,
+            user_id,
+            None,
+            -amount,
+            TransactionReason::Bet,
+        )
+        .await?;
+        trx.commit().await?;
+        Ok(())
+    }
+    async fn resolve_bet_pool(
+        self,
+        guild_id: u64,
+        pool_id: i32,
+        winner: Option<i16>,
+    ) -> Result<Vec<(i64, i64)>> {
+        let mut trx = self.begin().await?;
+        let (options, closed) = sqlx::query_as::<_, (i32, bool)>(
+            "SELECT cardinality(options), closes_at <= now() FROM bet_pool WHERE guild_id = $1 AND id = $2 AND resolved_at IS NULL FOR UPDATE",
+        )
+        .bind(guild_id as i64)
+        .bind(pool_id)
+        .fetch_optional(trx.as_mut())
+        .await
+        .map_err(|q| log_error(q, "Failed to get bet pool"))?
+        .ok_or_else(|| anyhow!("No such unresolved pool"))?;
+        if winner.is_some_and(|w| !(0..options).contains(&(w as i32))) {
+            return Err(anyhow!("No such option"));
+        }
+        // Cancelling is fine, but nobody can know the winner while bets are still taken
+        if winner.is_some() && !closed {
+            return Err(anyhow!("The bet is still open"));
+        }
+        let wagers = trx.as_mut().get_bet_wagers(pool_id).await?;
+        let payouts = BetPool::payouts(&wagers, winner);
+        for (user_id, amount) in &payouts {
+            if *amount > 0 {
+                change_balance(
+                    trx.as_mut(),
+                    guild_id,
+                    *user_id as u64,
+                    None,
+                    *amount,
+                    TransactionReason::Bet,
+                )
+                .await?;
+            }
+        }
+        sqlx::query("UPDATE bet_pool SET winner = $2, resolved_at = now() WHERE id = $1")
+            .bind(pool_id)
+            .bind(winner)
+            .execute(trx.as_mut())
+            .await
+            .map_err(|q| log_error(q, "Failed to resolve bet pool"))?;
+        trx.commit().await?;
+        Ok(payouts)
+    }
+    async fn buy_item(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        item: &str,
+        amount: i32,
+        price: i64,
+    ) -> Result<()> {
+        positive(amount as i64)?;
+        let mut trx = self.begin().await?;
+        trx.as_mut()
+            .subtract_bank_account_balance(guild_id, user_id, price, TransactionReason::Shop)
+            .await?;
+        add_items(trx.as_mut(), guild_id, user_id, item, amount).await?;
+        trx.commit().await?;
+        Ok(())
+    }
+    async fn get_inventory(self, guild_id: u64, user_id: u64) -> Result<Vec<InventoryItem>> {
+        sqlx::query_as(
+            "SELECT item, amount FROM inventory_item WHERE guild_id = $1 AND user_id = $2 AND amount > 0 ORDER BY item",
+        )
+        .bind(guild_id as i64)
+        .bind(user_id as i64)
+        .fetch_all(self)
+        .await
+        .map_err(|q| log_error(q, "Failed to get inventory"))
+    }
+    async fn take_inventory_item(self, guild_id: u64, user_id: u64, item: &str) -> Result<()> {
+        remove_items(self, guild_id, user_id, item, 1)
+            .await
+            .map_err(|_| anyhow!("You don't have that item"))
+    }
+    async fn give_inventory_item(self, guild_id: u64, user_id: u64, item: &str) -> Result<()> {
+        add_items(self, guild_id, user_id, item, 1).await
+    }
+    async fn create_market_listing(
+        self,
+        guild_id: u64,
+        seller_id: u64,
+        item: &str,
+        amount: i32,
+        price: i64,
+    ) -> Result<i32> {
+        positive(amount as i64)?;
+        positive(price)?;
+        let mut trx = self.begin().await?;
+        remove_items(trx.as_mut(), guild_id, seller_id, item, amount)
+            .await
+            .map_err(|_| anyhow!("You don't have that many"))?;
+        let (id,) = sqlx::query_as::<_, (i32,)>(
+            "INSERT INTO market_listing (guild_id, seller_id, item, amount, price) VALUES ($1, $2, $3, $4, $5) RETURNING id",
+        )
+        .bind(guild_id as i64)
+        .bind(seller_id as i64)
+        .bind(item)
+        .bind(amount)
+        .bind(price)
+        .fetch_one(trx.as_mut())
+        .await
+        .map_err(|q| log_error(q, "Failed to create listing"))?;
+        trx.commit().await?;
+        Ok(id)
+    }
+    async fn get_market_listings(self, guild_id: u64) -> Result<Vec<MarketListing>> {
+        sqlx::query_as("SELECT * FROM market_listing WHERE guild_id = $1 ORDER BY id")
+            .bind(guild_id as i64)
+            .fetch_all(self)
+            .await
+            .map_err(|q| log_error(q, "Failed to get listings"))
+    }
+    async fn buy_market_listing(
+        self,
+        guild_id: u64,
+        buyer_id: u64,
+        id: i32,
+    ) -> Result<MarketListing> {
+        let mut trx = self.begin().await?;
+        // Taking the listing down first means that only one buyer can get it
+        let listing: MarketListing = sqlx::query_as(
+            "DELETE FROM market_listing WHERE guild_id = $1 AND id = $2 AND seller_id != $3 RETURNING *",
+        )
+        .bind(guild_id as i64)
+        .bind(id)
+        .bind(buyer_id as i64)
+        .fetch_optional(trx.as_mut())
+        .await
+        .map_err(|q| log_error(q, "Failed to get listing"))?
+        .ok_or(anyhow!("No such listing"))?;
+        let seller_id = listing.seller_id as u64;
+        lock_bank_accounts(trx.as_mut(), guild_id, &[buyer_id, seller_id]).await?;
+        trx.as_mut()
+            .transfer_bank_account_balance(
+                guild_id,
+                buyer_id,
+                seller_id,
+                listing.price,
+                TransactionReason::Market,
+            )
+            .await?;
+        add_items(
+            trx.as_mut(),
+            guild_id,
+            buyer_id,
+            &listing.item,
+            listing.amount,
+        )
+        .await?;
+        trx.commit().await?;
+        Ok(listing)
+    }
+    async fn cancel_market_listing(
+        self,
+        guild_id: u64,
+        seller_id: u64,
+        id: i32,
+    ) -> Result<MarketListing> {
+        let mut trx = self.begin().await?;
+        let listing: MarketListing = sqlx::query_as(
+            "DELETE FROM market_listing WHERE guild_id = $1 AND id = $2 AND seller_id = $3 RETURNING *",
+        )
+        .bind(guild_id as i64)
+        .bind(id)
+        .bind(seller_id as i64)
+        .fetch_optional(trx.as_mut())
+        .await
+        .map_err(|q| log_error(q, "Failed to get listing"))?
+        .ok_or(anyhow!("No such listing of yours"))?;
+        add_items(
+            trx.as_mut(),
+            guild_id,
+            seller_id,
+            &listing.item,
+            listing.amount,
+        )
+        .await?;
+        trx.commit().await?;
+        Ok(listing)
+    }
+    async fn trade(
+        self,
+        guild_id: u64,
+        a_id: u64,
+        a_gives: &TradeGoods,
+        b_id: u64,
+        b_gives: &TradeGoods,
+    ) -> Result<()> {
+        if a_id == b_id {
+            return Err(anyhow!("Can't trade with self"));
+        }
+        let mut trx = self.begin().await?;
+        lock_bank_accounts(trx.as_mut(), guild_id, &[a_id, b_id]).await?;
+        for (from, to, goods) in [(a_id, b_id, a_gives), (b_id, a_id, b_gives)] {
+            if goods.kapsyler > 0 {
+                trx.as_mut()
+                    .transfer_bank_account_balance(
+                        guild_id,
+                        from,
+                        to,
+                        goods.kapsyler,
+                        TransactionReason::Trade,
+                    )
+                    .await?;
+            }
+            for (item, amount) in &goods.items {
+                remove_items(trx.as_mut(), guild_id, from, item, *amount)
+                    .await
+                    .map_err(|_| anyhow!("Not enough items to trade"))?;
+                add_items(trx.as_mut(), guild_id, to, item, *amount).await?;
+            }
+        }
+        trx.commit().await?;
+        Ok(())
+    }
+    async fn activate_buff(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        buff: &str,
+        duration: Duration,
+    ) -> Result<DateTime<Utc>> {
+        // Buying more of an active buff extends it
+        sqlx::query_as::<_, (DateTime<Utc>,)>(
+            "INSERT INTO active_buff (guild_id, user_id, buff, expires_at) VALUES ($1, $2, $3, now() + $4) ON CONFLICT (guild_id, user_id, buff) DO UPDATE SET expires_at = GREATEST(active_buff.expires_at, now()) + $4 RETURNING expires_at",
+        )
+        .bind(guild_id as i64)
+        .bind(user_id as i64)
+        .bind(buff)
+        .bind(duration)
+        .fetch_one(self)
+        .await
+        .map(|b| b.0)
+        .map_err(|q| log_error(q, "Failed to activate buff"))
+    }
+    async fn use_buff_item(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        item: &str,
+        duration: Duration,
+    ) -> Result<DateTime<Utc>> {
+        let mut trx = self.begin().await?;
+        trx.as_mut()
+            .take_inventory_item(guild_id, user_id, item)
+            .await?;
+        let expires_at = trx
+            .as_mut()
+            .activate_buff(guild_id, user_id, item, duration)
+            .await?;
+        trx.commit().await?;
+        Ok(expires_at)
+    }
+    async fn get_active_buffs(self, guild_id: u64, user_id: u64) -> Result<Vec<ActiveBuff>> {
+        sqlx::query_as(
+            "SELECT buff, expires_at FROM active_buff WHERE guild_id = $1 AND user_id = $2 AND expires_at > now() ORDER BY expires_at",
+        )
+        .bind(guild_id as i64)
+        .bind(user_id as i64)
+        .fetch_all(self)
+        .await
+        .map_err(|q| log_error(q, "Failed to get buffs"))
+    }
+    async fn has_active_buff(self, guild_id: u64, user_id: u64, buff: &str) -> Result<bool> {
+        sqlx::query_as::<_, (bool,)>(
+            "SELECT EXISTS (SELECT 1 FROM active_buff WHERE guild_id = $1 AND user_id = $2 AND buff = $3 AND expires_at > now())",
+        )
+        .bind(guild_id as i64)
+        .bind(user_id as i64)
+        .bind(buff)
+        .fetch_one(self)
+        .await
+        .map(|b| b.0)
+        .map_err(|q| log_error(q, "Failed to get buffs"))
+    }
+    async fn create_bonk_event(self, event: BonkEvent) -> Result<()> {
+        self.upsert_guild(event.guild_id).await?;
+        sqlx::query(
+            "INSERT INTO bonk_event (guild_id, caster_id, target_id, victim_id, size, chance, outcome, duration, cost) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+        )
+        .bind(event.guild_id as i64)
+        .bind(event.caster_id as i64)
+        .bind(event.target_id as i64)
+        .bind(event.victim_id.map(|id| id as i64))
+        .bind(event.size)
+        .bind(event.chance.map(|c| c as i32))
+        .bind(event.outcome.to_string())
+        .bind(event.duration as i32)
+        .bind(event.cost)
+        .execute(self)
+        .await
+        .map(|_| ())
+        .map_err(|q| log_error(q, "Failed to record bonk"))
+    }
+    async fn get_bonk_stats(self, guild_id: u64, user_id: u64) -> Result<BonkStats> {
+        sqlx::query_as(
+            "SELECT
+                COUNT(*) FILTER (WHERE victim_id = $2) AS bonked,
+                COUNT(*) FILTER (WHERE caster_id = $2 AND victim_id = $2 AND target_id <> $2) AS backfired,
+                COALESCE(SUM(duration) FILTER (WHERE caster_id = $2 AND victim_id <> $2), 0) AS dealt,
+                COALESCE(SUM(duration) FILTER (WHERE victim_id = $2), 0) AS received
+            FROM bonk_event WHERE guild_id = $1",
+        )
+        .bind(guild_id as i64)
+        .bind(user_id as i64)
+        .fetch_one(self)
+        .await
+        .map_err(|q| log_error(q, "Failed to get bonk stats"))
+    }
+    async fn get_most_bonked_in_guild(self, guild_id: u64, limit: i64) -> Result<Vec<(i64, i64)>> {
+        sqlx::query_as(
+            "SELECT victim_id, COUNT(*) FROM bonk_event WHERE guild_id = $1 AND victim_id IS NOT NULL GROUP BY victim_id ORDER BY COUNT(*) DESC LIMIT $2",
+        )
+        .bind(guild_id as i64)
+        .bind(limit)
+        .fetch_all(self)
+        .await
+        .map_err(|q| log_error(q, "Failed to get bonk stats"))
+    }
+    async fn upsert_channel(self, id: u64) -> Result<()> {
+        sqlx::query("INSERT INTO channel VALUES ($1) ON CONFLICT DO NOTHING")
+            .bind(id as i64)
+            .execute(self)
+            .await
+            .map(|_| ())
+            .map_err(|q| log_error(q, "Failed to upsert channel"))
+    }
+    async fn create_task(
+        self,
+        cron: String,
+        cmd: String,
+        arg: Option<String>,
+        channel_id: u64,
+        creator_id: u64,
+        timezone: Option<String>,
+    ) -> Result<Task> {
+        self.upsert_channel(channel_id).await?;
+        sqlx::query_as(
+            "INSERT INTO task (cron, cmd, arg, channel_id, creator_id, timezone) VALUES ($1, $2, $3, $4, $5, $6) RETURNING *",
+        )
+        .bind(cron)
+        .bind(cmd)
+        .bind(arg)
+        .bind(channel_id as i64)
+        .bind(creator_id as i64)
+        .bind(timezone)
+        .fetch_one(self)
+        .await
+        .map_err(|q| log_error(q, "Failed to create task"))
+    }
+    async fn get_task(self, id: i32) -> Result<Option<Task>> {
+        sqlx::query_as("SELECT * FROM task WHERE id = $1")
+            .bind(id)
+            .fetch_optional(self)
+            .await
+            .map_err(|q| log_error(q, "Failed to get task"))
+    }
+    async fn update_task(self, task: &Task) -> Result<Task> {
+        // Runs missed under the old schedule are not caught up on
+        sqlx::query_as(
+            "UPDATE task SET cron = $1, cmd = $2, arg = $3, timezone = $4, description = $5, last_run = CURRENT_TIMESTAMP WHERE id = $6 RETURNING *",
+        )
+        .bind(&task.cron)
+        .bind(&task.cmd)
+        .bind(&task.arg)
+        .bind(&task.timezone)
+        .bind(&task.description)
+        .bind(task.id)
+        .fetch_optional(self)
+        .await
+        .map_err(|q| log_error(q, "Failed to update task"))?
+        .ok_or_else(|| anyhow!("No such task"))
+    }
+    async fn delete_task(self, id: i32) -> Result<()> {
+        sqlx::query("DELETE FROM task WHERE id = $1")
+            .bind(id as i64)
+            .execute(self)
+            .await
+            .map(|_| ())
+            .map_err(|q| log_error(q, "Failed to delete task"))
+    }
+    async fn set_task_enabled(self, id: i32, enabled: bool) -> Result<Task> {
+        // Runs missed while paused are not caught up on
+        sqlx::query_as(
+            "UPDATE task SET enabled = $1, last_run = GREATEST(last_run, CURRENT_TIMESTAMP) WHERE id = $2 RETURNING *",
+        )
+        .bind(enabled)
+        .bind(id)
+        .fetch_optional(self)
+        .await
+        .map_err(|q| log_error(q, "Failed to update task"))?
+        .ok_or_else(|| anyhow!("No such task"))
+    }
+    async fn set_task_result(self, id: i32, result: String) -> Result<()> {
+        sqlx::query("UPDATE task SET last_result = LEFT($1, 255), last_result_at = CURRENT_TIMESTAMP WHERE id = $2")
+            .bind(result)
+            .bind(id)
+            .execute(self)
+            .await
+            .map(|_| ())
+            .map_err(|q| log_error(q, "Failed to update task"))
+    }
+    async fn set_task_last_run(self, id: i32, last_run: DateTime<Utc>) -> Result<()> {
+        sqlx::query("UPDATE task SET last_run = $1 WHERE id = $2")
+            .bind(last_run)
+            .bind(id)
+            .execute(self)
+            .await
+            .map(|_| ())
+            .map_err(|q| log_error(q, "Failed to update task"))
+    }
+    async fn set_task_catch_up(self, id: i32, catch_up: CatchUp) -> Result<()> {
+        let res = sqlx::query("UPDATE task SET catch_up = $1 WHERE id = $2")
+            .bind(catch_up.to_string())
+            .bind(id)
+            .execute(self)
+            .await
+            .map_err(|q| log_error(q, "Failed to update task"))?;
+        if res.rows_affected() == 0 {
+            return Err(anyhow!("No such task"));
+        }
+        Ok(())
+    }
+    async fn get_all_tasks(self) -> Result<Vec<Task>> {
+        sqlx::query_as("SELECT * FROM task")
+            .fetch_all(self)
+            .await
+            .map_err(|q| log_error(q, "Failed to get tasks"))
+    }
+    async fn get_all_tasks_in_channel(self, id: u64) -> Result<Vec<Task>> {
+        sqlx::query_as("SELECT * FROM task WHERE channel_id = $1")
+            .bind(id as i64)
+            .fetch_all(self)
+            .await
+            .map_err(|q| log_error(q, "Failed to get tasks"))
+    }
+    async fn get_all_tasks_in_channels(self, ids: Vec<u64>) -> Result<Vec<Task>> {
+        sqlx::query_as("SELECT * FROM task WHERE channel_id = ANY($1) ORDER BY channel_id, id")
+            .bind(ids.into_iter().map(|id| id as i64).collect::<Vec<_>>())
+            .fetch_all(self)
+            .await
+            .map_err(|q| log_error(q, "Failed to get tasks"))
+    }
+    async fn delete_tasks_in_channel(self, id: u64) -> Result<Vec<i32>> {
+        sqlx::query_as::<_, (i32,)>("DELETE FROM task WHERE channel_id = $1 RETURNING id")
+            .bind(id as i64)
+            .fetch_all(self)
+            .await
+            .map(|v| v.into_iter().map(|t| t.0).collect())
+            .map_err(|q| log_error(q, "Failed to delete tasks"))
+    }
+}
+
+impl WallaceDBClient for &PgPool {
+    async fn upsert_guild(self, id: u64) -> Result<()> {
+        let mut conn = self.acquire().await?;
+        conn.upsert_guild(id).await
+    }
+    async fn set_guild_default_name(self, id: u64, value: String) -> Result<()> {
+        let mut conn = self.acquire().await?;
+        conn.set_guild_default_name(id, value).await
+    }
+    async fn get_guild_default_name(self, id: u64) -> Result<String> {
+        let mut conn = self.acquire().await?;
+        conn.get_guild_default_name(id).await
+    }
+    async fn set_guild_settings(self, id: u64, settings: &GuildSettings) -> Result<()> {
+        let mut conn = self.acquire().await?;
+        conn.set_guild_settings(id, settings).await
+    }
+    async fn get_guild_settings(self, id: u64) -> Result<GuildSettings> {
+        let mut conn = self.acquire().await?;
+        conn.get_guild_settings(id).await
+    }
+    async fn get_guild_random_names(self, id: u64) -> Result<(Vec<String>, Vec<String>)> {
+        let mut conn = self.acquire().await?;
+        conn.get_guild_random_names(id).await
     }
     async fn add_guild_random_name_subject(self, id: u64, value: String) -> Result<()> {
         let mut conn = self.acquire().await?;
@@ -358,10 +1922,6 @@ impl WallaceDBClient for &PgPool {
         let mut conn = self.acquire().await?;
         conn.set_user_mature(id, mature).await
     }
-    async fn get_all_users(self) -> Result<Vec<User>> {
-        let mut conn = self.acquire().await?;
-        conn.get_all_users().await
-    }
     async fn create_lol_account(
         self,
         server: String,
@@ -380,40 +1940,384 @@ impl WallaceDBClient for &PgPool {
         let mut conn = self.acquire().await?;
         conn.get_all_lol_accounts_in_user(id).await
     }
-    async fn create_bank_account(self, user_id: u64) -> Result<()> {
+    async fn create_bank_account(self, guild_id: u64, user_id: u64) -> Result<()> {
         let mut conn = self.acquire().await?;
-        conn.create_bank_account(user_id).await
+        conn.create_bank_account(guild_id, user_id).await
     }
-    async fn delete_bank_account(self, user_id: u64) -> Result<()> {
+    async fn delete_bank_account(self, guild_id: u64, user_id: u64) -> Result<()> {
         let mut conn = self.acquire().await?;
-        conn.delete_bank_account(user_id).await
+        conn.delete_bank_account(guild_id, user_id).await
     }
-    async fn get_bank_account_balance(self, user_id: u64) -> Result<i64> {
+    async fn get_bank_account_balance(self, guild_id: u64, user_id: u64) -> Result<i64> {
         let mut conn = self.acquire().await?;
-        conn.get_bank_account_balance(user_id).await
+        conn.get_bank_account_balance(guild_id, user_id).await
     }
-    async fn has_bank_account_balance(self, user_id: u64, amount: i64) -> Result<()> {
+    async fn has_bank_account_balance(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        amount: i64,
+    ) -> Result<()> {
         let mut conn = self.acquire().await?;
-        conn.has_bank_account_balance(user_id, amount).await
+        conn.has_bank_account_balance(guild_id, user_id, amount)
+            .await
     }
-    async fn add_bank_account_balance(self, user_id: u64, amount: i64) -> Result<()> {
+    async fn add_bank_account_balance(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        amount: i64,
+        reason: TransactionReason,
+    ) -> Result<()> {
         let mut conn = self.acquire().await?;
-        conn.add_bank_account_balance(user_id, amount).await
+        conn.add_bank_account_balance(guild_id, user_id, amount, reason)
+            .await
     }
-    async fn subtract_bank_account_balance(self, user_id: u64, amount: i64) -> Result<()> {
+    async fn subtract_bank_account_balance(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        amount: i64,
+        reason: TransactionReason,
+    ) -> Result<()> {
         let mut conn = self.acquire().await?;
-        conn.subtract_bank_account_balance(user_id, amount).await
+        conn.subtract_bank_account_balance(guild_id, user_id, amount, reason)
+            .await
     }
     async fn transfer_bank_account_balance(
         self,
+        guild_id: u64,
         from_user_id: u64,
         to_user_id: u64,
         amount: i64,
+        reason: TransactionReason,
+    ) -> Result<()> {
+        let mut conn = self.acquire().await?;
+        conn.transfer_bank_account_balance(guild_id, from_user_id, to_user_id, amount, reason)
+            .await
+    }
+    async fn get_bank_transactions(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<BankTransaction>> {
+        let mut conn = self.acquire().await?;
+        conn.get_bank_transactions(guild_id, user_id, limit, offset)
+            .await
+    }
+    async fn pay_weekly_payout(self, week: NaiveDate, amount: i64) -> Result<bool> {
+        let mut conn = self.acquire().await?;
+        conn.pay_weekly_payout(week, amount).await
+    }
+    async fn add_activity_earnings(self, earnings: &[(u64, u64, i64)]) -> Result<()> {
+        let mut conn = self.acquire().await?;
+        conn.add_activity_earnings(earnings).await
+    }
+    async fn claim_daily(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        curve: &DailyCurve,
+    ) -> Result<DailyClaim> {
+        let mut conn = self.acquire().await?;
+        conn.claim_daily(guild_id, user_id, curve).await
+    }
+    async fn get_loan(self, guild_id: u64, user_id: u64) -> Result<Loan> {
+        let mut conn = self.acquire().await?;
+        conn.get_loan(guild_id, user_id).await
+    }
+    async fn take_loan(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        amount: i64,
+        limit: i64,
+        due_at: DateTime<Utc>,
     ) -> Result<()> {
         let mut conn = self.acquire().await?;
-        conn.transfer_bank_account_balance(from_user_id, to_user_id, amount)
+        conn.take_loan(guild_id, user_id, amount, limit, due_at)
             .await
     }
+    async fn repay_loan(self, guild_id: u64, user_id: u64, amount: Option<i64>) -> Result<i64> {
+        let mut conn = self.acquire().await?;
+        conn.repay_loan(guild_id, user_id, amount).await
+    }
+    async fn accrue_loan_interest(self, day: NaiveDate, percent: i64) -> Result<bool> {
+        let mut conn = self.acquire().await?;
+        conn.accrue_loan_interest(day, percent).await
+    }
+    async fn get_jackpot(self, guild_id: u64) -> Result<Jackpot> {
+        let mut conn = self.acquire().await?;
+        conn.get_jackpot(guild_id).await
+    }
+    async fn play_bet(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        bet: i64,
+        payout: i64,
+        reason: TransactionReason,
+        jackpot: Option<bool>,
+    ) -> Result<i64> {
+        let mut conn = self.acquire().await?;
+        conn.play_bet(guild_id, user_id, bet, payout, reason, jackpot)
+            .await
+    }
+    async fn hold_bet(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        amount: i64,
+        reason: TransactionReason,
+    ) -> Result<i32> {
+        let mut conn = self.acquire().await?;
+        conn.hold_bet(guild_id, user_id, amount, reason).await
+    }
+    async fn raise_held_bet(self, id: i32, amount: i64) -> Result<()> {
+        let mut conn = self.acquire().await?;
+        conn.raise_held_bet(id, amount).await
+    }
+    async fn settle_held_bet(self, id: i32, payout: i64, jackpot: Option<bool>) -> Result<i64> {
+        let mut conn = self.acquire().await?;
+        conn.settle_held_bet(id, payout, jackpot).await
+    }
+    async fn refund_held_bets(self, ids: Option<&[i32]>) -> Result<usize> {
+        let mut conn = self.acquire().await?;
+        conn.refund_held_bets(ids).await
+    }
+    async fn get_top_bank_accounts_in_guild(
+        self,
+        guild_id: u64,
+        limit: i64,
+    ) -> Result<Vec<BankAccount>> {
+        let mut conn = self.acquire().await?;
+        conn.get_top_bank_accounts_in_guild(guild_id, limit).await
+    }
+    async fn get_fair_seed(self, user_id: u64) -> Result<Option<FairSeed>> {
+        let mut conn = self.acquire().await?;
+        conn.get_fair_seed(user_id).await
+    }
+    async fn get_or_create_fair_seed(
+        self,
+        user_id: u64,
+        server_seed: String,
+        client_seed: String,
+    ) -> Result<FairSeed> {
+        let mut conn = self.acquire().await?;
+        conn.get_or_create_fair_seed(user_id, server_seed, client_seed)
+            .await
+    }
+    async fn reveal_fair_seed(
+        self,
+        user_id: u64,
+        server_seed: String,
+        client_seed: String,
+    ) -> Result<Option<FairSeed>> {
+        let mut conn = self.acquire().await?;
+        conn.reveal_fair_seed(user_id, server_seed, client_seed)
+            .await
+    }
+    async fn create_fair_spin(
+        self,
+        user_id: u64,
+        game: String,
+        server_seed: String,
+        client_seed: String,
+    ) -> Result<FairSpin> {
+        let mut conn = self.acquire().await?;
+        conn.create_fair_spin(user_id, game, server_seed, client_seed)
+            .await
+    }
+    async fn set_fair_spin_outcome(self, id: i32, outcome: String) -> Result<()> {
+        let mut conn = self.acquire().await?;
+        conn.set_fair_spin_outcome(id, outcome).await
+    }
+    async fn get_fair_spin(self, id: i32) -> Result<Option<FairSpin>> {
+        let mut conn = self.acquire().await?;
+        conn.get_fair_spin(id).await
+    }
+    async fn get_fair_spins(self, user_id: u64, limit: i64) -> Result<Vec<FairSpin>> {
+        let mut conn = self.acquire().await?;
+        conn.get_fair_spins(user_id, limit).await
+    }
+    async fn create_slot_table(
+        self,
+        guild_id: u64,
+        creator_id: u64,
+        rules: String,
+        rtp: f64,
+    ) -> Result<i32> {
+        let mut conn = self.acquire().await?;
+        conn.create_slot_table(guild_id, creator_id, rules, rtp)
+            .await
+    }
+    async fn get_slot_table(self, id: i32) -> Result<Option<SlotTable>> {
+        let mut conn = self.acquire().await?;
+        conn.get_slot_table(id).await
+    }
+    async fn get_guild_slot_table(self, guild_id: u64) -> Result<Option<SlotTable>> {
+        let mut conn = self.acquire().await?;
+        conn.get_guild_slot_table(guild_id).await
+    }
+    async fn set_guild_slot_table(self, guild_id: u64, id: Option<i32>) -> Result<()> {
+        let mut conn = self.acquire().await?;
+        conn.set_guild_slot_table(guild_id, id).await
+    }
+    async fn create_bet_pool(
+        self,
+        guild_id: u64,
+        creator_id: u64,
+        question: String,
+        options: Vec<String>,
+        closes_at: DateTime<Utc>,
+    ) -> Result<i32> {
+        let mut conn = self.acquire().await?;
+        conn.create_bet_pool(guild_id, creator_id, question, options, closes_at)
+            .await
+    }
+    async fn get_bet_pool(self, guild_id: u64, id: i32) -> Result<Option<BetPool>> {
+        let mut conn = self.acquire().await?;
+        conn.get_bet_pool(guild_id, id).await
+    }
+    async fn get_unresolved_bet_pools(self, guild_id: u64) -> Result<Vec<BetPool>> {
+        let mut conn = self.acquire().await?;
+        conn.get_unresolved_bet_pools(guild_id).await
+    }
+    async fn get_bet_wagers(self, pool_id: i32) -> Result<Vec<BetWager>> {
+        let mut conn = self.acquire().await?;
+        conn.get_bet_wagers(pool_id).await
+    }
+    async fn place_bet_wager(
+        self,
+        guild_id: u64,
+        pool_id: i32,
+        user_id: u64,
+        option: i16,
+        amount: i64,
+    ) -> Result<()> {
+        let mut conn = self.acquire().await?;
+        conn.place_bet_wager(guild_id, pool_id, user_id, option, amount)
+            .await
+    }
+    async fn resolve_bet_pool(
+        self,
+        guild_id: u64,
+        pool_id: i32,
+        winner: Option<i16>,
+    ) -> Result<Vec<(i64, i64)>> {
+        let mut conn = self.acquire().await?;
+        conn.resolve_bet_pool(guild_id, pool_id, winner).await
+    }
+    async fn buy_item(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        item: &str,
+        amount: i32,
+        price: i64,
+    ) -> Result<()> {
+        let mut conn = self.acquire().await?;
+        conn.buy_item(guild_id, user_id, item, amount, price).await
+    }
+    async fn get_inventory(self, guild_id: u64, user_id: u64) -> Result<Vec<InventoryItem>> {
+        let mut conn = self.acquire().await?;
+        conn.get_inventory(guild_id, user_id).await
+    }
+    async fn take_inventory_item(self, guild_id: u64, user_id: u64, item: &str) -> Result<()> {
+        let mut conn = self.acquire().await?;
+        conn.take_inventory_item(guild_id, user_id, item).await
+    }
+    async fn give_inventory_item(self, guild_id: u64, user_id: u64, item: &str) -> Result<()> {
+        let mut conn = self.acquire().await?;
+        conn.give_inventory_item(guild_id, user_id, item).await
+    }
+    async fn create_market_listing(
+        self,
+        guild_id: u64,
+        seller_id: u64,
+        item: &str,
+        amount: i32,
+        price: i64,
+    ) -> Result<i32> {
+        let mut conn = self.acquire().await?;
+        conn.create_market_listing(guild_id, seller_id, item, amount, price)
+            .await
+    }
+    async fn get_market_listings(self, guild_id: u64) -> Result<Vec<MarketListing>> {
+        let mut conn = self.acquire().await?;
+        conn.get_market_listings(guild_id).await
+    }
+    async fn buy_market_listing(
+        self,
+        guild_id: u64,
+        buyer_id: u64,
+        id: i32,
+    ) -> Result<MarketListing> {
+        let mut conn = self.acquire().await?;
+        conn.buy_market_listing(guild_id, buyer_id, id).await
+    }
+    async fn cancel_market_listing(
+        self,
+        guild_id: u64,
+        seller_id: u64,
+        id: i32,
+    ) -> Result<MarketListing> {
+        let mut conn = self.acquire().await?;
+        conn.cancel_market_listing(guild_id, seller_id, id).await
+    }
+    async fn trade(
+        self,
+        guild_id: u64,
+        a_id: u64,
+        a_gives: &TradeGoods,
+        b_id: u64,
+        b_gives: &TradeGoods,
+    ) -> Result<()> {
+        let mut conn = self.acquire().await?;
+        conn.trade(guild_id, a_id, a_gives, b_id, b_gives).await
+    }
+    async fn activate_buff(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        buff: &str,
+        duration: Duration,
+    ) -> Result<DateTime<Utc>> {
+        let mut conn = self.acquire().await?;
+        conn.activate_buff(guild_id, user_id, buff, duration).await
+    }
+    async fn use_buff_item(
+        self,
+        guild_id: u64,
+        user_id: u64,
+        item: &str,
+        duration: Duration,
+    ) -> Result<DateTime<Utc>> {
+        let mut conn = self.acquire().await?;
+        conn.use_buff_item(guild_id, user_id, item, duration).await
+    }
+    async fn get_active_buffs(self, guild_id: u64, user_id: u64) -> Result<Vec<ActiveBuff>> {
+        let mut conn = self.acquire().await?;
+        conn.get_active_buffs(guild_id, user_id).await
+    }
+    async fn has_active_buff(self, guild_id: u64, user_id: u64, buff: &str) -> Result<bool> {
+        let mut conn = self.acquire().await?;
+        conn.has_active_buff(guild_id, user_id, buff).await
+    }
+    async fn create_bonk_event(self, event: BonkEvent) -> Result<()> {
+        let mut conn = self.acquire().await?;
+        conn.create_bonk_event(event).await
+    }
+    async fn get_bonk_stats(self, guild_id: u64, user_id: u64) -> Result<BonkStats> {
+        let mut conn = self.acquire().await?;
+        conn.get_bonk_stats(guild_id, user_id).await
+    }
+    async fn get_most_bonked_in_guild(self, guild_id: u64, limit: i64) -> Result<Vec<(i64, i64)>> {
+        let mut conn = self.acquire().await?;
+        conn.get_most_bonked_in_guild(guild_id, limit).await
+    }
     async fn upsert_channel(self, id: u64) -> Result<()> {
         let mut conn = self.acquire().await?;
         conn.upsert_channel(id).await
@@ -424,14 +2328,41 @@ impl WallaceDBClient for &PgPool {
         cmd: String,
         arg: Option<String>,
         channel_id: u64,
-    ) -> Result<()> {
+        creator_id: u64,
+        timezone: Option<String>,
+    ) -> Result<Task> {
+        let mut conn = self.acquire().await?;
+        conn.create_task(cron, cmd, arg, channel_id, creator_id, timezone)
+            .await
+    }
+    async fn get_task(self, id: i32) -> Result<Option<Task>> {
         let mut conn = self.acquire().await?;
-        conn.create_task(cron, cmd, arg, channel_id).await
+        conn.get_task(id).await
+    }
+    async fn update_task(self, task: &Task) -> Result<Task> {
+        let mut conn = self.acquire().await?;
+        conn.update_task(task).await
     }
     async fn delete_task(self, id: i32) -> Result<()> {
         let mut conn = self.acquire().await?;
         conn.delete_task(id).await
     }
+    async fn set_task_enabled(self, id: i32, enabled: bool) -> Result<Task> {
+        let mut conn = self.acquire().await?;
+        conn.set_task_enabled(id, enabled).await
+    }
+    async fn set_task_result(self, id: i32, result: String) -> Result<()> {
+        let mut conn = self.acquire().await?;
+        conn.set_task_result(id, result).await
+    }
+    async fn set_task_last_run(self, id: i32, last_run: DateTime<Utc>) -> Result<()> {
+        let mut conn = self.acquire().await?;
+        conn.set_task_last_run(id, last_run).await
+    }
+    async fn set_task_catch_up(self, id: i32, catch_up: CatchUp) -> Result<()> {
+        let mut conn = self.acquire().await?;
+        conn.set_task_catch_up(id, catch_up).await
+    }
     async fn get_all_tasks(self) -> Result<Vec<Task>> {
         let mut conn = self.acquire().await?;
         conn.get_all_tasks().await
@@ -440,4 +2371,12 @@ impl WallaceDBClient for &PgPool {
         let mut conn = self.acquire().await?;
         conn.get_all_tasks_in_channel(id).await
     }
+    async fn get_all_tasks_in_channels(self, ids: Vec<u64>) -> Result<Vec<Task>> {
+        let mut conn = self.acquire().await?;
+        conn.get_all_tasks_in_channels(ids).await
+    }
+    async fn delete_tasks_in_channel(self, id: u64) -> Result<Vec<i32>> {
+        let mut conn = self.acquire().await?;
+        conn.delete_tasks_in_channel(id).await
+    }
 }
@@ -9,39 +9,60 @@ use anyhow::anyhow;
 use async_openai::{config::OpenAIConfig, Client as OpenAIClient};
 use async_trait::async_trait;
 use chrono::Utc;
+use chrono_tz::Tz;
 use rand::{rngs::StdRng, Rng, SeedableRng};
 use serenity::{
-    client::{Client as DiscordClient, Context, EventHandler},
-    framework::standard::{
-        help_commands::with_embeds,
-        macros::{help, hook},
-        Args, BucketBuilder, CommandError, CommandGroup, CommandResult, Configuration,
-        DispatchError, HelpOptions, StandardFramework,
+    all::Interaction,
+    builder::{CreateAllowedMentions, CreateMessage},
+    client::{Client as DiscordClient, Context, EventHandler, FullEvent},
+    framework::{
+        standard::{
+            help_commands::with_embeds,
+            macros::{help, hook},
+            Args, BucketBuilder, Command, CommandError, CommandGroup, CommandResult, Configuration,
+            DispatchError, HelpOptions, StandardFramework,
+        },
+        Framework,
     },
     gateway::ActivityData,
-    http::Http,
-    model::prelude::{ChannelId, GatewayIntents, GuildId, Message, Ready, ResumedEvent, UserId},
+    http::{Http, HttpError},
+    model::prelude::{
+        ChannelId, GatewayIntents, Guild, GuildChannel, GuildId, Message, Ready, ResumedEvent,
+        UnavailableGuild, User, UserId, VoiceState,
+    },
     prelude::TypeMapKey,
 };
 use songbird::Songbird;
 use sqlx::PgPool;
-use strum::EnumString;
-use tokio::{sync::Mutex, task::JoinHandle, time::Duration};
+use strum::{Display, EnumString, VariantNames};
+use tokio::{
+    sync::{mpsc::UnboundedReceiver, Mutex, RwLock},
+    time::Duration,
+};
 use tracing::{error, info, warn};
 
 use crate::{
     commands::{
+        activity::{credit_activity, guild_voice_earnings, ActivityTracker},
         ai_voice::{WallaceAIConv, AIVOICE_GROUP},
         bank::BANK_GROUP,
+        blackjack::{blackjack_component, BlackjackTables},
+        config::CONFIG_GROUP,
         cooltext::COOLTEXT_GROUP,
         emote::EMOTE_GROUP,
         general::GENERAL_GROUP,
+        items::ITEMS_GROUP,
+        loans::LOAN_INTEREST_PERCENT,
+        market::{trade_component, TradeOffers},
         riot::{lol_report, LOL_GROUP, TFT_GROUP},
+        roulette::RouletteTables,
         scheduling::SCHEDULING_GROUP,
+        slash::{autocomplete_slash_command, register_slash_commands, run_slash_command},
         spells::{random_name, SPELLS_GROUP},
     },
     database::WallaceDBClient,
-    model::Task,
+    model::{GuildSettings, Task},
+    scheduler::{Scheduler, SchedulerHandle, SchedulerMsg, TaskRunner},
     services::{riot_api::RiotAPIClients, set_server_name},
 };
 
@@ -49,12 +70,27 @@ pub static WALLACE_VERSION: std::sync::OnceLock<String> = std::sync::OnceLock::n
 
 pub const PREFIX: &str = "!";
 
+pub static GROUPS: &[&CommandGroup] = &[
+    &GENERAL_GROUP,
+    &AIVOICE_GROUP,
+    &BANK_GROUP,
+    &ITEMS_GROUP,
+    &SPELLS_GROUP,
+    &EMOTE_GROUP,
+    &COOLTEXT_GROUP,
+    &SCHEDULING_GROUP,
+    &CONFIG_GROUP,
+    &LOL_GROUP,
+    &TFT_GROUP,
+];
+
 pub async fn build_bot(
     discord_token: String,
     riot_token_lol: String,
     riot_token_tft: String,
     db_url: String,
     openai_token: String,
+    message_content: bool,
 ) -> DiscordClient {
     WALLACE_VERSION.get_or_init(|| {
         format!(
@@ -85,51 +121,62 @@ pub async fn build_bot(
         Err(why) => panic!("Could not access application info: {:?}", why),
     };
 
-    let framework = StandardFramework::new()
-        .unrecognised_command(unknown_command_hook)
-        .after(after_hook)
-        .on_dispatch_error(dispatch_error_hook)
-        .bucket("slots", BucketBuilder::new_channel().delay(10))
-        .await
-        .group(&GENERAL_GROUP)
-        .group(&AIVOICE_GROUP)
-        .group(&BANK_GROUP)
-        .group(&SPELLS_GROUP)
-        .group(&EMOTE_GROUP)
-        .group(&COOLTEXT_GROUP)
-        .group(&SCHEDULING_GROUP)
-        .group(&LOL_GROUP)
-        .group(&TFT_GROUP)
+    let framework = GROUPS
+        .iter()
+        .fold(
+            StandardFramework::new()
+                .unrecognised_command(unknown_command_hook)
+                .before(before_hook)
+                .after(after_hook)
+                .on_dispatch_error(dispatch_error_hook)
+                .bucket("slots", BucketBuilder::new_channel().delay(10))
+                .await,
+            |f, g| f.group(g),
+        )
         .help(&HELP_COMMAND);
     framework.configure(
         Configuration::new()
-            .prefix(PREFIX)
-            .owners(owners)
+            // Only the dynamic prefix, so that the default one doesn't work in guilds that replaced it
+            .prefix("")
+            .dynamic_prefix(guild_prefix)
+            .owners(owners.clone())
             .case_insensitivity(true)
             .on_mention(Some(bot_id)),
     );
+    // Shared with the slash command handler, which dispatches to it manually
+    let framework = Arc::new(framework);
     let songbird = Songbird::serenity();
-    let client = DiscordClient::builder(
-        discord_token,
-        GatewayIntents::non_privileged()
-            | GatewayIntents::MESSAGE_CONTENT
-            | GatewayIntents::GUILD_MESSAGES,
-    )
-    .event_handler(Handler)
-    .framework(framework)
-    .voice_manager_arc(songbird.clone())
-    .await
-    .expect("Error creating Discord client");
+    // Without the message content intent, only messages that mention Wallace and DMs have content.
+    // Slash commands and prefix commands after a mention still work,
+    // but the WALLACE reaction and activity earnings from messages need the intent.
+    let mut intents = GatewayIntents::non_privileged() | GatewayIntents::GUILD_MESSAGES;
+    if message_content {
+        intents |= GatewayIntents::MESSAGE_CONTENT;
+    }
+    let client = DiscordClient::builder(discord_token, intents)
+        .event_handler(Handler)
+        .framework(SharedFramework(framework.clone()))
+        .voice_manager_arc(songbird.clone())
+        .await
+        .expect("Error creating Discord client");
 
     let db = sqlx::pool::Pool::connect(&db_url)
         .await
         .expect("Database connection failed.");
     info!("Connected to database!");
+    // Games that were cut short by a restart can't be finished
+    match db.refund_held_bets(None).await {
+        Ok(0) => (),
+        Ok(n) => info!("Refunded {n} bets of unfinished games."),
+        Err(e) => error!("Failed to refund bets of unfinished games: {e}"),
+    }
 
     // Insert shared data
     {
         // Open the data lock in write mode, so that entries can be inserted.
         let mut data = client.data.write().await;
+        data.insert::<WallaceFramework>(framework);
+        data.insert::<WallaceOwners>(Arc::new(owners));
         data.insert::<WallaceSongbird>(songbird);
         data.insert::<WallaceRiot>(Arc::new(RiotAPIClients::new(
             &riot_token_lol,
@@ -140,14 +187,104 @@ pub async fn build_bot(
             OpenAIConfig::new().with_api_key(openai_token),
         )));
         data.insert::<WallaceOpenAIConvos>(Default::default());
-        let (tx, rx) = tokio::sync::mpsc::channel::<()>(1);
-        data.insert::<TaskSignal>(Arc::new(tx));
-        data.insert::<TaskSignalRx>(rx);
+        data.insert::<WallaceBlackjack>(Default::default());
+        data.insert::<WallaceRoulette>(Default::default());
+        data.insert::<WallaceActivity>(Default::default());
+        data.insert::<WallaceTrades>(Default::default());
+        data.insert::<WallaceGuildSettings>(Default::default());
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        data.insert::<WallaceScheduler>(tx);
+        data.insert::<WallaceSchedulerRx>(rx);
     } // Release lock
 
     client
 }
 
+struct SharedFramework(Arc<StandardFramework>);
+#[async_trait]
+impl Framework for SharedFramework {
+    async fn dispatch(&self, ctx: Context, event: FullEvent) {
+        self.0.dispatch(ctx, event).await
+    }
+}
+
+struct WallaceFramework;
+type TWallaceFramework = Arc<StandardFramework>;
+impl TypeMapKey for WallaceFramework {
+    type Value = TWallaceFramework;
+}
+pub async fn get_framework(ctx: &Context) -> TWallaceFramework {
+    ctx.data
+        .read()
+        .await
+        .get::<WallaceFramework>()
+        .expect("type in typemap")
+        .clone()
+}
+
+struct WallaceOwners;
+impl TypeMapKey for WallaceOwners {
+    type Value = Arc<HashSet<UserId>>;
+}
+/// If the user owns the bot.
+pub async fn is_owner(ctx: &Context, user_id: UserId) -> bool {
+    ctx.data
+        .read()
+        .await
+        .get::<WallaceOwners>()
+        .expect("type in typemap")
+        .contains(&user_id)
+}
+
+/// Run a prefix command line through the framework as if `author` sent it.
+/// `msg` is a message sent by the bot, and is used as the invoking message
+/// so that replies and reactions have a target.
+pub async fn dispatch_as(
+    ctx: &Context,
+    mut msg: Message,
+    author: User,
+    guild_id: Option<GuildId>,
+    line: String,
+) {
+    msg.author = author;
+    msg.guild_id = guild_id;
+    msg.member = None;
+    msg.webhook_id = None;
+    // Lines are written with the default prefix, which the guild might not use
+    msg.content = match (guild_id, line.strip_prefix(PREFIX)) {
+        (Some(gid), Some(rest)) => {
+            format!("{}{rest}", get_guild_settings(ctx, gid.get()).await.prefix)
+        }
+        _ => line,
+    };
+    get_framework(ctx)
+        .await
+        .dispatch(ctx.clone(), FullEvent::Message { new_message: msg })
+        .await;
+}
+
+/// Find the group of a registered command by name or alias.
+pub fn find_group(name: &str) -> Option<&'static CommandGroup> {
+    GROUPS
+        .iter()
+        .find(|g| {
+            g.options
+                .commands
+                .iter()
+                .any(|c| c.options.names.iter().any(|n| n.eq_ignore_ascii_case(name)))
+        })
+        .copied()
+}
+
+/// Find a registered command by name or alias.
+pub fn find_command(name: &str) -> Option<&'static Command> {
+    GROUPS
+        .iter()
+        .flat_map(|g| g.options.commands.iter())
+        .find(|c| c.options.names.iter().any(|n| n.eq_ignore_ascii_case(name)))
+        .copied()
+}
+
 struct WallaceSongbird;
 type TWallaceSongbird = Arc<Songbird>;
 impl TypeMapKey for WallaceSongbird {
@@ -218,22 +355,125 @@ pub async fn get_openai_convos(ctx: &Context) -> TWallaceOpenAIConvos {
         .clone()
 }
 
-struct TaskSignal;
-type TTaskSignal = Arc<tokio::sync::mpsc::Sender<()>>;
-impl TypeMapKey for TaskSignal {
-    type Value = TTaskSignal;
+struct WallaceBlackjack;
+type TWallaceBlackjack = Arc<Mutex<BlackjackTables>>;
+impl TypeMapKey for WallaceBlackjack {
+    type Value = TWallaceBlackjack;
+}
+pub async fn get_blackjack_tables(ctx: &Context) -> TWallaceBlackjack {
+    ctx.data
+        .read()
+        .await
+        .get::<WallaceBlackjack>()
+        .expect("type in typemap")
+        .clone()
+}
+
+struct WallaceRoulette;
+type TWallaceRoulette = Arc<Mutex<RouletteTables>>;
+impl TypeMapKey for WallaceRoulette {
+    type Value = TWallaceRoulette;
+}
+pub async fn get_roulette_tables(ctx: &Context) -> TWallaceRoulette {
+    ctx.data
+        .read()
+        .await
+        .get::<WallaceRoulette>()
+        .expect("type in typemap")
+        .clone()
+}
+
+struct WallaceActivity;
+type TWallaceActivity = Arc<Mutex<ActivityTracker>>;
+impl TypeMapKey for WallaceActivity {
+    type Value = TWallaceActivity;
 }
-pub async fn get_task_signal(ctx: &Context) -> TTaskSignal {
+pub async fn get_activity_tracker(ctx: &Context) -> TWallaceActivity {
     ctx.data
         .read()
         .await
-        .get::<TaskSignal>()
+        .get::<WallaceActivity>()
         .expect("type in typemap")
         .clone()
 }
-struct TaskSignalRx;
-impl TypeMapKey for TaskSignalRx {
-    type Value = tokio::sync::mpsc::Receiver<()>;
+
+struct WallaceTrades;
+type TWallaceTrades = Arc<Mutex<TradeOffers>>;
+impl TypeMapKey for WallaceTrades {
+    type Value = TWallaceTrades;
+}
+pub async fn get_trade_offers(ctx: &Context) -> TWallaceTrades {
+    ctx.data
+        .read()
+        .await
+        .get::<WallaceTrades>()
+        .expect("type in typemap")
+        .clone()
+}
+
+struct WallaceGuildSettings;
+type TWallaceGuildSettings = Arc<RwLock<HashMap<u64, Arc<GuildSettings>>>>;
+impl TypeMapKey for WallaceGuildSettings {
+    type Value = TWallaceGuildSettings;
+}
+/// The settings of a guild. Cached, and kept up to date by [`set_guild_settings`].
+pub async fn get_guild_settings(ctx: &Context, guild_id: u64) -> Arc<GuildSettings> {
+    let cache = guild_settings_cache(ctx).await;
+    if let Some(s) = cache.read().await.get(&guild_id) {
+        return s.clone();
+    }
+    // Loaded under the lock, so that settings saved meanwhile are not replaced by older ones
+    let mut cache = cache.write().await;
+    if let Some(s) = cache.get(&guild_id) {
+        return s.clone();
+    }
+    let settings = match get_db_handler(ctx).await.get_guild_settings(guild_id).await {
+        Ok(s) => Arc::new(s),
+        // Not cached, so that the real settings are used once the database is back
+        Err(_) => return Default::default(),
+    };
+    cache.insert(guild_id, settings.clone());
+    settings
+}
+/// Save the settings of a guild, and cache them.
+pub async fn set_guild_settings(
+    ctx: &Context,
+    guild_id: u64,
+    settings: &GuildSettings,
+) -> anyhow::Result<()> {
+    let cache = guild_settings_cache(ctx).await;
+    let mut cache = cache.write().await;
+    get_db_handler(ctx)
+        .await
+        .set_guild_settings(guild_id, settings)
+        .await?;
+    cache.insert(guild_id, Arc::new(settings.clone()));
+    Ok(())
+}
+async fn guild_settings_cache(ctx: &Context) -> TWallaceGuildSettings {
+    ctx.data
+        .read()
+        .await
+        .get::<WallaceGuildSettings>()
+        .expect("type in typemap")
+        .clone()
+}
+
+struct WallaceScheduler;
+impl TypeMapKey for WallaceScheduler {
+    type Value = SchedulerHandle;
+}
+pub async fn get_scheduler(ctx: &Context) -> SchedulerHandle {
+    ctx.data
+        .read()
+        .await
+        .get::<WallaceScheduler>()
+        .expect("type in typemap")
+        .clone()
+}
+struct WallaceSchedulerRx;
+impl TypeMapKey for WallaceSchedulerRx {
+    type Value = UnboundedReceiver<SchedulerMsg>;
 }
 
 const REACTIONS: &[char] = &['😳', '😏', '😊', '😎'];
@@ -254,11 +494,53 @@ impl EventHandler for Handler {
             ))
         };
         let _ = ctx.set_activity(Some(activity));
+        if let Err(e) = register_slash_commands(&ctx).await {
+            warn!("Failed to register slash commands: {e:?}");
+        }
     }
 
     async fn cache_ready(&self, ctx: Context, guilds: Vec<GuildId>) {
         info!("Loaded {} guilds.", guilds.len());
-        tokio::spawn(schedule_loop(ctx));
+        // Start counting voice time for users that were already in voice channels
+        let now = Utc::now();
+        let tracker = get_activity_tracker(&ctx).await;
+        let mut tracker = tracker.lock().await;
+        for gid in guilds {
+            for (uid, earning) in guild_voice_earnings(&ctx, gid) {
+                tracker.voice(gid.get(), uid, earning, now);
+            }
+        }
+        drop(tracker);
+        built_in_tasks(ctx.clone()).await;
+        let rx = ctx
+            .data
+            .write()
+            .await
+            .remove::<WallaceSchedulerRx>()
+            .expect("brudda");
+        tokio::spawn(Scheduler::new(DiscordRunner { ctx }).run(rx));
+    }
+
+    async fn channel_delete(
+        &self,
+        ctx: Context,
+        channel: GuildChannel,
+        _messages: Option<Vec<Message>>,
+    ) {
+        remove_channel_tasks(&ctx, channel.id).await;
+    }
+
+    async fn guild_delete(&self, ctx: Context, incomplete: UnavailableGuild, full: Option<Guild>) {
+        // Unavailable guilds are only down for now
+        if incomplete.unavailable {
+            return;
+        }
+        let Some(guild) = full else {
+            return;
+        };
+        for id in guild.channels.keys() {
+            remove_channel_tasks(&ctx, *id).await;
+        }
     }
 
     async fn resume(&self, _ctx: Context, _r: ResumedEvent) {
@@ -268,15 +550,108 @@ impl EventHandler for Handler {
     async fn message(&self, ctx: Context, msg: Message) {
         if msg.content.to_uppercase().contains("WALLACE")
             && msg.author != ctx.cache.current_user().to_owned().into()
+            && match msg.guild_id {
+                Some(gid) => get_guild_settings(&ctx, gid.get()).await.react_to_name,
+                None => true,
+            }
         {
             let mut rng: StdRng = SeedableRng::from_entropy();
             let _ = msg
-                .react(ctx, REACTIONS[rng.gen_range(0..REACTIONS.len())])
+                .react(&ctx, REACTIONS[rng.gen_range(0..REACTIONS.len())])
                 .await;
         }
+        if let Some(gid) = msg.guild_id {
+            if !msg.author.bot {
+                get_activity_tracker(&ctx).await.lock().await.message(
+                    gid.get(),
+                    msg.author.id.get(),
+                    &msg.content,
+                    Utc::now(),
+                );
+            }
+        }
+    }
+
+    async fn voice_state_update(&self, ctx: Context, _old: Option<VoiceState>, new: VoiceState) {
+        let Some(gid) = new.guild_id else {
+            return;
+        };
+        if new.member.as_ref().is_some_and(|m| m.user.bot) {
+            return;
+        }
+        // Joining or leaving can change whether the others in the channel earn
+        let mut earnings = guild_voice_earnings(&ctx, gid);
+        if !earnings.iter().any(|(uid, _)| *uid == new.user_id.get()) {
+            earnings.push((new.user_id.get(), false));
+        }
+        let now = Utc::now();
+        let tracker = get_activity_tracker(&ctx).await;
+        let mut tracker = tracker.lock().await;
+        for (uid, earning) in earnings {
+            tracker.voice(gid.get(), uid, earning, now);
+        }
+    }
+
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        let res = match interaction {
+            Interaction::Command(c) => run_slash_command(&ctx, c).await,
+            Interaction::Autocomplete(c) => autocomplete_slash_command(&ctx, c).await,
+            Interaction::Component(c) if c.data.custom_id.starts_with("trade:") => {
+                trade_component(&ctx, &c).await
+            }
+            Interaction::Component(c) => blackjack_component(&ctx, &c).await,
+            _ => Ok(()),
+        };
+        if let Err(why) = res {
+            warn!("Error in interaction: {:?}", why);
+        }
+    }
+}
+
+/// The prefix of the guild, or the default one outside of guilds.
+pub async fn get_prefix(ctx: &Context, guild_id: Option<GuildId>) -> String {
+    match guild_id {
+        Some(gid) => get_guild_settings(ctx, gid.get()).await.prefix.clone(),
+        None => PREFIX.to_owned(),
     }
 }
 
+#[hook]
+async fn guild_prefix(ctx: &Context, msg: &Message) -> Option<String> {
+    Some(get_prefix(ctx, msg.guild_id).await)
+}
+
+/// Tell the author if the group of the command in the message is turned off in the guild.
+/// Returns whether it is.
+async fn refuse_disabled_group(ctx: &Context, msg: &Message) -> bool {
+    let Some(g) = disabled_group(ctx, msg).await else {
+        return false;
+    };
+    let _ = msg
+        .channel_id
+        .say(ctx, format!("{} is turned off in this server 😔", g.name))
+        .await;
+    true
+}
+async fn disabled_group(ctx: &Context, msg: &Message) -> Option<&'static CommandGroup> {
+    let settings = get_guild_settings(ctx, msg.guild_id?.get()).await;
+    // The command name given to hooks is that of the sub-command, so look at what was typed instead
+    let content = msg.content.to_lowercase();
+    let name = content
+        .strip_prefix(settings.prefix.as_str())
+        .or_else(|| {
+            let mention = content.trim_start().strip_prefix("<@")?;
+            Some(&mention[mention.find('>')? + 1..])
+        })
+        .and_then(|s| s.split_whitespace().next())?;
+    find_group(name).filter(|g| settings.disabled_groups.iter().any(|d| d == g.name))
+}
+
+#[hook]
+async fn before_hook(ctx: &Context, msg: &Message, _cmd_name: &str) -> bool {
+    !refuse_disabled_group(ctx, msg).await
+}
+
 #[hook]
 async fn unknown_command_hook(ctx: &Context, msg: &Message, unknown_command_name: &str) {
     let _ = msg
@@ -301,6 +676,10 @@ async fn after_hook(ctx: &Context, msg: &Message, cmd_name: &str, error: Result<
 
 #[hook]
 async fn dispatch_error_hook(ctx: &Context, msg: &Message, err: DispatchError, cmd_name: &str) {
+    // Argument counts, permissions and buckets are checked before the before hook
+    if refuse_disabled_group(ctx, msg).await {
+        return;
+    }
     if let Some(s) = match err {
         DispatchError::NotEnoughArguments { min, given } => {
             Some(format!("Need {} arguments, but only got {} 😋", min, given))
@@ -341,91 +720,220 @@ async fn help_command(
     Ok(())
 }
 
-const WEEKLY_PAYOUT: i64 = 15;
 async fn built_in_tasks(ctx: Context) {
     let db = get_db_handler(&ctx).await;
-    // Weekly payout
+    // Weekly payout. Each week is only paid once, and a missed payout is dealt on startup.
+    // Loan repayments are deducted from it.
+    spawn_built_in_task(
+        "0 0 8 * * Mon *",
+        chrono::Duration::try_weeks(1).unwrap(),
+        move |week| {
+            let db = db.clone();
+            async move {
+                let paid = db
+                    .pay_weekly_payout(week.date_naive(), GuildSettings::default().weekly_payout)
+                    .await?;
+                if paid {
+                    info!("Veckopeng has been dealt for week of {week}.");
+                }
+                Ok(())
+            }
+        },
+    );
+    let db = get_db_handler(&ctx).await;
+    // Daily loan interest, which also marks overdue loans as defaulted.
+    spawn_built_in_task(
+        "0 0 8 * * * *",
+        chrono::Duration::try_days(1).unwrap(),
+        move |day| {
+            let db = db.clone();
+            async move {
+                let accrued = db
+                    .accrue_loan_interest(day.date_naive(), LOAN_INTEREST_PERCENT)
+                    .await?;
+                if accrued {
+                    info!("Loan interest has been added for {day}.");
+                }
+                Ok(())
+            }
+        },
+    );
+    // Activity earnings are credited in batches
+    spawn_built_in_task(
+        "0 */5 * * * * *",
+        chrono::Duration::try_minutes(5).unwrap(),
+        move |_| {
+            let ctx = ctx.clone();
+            async move { credit_activity(&ctx).await }
+        },
+    );
+}
+
+/// Run `job` for the latest time in `schedule` within the last `period`, and then on schedule.
+/// The job should only do its thing once per time, so that a missed run is done on startup.
+fn spawn_built_in_task<F, Fut>(schedule: &str, period: chrono::Duration, job: F)
+where
+    F: Fn(chrono::DateTime<Utc>) -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = anyhow::Result<()>> + Send,
+{
+    let s = cron::Schedule::from_str(schedule).unwrap();
     tokio::spawn(async move {
-        let s = cron::Schedule::from_str("0 0 8 * * Mon *").unwrap();
-        for next in s.upcoming(Utc) {
-            tokio::time::sleep(
-                (next - Utc::now())
-                    .to_std()
-                    .expect("Failed time conversion"),
-            )
-            .await;
-            info!("Time for veckopeng.");
-            for u in db.get_all_users().await.expect("Could not fetch users") {
-                info!("Veckopeng for {}.", u.id as u64);
-                let _ = db
-                    .add_bank_account_balance(u.id as u64, WEEKLY_PAYOUT)
-                    .await;
+        loop {
+            let now = Utc::now();
+            let latest = s.after(&(now - period)).take_while(|t| *t <= now).last();
+            if let Some(t) = latest {
+                if let Err(e) = job(t).await {
+                    error!("Failed built-in task for {t}: {e:?}. Retrying in 60 secs.");
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    continue;
+                }
             }
-            info!("Veckopeng has been dealt.");
+            let next = s.upcoming(Utc).next().unwrap();
+            tokio::time::sleep((next - Utc::now()).to_std().unwrap_or_default()).await;
         }
     });
 }
 
-async fn schedule_loop(ctx: Context) {
-    built_in_tasks(ctx.clone()).await;
-    let mut running_tasks: HashMap<i32, JoinHandle<()>> = HashMap::new();
-    let db = get_db_handler(&ctx).await;
-    let mut rx = ctx
-        .data
-        .write()
-        .await
-        .remove::<TaskSignalRx>()
-        .expect("brudda");
-    loop {
-        let tasks = match db.get_all_tasks().await {
-            Ok(tasks) => tasks,
-            Err(e) => {
-                error!("Failed to get tasks: {e:?}. Retrying in 60 secs.");
-                tokio::time::sleep(Duration::from_secs(60)).await;
-                continue;
-            }
-        };
-        let mut db_task_ids: HashSet<i32> = HashSet::new();
-        for t in &tasks {
-            db_task_ids.insert(t.id);
+/// Runs tasks in Discord and keeps track of them in the database.
+#[derive(Clone)]
+struct DiscordRunner {
+    ctx: Context,
+}
+
+#[async_trait]
+impl TaskRunner for DiscordRunner {
+    async fn load_tasks(&self) -> anyhow::Result<Vec<Task>> {
+        get_db_handler(&self.ctx).await.get_all_tasks().await
+    }
+    async fn timezone(&self, t: &Task) -> Tz {
+        task_timezone(&self.ctx, t).await
+    }
+    async fn set_last_run(&self, t: &Task, last_run: chrono::DateTime<Utc>) {
+        let db = get_db_handler(&self.ctx).await;
+        if let Err(e) = db.set_task_last_run(t.id, last_run).await {
+            warn!("Failed to set last run of task {}: {}", t.id, e);
         }
-        for rt in &running_tasks {
-            if !db_task_ids.contains(rt.0) {
-                rt.1.abort();
+    }
+    async fn run(&self, t: &Task) -> anyhow::Result<()> {
+        run_task(&self.ctx, t).await
+    }
+    async fn finished(&self, t: &Task, res: anyhow::Result<()>) {
+        let db = get_db_handler(&self.ctx).await;
+        match res {
+            Ok(()) => {
+                info!("Remove task {}", t.id);
+                if let Err(e) = db.delete_task(t.id).await {
+                    warn!("Failed to remove task {}: {}", t.id, e);
+                };
+            }
+            Err(_) if channel_is_gone(&self.ctx, ChannelId::new(t.channel_id as u64)).await => {
+                remove_channel_tasks(&self.ctx, ChannelId::new(t.channel_id as u64)).await;
+            }
+            Err(e) => {
+                warn!("Pause failed task {}: {}", t.id, e);
+                if let Err(e) = db.set_task_enabled(t.id, false).await {
+                    warn!("Failed to pause task {}: {}", t.id, e);
+                };
             }
         }
-        for t in tasks.into_iter() {
-            running_tasks.entry(t.id).or_insert_with(|| {
-                let ctx = ctx.clone();
-                let db = db.clone();
-                tokio::spawn(async move {
-                    let s = cron::Schedule::from_str(&t.cron).expect("Invalid cron string");
-                    if let Ok(task) = t.cmd.parse::<ScheduleTask>() {
-                        for next in s.upcoming(Utc) {
-                            tokio::time::sleep(
-                                (next - Utc::now())
-                                    .to_std()
-                                    .expect("Failed time conversion"),
-                            )
-                            .await;
-                            if task.run(&ctx, &t).await.is_err() {
-                                break;
-                            }
-                        }
-                    }
-                    info!("Remove task {}", t.id);
-                    if let Err(e) = db.delete_task(t.id).await {
-                        warn!("Failed to remove task {}: {}", t.id, e);
-                    };
-                })
-            });
+    }
+}
+
+/// If Discord says that a channel doesn't exist.
+pub async fn channel_is_gone(ctx: &Context, id: ChannelId) -> bool {
+    matches!(
+        id.to_channel(ctx).await,
+        Err(serenity::Error::Http(HttpError::UnsuccessfulRequest(ref e))) if e.status_code.as_u16() == 404
+    )
+}
+
+/// Remove the tasks in a channel that was deleted.
+pub async fn remove_channel_tasks(ctx: &Context, id: ChannelId) -> usize {
+    let ids = match get_db_handler(ctx)
+        .await
+        .delete_tasks_in_channel(id.get())
+        .await
+    {
+        Ok(ids) => ids,
+        Err(e) => {
+            warn!("Failed to remove tasks in channel {id}: {e}");
+            return 0;
         }
-        rx.recv().await.expect("channel to be open");
-        warn!("New task loop");
+    };
+    let scheduler = get_scheduler(ctx).await;
+    for id in &ids {
+        info!("Remove task {id} in deleted channel");
+        scheduler
+            .send(SchedulerMsg::Remove(*id))
+            .expect("scheduler to be running");
     }
+    ids.len()
 }
 
-#[derive(EnumString)]
+/// The default timezone of a guild, or UTC.
+pub async fn guild_timezone(ctx: &Context, guild_id: Option<GuildId>) -> Tz {
+    let Some(gid) = guild_id else {
+        return Tz::UTC;
+    };
+    get_guild_settings(ctx, gid.get())
+        .await
+        .timezone
+        .as_deref()
+        .and_then(|tz| tz.parse().ok())
+        .unwrap_or(Tz::UTC)
+}
+
+/// The timezone of a task, or the default timezone of its guild.
+pub async fn task_timezone(ctx: &Context, t: &Task) -> Tz {
+    if let Some(tz) = t.timezone.as_deref().and_then(|tz| tz.parse().ok()) {
+        return tz;
+    }
+    let guild_id = ctx.cache.channel(t.channel_id as u64).map(|c| c.guild_id);
+    guild_timezone(ctx, guild_id).await
+}
+
+/// Built-in tasks, or any registered command.
+pub fn is_schedulable(cmd: &str) -> bool {
+    cmd.parse::<ScheduleTask>().is_ok() || find_command(cmd).is_some()
+}
+
+/// Run a task and record the result.
+pub async fn run_task(ctx: &Context, t: &Task) -> anyhow::Result<()> {
+    let res = match t.cmd.parse::<ScheduleTask>() {
+        Ok(task) => task.run(ctx, t).await,
+        Err(_) if is_schedulable(&t.cmd) => run_command_task(ctx, t).await,
+        Err(_) => Err(anyhow!("Invalid command to schedule")),
+    };
+    let result = match res {
+        Ok(()) => "ok".to_owned(),
+        Err(ref e) => e.to_string(),
+    };
+    if let Err(e) = get_db_handler(ctx)
+        .await
+        .set_task_result(t.id, result)
+        .await
+    {
+        warn!("Failed to set result of task {}: {}", t.id, e);
+    }
+    res
+}
+
+/// Run the command of a task as the user that created it.
+async fn run_command_task(ctx: &Context, t: &Task) -> anyhow::Result<()> {
+    let creator = t.creator_id.ok_or_else(|| anyhow!("Task has no creator"))?;
+    let author = UserId::new(creator as u64).to_user(ctx).await?;
+    let channel = ChannelId::new(t.channel_id as u64);
+    let guild_id = ctx.cache.channel(channel).map(|c| c.guild_id);
+    let line = match t.arg {
+        Some(ref arg) => format!("{PREFIX}{} {arg}", t.cmd),
+        None => format!("{PREFIX}{}", t.cmd),
+    };
+    let msg = channel.say(ctx, format!("⏰ `{line}`")).await?;
+    dispatch_as(ctx, msg, author, guild_id, line).await;
+    Ok(())
+}
+
+#[derive(Display, EnumString, VariantNames)]
 pub enum ScheduleTask {
     #[strum(serialize = "say")]
     Say,
@@ -435,6 +943,8 @@ pub enum ScheduleTask {
     RandomName,
     #[strum(serialize = "lolweekly")]
     LolWeekly,
+    #[strum(serialize = "remind")]
+    Remind,
 }
 
 impl ScheduleTask {
@@ -444,9 +954,9 @@ impl ScheduleTask {
             ScheduleTask::Say => {
                 let arg = match data.arg {
                     Some(ref s) => s,
-                    None => return Err(anyhow!("")),
+                    None => return Err(anyhow!("Nothing to say")),
                 };
-                let _ = ChannelId::new(data.channel_id as u64).say(ctx, arg).await;
+                ChannelId::new(data.channel_id as u64).say(ctx, arg).await?;
             }
             ScheduleTask::RandomName => {
                 let g = match ctx
@@ -455,7 +965,7 @@ impl ScheduleTask {
                     .and_then(|c| c.guild(ctx))
                 {
                     Some(g) => g.to_owned(),
-                    None => return Err(anyhow!("")),
+                    None => return Err(anyhow!("Channel is not in a server")),
                 };
                 if let Ok((s, o)) = db.get_guild_random_names(g.id.get()).await {
                     let _ = set_server_name(ctx, g, None, &random_name(s, o)).await;
@@ -468,7 +978,7 @@ impl ScheduleTask {
                     .and_then(|c| c.guild(ctx))
                 {
                     Some(g) => g.to_owned(),
-                    None => return Err(anyhow!("")),
+                    None => return Err(anyhow!("Channel is not in a server")),
                 };
                 if let Ok(s) = db.get_guild_default_name(g.id.get()).await {
                     let _ = set_server_name(ctx, g, None, &s).await;
@@ -477,10 +987,26 @@ impl ScheduleTask {
             ScheduleTask::LolWeekly => {
                 let gc = match ctx.cache.channel(data.channel_id as u64) {
                     Some(gc) => gc.to_owned(),
-                    None => return Err(anyhow!("")),
+                    None => return Err(anyhow!("Channel is not in a server")),
                 };
                 let _ = lol_report(ctx, gc).await;
             }
+            ScheduleTask::Remind => {
+                let (Some(ref arg), Some(uid)) = (&data.arg, data.creator_id) else {
+                    return Err(anyhow!("Nothing to remind about"));
+                };
+                // Only the reminded user is pinged, whatever the reminder says
+                ChannelId::new(data.channel_id as u64)
+                    .send_message(
+                        ctx,
+                        CreateMessage::new()
+                            .content(format!("⏰ <@{uid}> {arg}"))
+                            .allowed_mentions(
+                                CreateAllowedMentions::new().users([UserId::new(uid as u64)]),
+                            ),
+                    )
+                    .await?;
+            }
         };
         Ok(())
     }
@@ -9,6 +9,7 @@ mod commands;
 mod database;
 mod discord;
 mod model;
+mod scheduler;
 mod services;
 
 #[shuttle_runtime::main]
@@ -29,6 +30,10 @@ async fn serenity(#[Secrets] secrets: SecretStore) -> Result<MyService, shuttle_
     let openai_token = secrets
         .get("OPENAI_TOKEN")
         .expect("OpenAI token missing! (env variable `OPENAI_TOKEN`)");
+    // The privileged intent is only requested if the bot is allowed to have it
+    let message_content = secrets
+        .get("MESSAGE_CONTENT")
+        .is_some_and(|v| v.eq_ignore_ascii_case("true"));
 
     let client = build_bot(
         discord_token,
@@ -36,6 +41,7 @@ async fn serenity(#[Secrets] secrets: SecretStore) -> Result<MyService, shuttle_
         riot_token_tft,
         db_url,
         openai_token,
+        message_content,
     )
     .await;
 
@@ -1,3 +1,6 @@
+use chrono::{DateTime, Duration, Utc};
+use strum::{Display, EnumString, VariantNames};
+
 #[derive(sqlx::FromRow)]
 pub struct LoLAccount {
     pub server: String,
@@ -5,18 +8,570 @@ pub struct LoLAccount {
     pub tag: String,
 }
 
-#[derive(sqlx::FromRow)]
+#[derive(Clone, sqlx::FromRow)]
 pub struct Task {
     pub id: i32,
     pub cron: String,
     pub cmd: String,
     pub arg: Option<String>,
     pub channel_id: i64,
+    /// The user that commands are run as. Not set on old tasks.
+    pub creator_id: Option<i64>,
+    /// IANA timezone of the cron schedule. Defaults to the timezone of the guild.
+    pub timezone: Option<String>,
+    pub catch_up: String,
+    /// The last scheduled run that has been handled.
+    pub last_run: DateTime<Utc>,
+    /// "ok", or the error of the last run.
+    pub last_result: Option<String>,
+    /// When the last run was, scheduled or not.
+    pub last_result_at: Option<DateTime<Utc>>,
+    /// Paused tasks are not run.
+    pub enabled: bool,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What to do with runs of a task that were missed while the bot was down.
+#[derive(Clone, Copy, Debug, Display, EnumString, VariantNames)]
+#[strum(serialize_all = "lowercase")]
+pub enum CatchUp {
+    Skip,
+    Once,
+    All,
 }
 
 #[derive(sqlx::FromRow)]
-pub struct User {
-    pub id: i64,
+pub struct BankAccount {
     #[allow(unused)]
-    pub mature: bool,
+    pub guild_id: i64,
+    pub user_id: i64,
+    pub balance: i64,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct BankTransaction {
+    #[allow(unused)]
+    pub id: i32,
+    #[allow(unused)]
+    pub guild_id: i64,
+    #[allow(unused)]
+    pub user_id: i64,
+    pub counterparty_id: Option<i64>,
+    pub amount: i64,
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The progressive jackpot of a guild.
+#[derive(Default, sqlx::FromRow)]
+pub struct Jackpot {
+    #[allow(unused)]
+    pub guild_id: i64,
+    /// In hundredths of a Kapsyl, so that small shares of small bets add up.
+    pub pot: i64,
+    pub winner_id: Option<i64>,
+    pub won: Option<i64>,
+    pub won_at: Option<DateTime<Utc>>,
+}
+
+/// The seeds that the spins of a user are rolled with, until they are revealed.
+#[derive(sqlx::FromRow)]
+pub struct FairSeed {
+    /// Kept secret until revealed. Only its hash is shown.
+    pub server_seed: String,
+    pub client_seed: String,
+    /// The nonce of the next spin.
+    pub nonce: i32,
+}
+
+/// A spin rolled with a fair seed, that can be verified once the seed is revealed.
+#[derive(sqlx::FromRow)]
+pub struct FairSpin {
+    pub id: i32,
+    pub user_id: i64,
+    /// The game and any parameters that affect the outcome.
+    pub game: String,
+    pub server_seed: String,
+    pub client_seed: String,
+    pub nonce: i32,
+    pub outcome: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A reel and payout table for the slots, proposed for a guild.
+#[derive(sqlx::FromRow)]
+pub struct SlotTable {
+    pub id: i32,
+    #[allow(unused)]
+    pub guild_id: i64,
+    /// The rules as JSON.
+    pub rules: String,
+    /// The simulated return to player.
+    #[allow(unused)]
+    pub rtp: f64,
+    #[allow(unused)]
+    pub creator_id: i64,
+    #[allow(unused)]
+    pub created_at: DateTime<Utc>,
+}
+
+/// How the daily reward grows with the streak of daily claims. Part of the [`GuildSettings`].
+#[derive(Clone, Copy, Debug, PartialEq, sqlx::FromRow)]
+pub struct DailyCurve {
+    /// The reward on the first day.
+    #[sqlx(rename = "daily_base")]
+    pub base: i16,
+    /// Added to the reward for each day of the streak.
+    #[sqlx(rename = "daily_bonus")]
+    pub bonus: i16,
+    /// The streak stops growing the reward after this many days.
+    #[sqlx(rename = "daily_max_streak")]
+    pub max_streak: i16,
+}
+impl Default for DailyCurve {
+    fn default() -> Self {
+        Self {
+            base: 1,
+            bonus: 1,
+            max_streak: 7,
+        }
+    }
+}
+impl DailyCurve {
+    pub fn reward(&self, streak: i32) -> i64 {
+        let days = streak.clamp(1, self.max_streak as i32) as i64;
+        self.base as i64 + self.bonus as i64 * (days - 1)
+    }
+}
+
+/// General settings of a guild.
+#[derive(Clone, Debug, PartialEq, sqlx::FromRow)]
+pub struct GuildSettings {
+    pub prefix: String,
+    /// Names of the command groups that are turned off.
+    pub disabled_groups: Vec<String>,
+    /// If messages that mention Wallace get a reaction.
+    pub react_to_name: bool,
+    /// Who Wallace is in AI chat. The default persona is used if not set.
+    pub ai_persona: Option<String>,
+    pub tts_voice: String,
+    pub weekly_payout: i64,
+    /// How much a user can owe the bank.
+    pub credit_limit: i64,
+    /// Where notable events are logged.
+    pub log_channel_id: Option<i64>,
+    pub locale: String,
+    /// The timezone that tasks follow. UTC if not set.
+    pub timezone: Option<String>,
+    pub blackjack_decks: i16,
+    /// Percent of lost bets that go into the jackpot.
+    pub jackpot_share: i16,
+    #[sqlx(flatten)]
+    pub daily: DailyCurve,
+    #[sqlx(flatten)]
+    pub activity: ActivityRates,
+    #[sqlx(flatten)]
+    pub spells: SpellSettings,
+}
+impl Default for GuildSettings {
+    fn default() -> Self {
+        Self {
+            prefix: "!".into(),
+            disabled_groups: vec![],
+            react_to_name: true,
+            ai_persona: None,
+            tts_voice: "onyx".into(),
+            weekly_payout: 15,
+            credit_limit: 50,
+            log_channel_id: None,
+            locale: "en".into(),
+            timezone: None,
+            blackjack_decks: 6,
+            jackpot_share: 10,
+            daily: DailyCurve::default(),
+            activity: ActivityRates::default(),
+            spells: SpellSettings::default(),
+        }
+    }
+}
+impl GuildSettings {
+    /// The language of the locale, in English.
+    pub fn language(&self) -> &'static str {
+        LOCALES
+            .iter()
+            .find(|(l, _)| *l == self.locale)
+            .map_or("English", |(_, name)| name)
+    }
+}
+
+/// The locales that can be set for a guild, and their languages.
+pub static LOCALES: &[(&str, &str)] = &[
+    ("en", "English"),
+    ("sv", "Swedish"),
+    ("no", "Norwegian"),
+    ("da", "Danish"),
+    ("fi", "Finnish"),
+    ("de", "German"),
+    ("fr", "French"),
+    ("es", "Spanish"),
+];
+
+/// Spell prices and restrictions. Part of the [`GuildSettings`].
+#[derive(Clone, Debug, PartialEq, sqlx::FromRow)]
+pub struct SpellSettings {
+    pub unbonk_cost: i64,
+    pub nickname_cost: i64,
+    pub servername_cost: i64,
+    pub gamba_min_bet: i64,
+    /// The name of the biggest gamba size allowed.
+    pub max_gamba_size: String,
+    /// Names of the spells that can't be cast.
+    pub disabled_spells: Vec<String>,
+}
+impl Default for SpellSettings {
+    fn default() -> Self {
+        Self {
+            unbonk_cost: 2,
+            nickname_cost: 1,
+            servername_cost: 3,
+            gamba_min_bet: 1,
+            max_gamba_size: "XXL".into(),
+            disabled_spells: vec![],
+        }
+    }
+}
+impl SpellSettings {
+    pub fn is_disabled(&self, spell: &str) -> bool {
+        self.disabled_spells
+            .iter()
+            .any(|s| s.eq_ignore_ascii_case(spell))
+    }
+}
+
+/// How many hundredths of a Kapsyl activity earns. Part of the [`GuildSettings`].
+#[derive(Clone, Copy, Debug, PartialEq, sqlx::FromRow)]
+pub struct ActivityRates {
+    /// Per eligible message.
+    #[sqlx(rename = "activity_message_reward")]
+    pub message: i16,
+    /// Per minute in a voice channel.
+    #[sqlx(rename = "activity_voice_reward")]
+    pub voice: i16,
+}
+impl Default for ActivityRates {
+    fn default() -> Self {
+        Self {
+            message: 10,
+            voice: 5,
+        }
+    }
+}
+
+/// The streak after a daily claim at `now`, or when the next claim can be made.
+/// The streak is lost if a day is missed.
+pub fn daily_streak(
+    last_claim: Option<DateTime<Utc>>,
+    streak: i32,
+    now: DateTime<Utc>,
+) -> Result<i32, DateTime<Utc>> {
+    let day = Duration::try_days(1).unwrap();
+    match last_claim {
+        Some(t) if now < t + day => Err(t + day),
+        Some(t) if now < t + day * 2 => Ok(streak + 1),
+        _ => Ok(1),
+    }
+}
+
+pub enum DailyClaim {
+    Claimed {
+        streak: i32,
+        reward: i64,
+    },
+    /// Already claimed. Can claim again at this time.
+    Cooldown(DateTime<Utc>),
+}
+
+/// Kapsyler borrowed from the bank by a user, with interest.
+#[derive(Default, sqlx::FromRow)]
+pub struct Loan {
+    #[allow(unused)]
+    pub guild_id: i64,
+    #[allow(unused)]
+    pub user_id: i64,
+    pub debt: i64,
+    pub due_at: DateTime<Utc>,
+    /// Set when the loan is not repaid in time. Cleared when it is.
+    pub defaulted: bool,
+}
+
+/// A pool that users bet against each other in. The winners split the whole pool.
+#[derive(sqlx::FromRow)]
+pub struct BetPool {
+    pub id: i32,
+    pub guild_id: i64,
+    pub creator_id: i64,
+    pub question: String,
+    pub options: Vec<String>,
+    pub closes_at: DateTime<Utc>,
+    /// The index of the winning option. Not set if the pool was cancelled.
+    pub winner: Option<i16>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    #[allow(unused)]
+    pub created_at: DateTime<Utc>,
+}
+impl BetPool {
+    pub fn is_open(&self) -> bool {
+        self.resolved_at.is_none() && self.closes_at > Utc::now()
+    }
+    /// What each user gets back when `winner` wins, or everyone's bets if the pool is cancelled.
+    /// Winners share the pool in proportion to their bets, rounded down,
+    /// and what is left from the rounding goes to the first of the biggest bets on the winner.
+    /// Everyone is refunded if nobody bet on the winner.
+    pub fn payouts(wagers: &[BetWager], winner: Option<i16>) -> Vec<(i64, i64)> {
+        let total: i64 = wagers.iter().map(|w| w.amount).sum();
+        let winners: Vec<&BetWager> = wagers.iter().filter(|w| Some(w.option) == winner).collect();
+        let winning: i64 = winners.iter().map(|w| w.amount).sum();
+        if winning == 0 {
+            return wagers.iter().map(|w| (w.user_id, w.amount)).collect();
+        }
+        let mut payouts: Vec<(i64, i64)> = winners
+            .iter()
+            .map(|w| {
+                let share = w.amount as i128 * total as i128 / winning as i128;
+                (w.user_id, share as i64)
+            })
+            .collect();
+        // The last of the biggest in reverse is the first
+        let (biggest, _) = winners
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|(_, w)| w.amount)
+            .unwrap();
+        let paid: i64 = payouts.iter().map(|p| p.1).sum();
+        payouts[biggest].1 += total - paid;
+        payouts
+    }
+}
+
+/// The escrowed bet of a user in a bet pool.
+#[derive(sqlx::FromRow)]
+pub struct BetWager {
+    #[allow(unused)]
+    pub pool_id: i32,
+    pub user_id: i64,
+    pub option: i16,
+    pub amount: i64,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct InventoryItem {
+    pub item: String,
+    pub amount: i32,
+}
+
+/// Items put up for sale. The items are held by the listing until it is bought or cancelled.
+#[derive(sqlx::FromRow)]
+pub struct MarketListing {
+    pub id: i32,
+    #[allow(unused)]
+    pub guild_id: i64,
+    pub seller_id: i64,
+    pub item: String,
+    pub amount: i32,
+    pub price: i64,
+    #[allow(unused)]
+    pub created_at: DateTime<Utc>,
+}
+
+/// What one side of a trade gives.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TradeGoods {
+    pub kapsyler: i64,
+    /// Item ids and amounts.
+    pub items: Vec<(String, i32)>,
+}
+impl TradeGoods {
+    pub fn is_empty(&self) -> bool {
+        self.kapsyler == 0 && self.items.is_empty()
+    }
+}
+
+#[derive(sqlx::FromRow)]
+pub struct ActiveBuff {
+    pub buff: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// A bonk to record in the bonk stats.
+pub struct BonkEvent {
+    pub guild_id: u64,
+    pub caster_id: u64,
+    /// Who the caster wanted to bonk.
+    pub target_id: u64,
+    /// Who actually got timed out, if anyone.
+    pub victim_id: Option<u64>,
+    pub size: Option<&'static str>,
+    pub chance: Option<u32>,
+    pub outcome: BonkOutcome,
+    pub duration: u32,
+    pub cost: i64,
+}
+
+#[derive(Clone, Copy, Debug, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum BonkOutcome {
+    Hit,
+    Backfire,
+    Blocked,
+    Reflected,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct BonkStats {
+    pub bonked: i64,
+    pub backfired: i64,
+    pub dealt: i64,
+    pub received: i64,
+}
+
+/// A bet that has been paid for, in a game that is not over yet.
+#[derive(sqlx::FromRow)]
+pub struct HeldBet {
+    pub guild_id: i64,
+    pub user_id: i64,
+    pub amount: i64,
+    pub reason: String,
+}
+impl HeldBet {
+    pub fn reason(&self) -> TransactionReason {
+        self.reason.parse().unwrap_or(TransactionReason::Bet)
+    }
+}
+
+/// Why a bank account balance was changed. Stored as text in the ledger.
+#[derive(Clone, Copy, Debug, Display, EnumString)]
+pub enum TransactionReason {
+    #[strum(serialize = "slots")]
+    Slots,
+    #[strum(serialize = "roulette")]
+    Roulette,
+    #[strum(serialize = "blackjack")]
+    Blackjack,
+    #[strum(serialize = "jackpot")]
+    Jackpot,
+    #[strum(serialize = "bet")]
+    Bet,
+    #[strum(serialize = "loan")]
+    Loan,
+    #[strum(serialize = "loan repayment")]
+    LoanRepayment,
+    #[strum(serialize = "daily")]
+    Daily,
+    #[strum(serialize = "activity")]
+    Activity,
+    #[strum(serialize = "gamba")]
+    Gamba,
+    #[strum(serialize = "spell")]
+    Spell,
+    #[strum(serialize = "dalle")]
+    Dalle,
+    #[strum(serialize = "give")]
+    Give,
+    #[strum(serialize = "mint")]
+    Mint,
+    #[strum(serialize = "shop")]
+    Shop,
+    #[strum(serialize = "market")]
+    Market,
+    #[strum(serialize = "trade")]
+    Trade,
+    #[strum(serialize = "weekly payout")]
+    WeeklyPayout,
+    #[strum(serialize = "AI gift")]
+    AiGift,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wager(user_id: i64, option: i16, amount: i64) -> BetWager {
+        BetWager {
+            pool_id: 1,
+            user_id,
+            option,
+            amount,
+        }
+    }
+
+    #[test]
+    fn bet_payouts() {
+        let wagers = [
+            wager(1, 0, 10),
+            wager(2, 0, 30),
+            wager(3, 1, 60),
+            wager(4, 2, 1),
+        ];
+        // Winners split all 101 in proportion to their bets
+        assert_eq!(BetPool::payouts(&wagers, Some(0)), [(1, 25), (2, 76)]);
+        assert_eq!(BetPool::payouts(&wagers, Some(1)), [(3, 101)]);
+        // Nobody bet on the winner, or the pool was cancelled
+        let refunds = [(1, 10), (2, 30), (3, 60), (4, 1)];
+        assert_eq!(BetPool::payouts(&wagers, Some(3)), refunds);
+        assert_eq!(BetPool::payouts(&wagers, None), refunds);
+        assert!(BetPool::payouts(&[], Some(0)).is_empty());
+        // What is left from rounding goes to the first of the biggest bets
+        let wagers = [
+            wager(1, 0, 1),
+            wager(2, 0, 1),
+            wager(3, 0, 1),
+            wager(4, 1, 1),
+        ];
+        assert_eq!(BetPool::payouts(&wagers, Some(0)), [(1, 2), (2, 1), (3, 1)]);
+        let wagers = [
+            wager(1, 0, 3),
+            wager(2, 0, 7),
+            wager(3, 0, 7),
+            wager(4, 1, 5),
+        ];
+        assert_eq!(
+            BetPool::payouts(&wagers, Some(0)),
+            [(1, 3), (2, 10), (3, 9)]
+        );
+        // Payouts always add up to the pool
+        for winner in [Some(0), Some(1), Some(2), None] {
+            let paid: i64 = BetPool::payouts(&wagers, winner).iter().map(|p| p.1).sum();
+            assert_eq!(paid, 22);
+        }
+    }
+
+    #[test]
+    fn daily_rewards() {
+        let curve = DailyCurve::default();
+        let rewards: Vec<i64> = (1..=9).map(|s| curve.reward(s)).collect();
+        assert_eq!(rewards, [1, 2, 3, 4, 5, 6, 7, 7, 7]);
+        let curve = DailyCurve {
+            base: 5,
+            bonus: 0,
+            max_streak: 1,
+        };
+        assert_eq!(curve.reward(1), 5);
+        assert_eq!(curve.reward(30), 5);
+    }
+
+    #[test]
+    fn daily_streaks() {
+        let now = Utc::now();
+        let h = |h| Duration::try_hours(h).unwrap();
+        assert_eq!(daily_streak(None, 0, now), Ok(1));
+        // Too soon
+        assert_eq!(daily_streak(Some(now - h(23)), 3, now), Err(now + h(1)));
+        // Within a day of the cooldown
+        assert_eq!(daily_streak(Some(now - h(24)), 3, now), Ok(4));
+        assert_eq!(daily_streak(Some(now - h(47)), 3, now), Ok(4));
+        // Missed a day
+        assert_eq!(daily_streak(Some(now - h(48)), 3, now), Ok(1));
+    }
 }
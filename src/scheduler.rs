@@ -0,0 +1,365 @@
+use std::{collections::HashMap, str::FromStr};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use tokio::{
+    sync::mpsc::{UnboundedReceiver, UnboundedSender},
+    task::JoinHandle,
+    time::Duration,
+};
+use tracing::{error, info, warn};
+
+use crate::model::{CatchUp, Task};
+
+/// Max number of missed runs of a task to catch up on.
+const MAX_CATCH_UP: usize = 10;
+
+/// A change to the scheduled tasks.
+pub enum SchedulerMsg {
+    Add(Task),
+    Update(Task),
+    Remove(i32),
+    /// Reload all tasks, e.g. when the timezone of a guild changes.
+    ReloadAll,
+}
+
+pub type SchedulerHandle = UnboundedSender<SchedulerMsg>;
+
+/// What the scheduler needs from the outside world.
+#[async_trait]
+pub trait TaskRunner: Clone + Send + Sync + 'static {
+    async fn load_tasks(&self) -> anyhow::Result<Vec<Task>>;
+    /// The timezone that the schedule of a task follows.
+    async fn timezone(&self, t: &Task) -> Tz;
+    async fn set_last_run(&self, t: &Task, last_run: DateTime<Utc>);
+    async fn run(&self, t: &Task) -> anyhow::Result<()>;
+    /// Called when the schedule of a task expires, or with the error of a failed run.
+    async fn finished(&self, t: &Task, res: anyhow::Result<()>);
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+struct Running {
+    task: Task,
+    tz: Tz,
+    handle: JoinHandle<()>,
+}
+
+impl Running {
+    /// If the running task would behave differently from `t` in timezone `tz`.
+    fn differs(&self, t: &Task, tz: Tz) -> bool {
+        self.tz != tz
+            || self.task.cron != t.cron
+            || self.task.cmd != t.cmd
+            || self.task.arg != t.arg
+            || self.task.channel_id != t.channel_id
+            || self.task.creator_id != t.creator_id
+            || self.task.enabled != t.enabled
+    }
+}
+
+pub struct Scheduler<R: TaskRunner> {
+    runner: R,
+    running: HashMap<i32, Running>,
+}
+
+impl<R: TaskRunner> Scheduler<R> {
+    pub fn new(runner: R) -> Self {
+        Self {
+            runner,
+            running: HashMap::new(),
+        }
+    }
+
+    /// Start all tasks, then handle messages until all handles are dropped.
+    pub async fn run(mut self, mut rx: UnboundedReceiver<SchedulerMsg>) {
+        self.handle(SchedulerMsg::ReloadAll).await;
+        while let Some(msg) = rx.recv().await {
+            self.handle(msg).await;
+        }
+        warn!("Scheduler stopped");
+    }
+
+    pub async fn handle(&mut self, msg: SchedulerMsg) {
+        // Forget tasks that expired or failed
+        self.running.retain(|_, r| !r.handle.is_finished());
+        match msg {
+            SchedulerMsg::Add(t) | SchedulerMsg::Update(t) => self.upsert(t).await,
+            SchedulerMsg::Remove(id) => self.stop(id),
+            SchedulerMsg::ReloadAll => {
+                let tasks = loop {
+                    match self.runner.load_tasks().await {
+                        Ok(tasks) => break tasks,
+                        Err(e) => {
+                            error!("Failed to get tasks: {e:?}. Retrying in 60 secs.");
+                            tokio::time::sleep(Duration::from_secs(60)).await;
+                        }
+                    }
+                };
+                let ids: Vec<i32> = self
+                    .running
+                    .keys()
+                    .filter(|id| !tasks.iter().any(|t| t.id == **id))
+                    .copied()
+                    .collect();
+                for id in ids {
+                    self.stop(id);
+                }
+                for t in tasks {
+                    self.upsert(t).await;
+                }
+            }
+        }
+    }
+
+    /// Start a task, or restart it if it has changed.
+    async fn upsert(&mut self, t: Task) {
+        if !t.enabled {
+            self.stop(t.id);
+            return;
+        }
+        let tz = self.runner.timezone(&t).await;
+        if self.running.get(&t.id).is_some_and(|r| !r.differs(&t, tz)) {
+            return;
+        }
+        self.stop(t.id);
+        let runner = self.runner.clone();
+        let task = t.clone();
+        let handle = tokio::spawn(async move {
+            let res = run_schedule(&runner, &task, tz).await;
+            runner.finished(&task, res).await;
+        });
+        self.running.insert(
+            t.id,
+            Running {
+                task: t,
+                tz,
+                handle,
+            },
+        );
+    }
+
+    fn stop(&mut self, id: i32) {
+        if let Some(r) = self.running.remove(&id) {
+            r.handle.abort();
+        }
+    }
+}
+
+/// Run a task according to its schedule. Returns when the schedule expires, or with the error of a failed run.
+async fn run_schedule<R: TaskRunner>(runner: &R, t: &Task, tz: Tz) -> anyhow::Result<()> {
+    let s = cron::Schedule::from_str(&t.cron)?;
+    // Runs that were missed while the bot was down
+    let now = runner.now();
+    let missed = s
+        .after(&t.last_run.with_timezone(&tz))
+        .take_while(|next| next.with_timezone(&Utc) <= now)
+        .take(MAX_CATCH_UP)
+        .count();
+    if missed > 0 {
+        let runs = match t.catch_up.parse() {
+            Ok(CatchUp::Skip) => 0,
+            Ok(CatchUp::All) => missed,
+            _ => 1,
+        };
+        info!("Task {} missed {missed} runs, catching up on {runs}", t.id);
+        runner.set_last_run(t, now).await;
+        for _ in 0..runs {
+            runner.run(t).await?;
+        }
+    }
+    for next in s.after(&now.with_timezone(&tz)) {
+        let next = next.with_timezone(&Utc);
+        tokio::time::sleep((next - runner.now()).to_std().unwrap_or_default()).await;
+        // Marked as run before running, so that a restart can't run it twice
+        runner.set_last_run(t, next).await;
+        runner.run(t).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use chrono::{TimeZone, Timelike};
+    use tokio::time::{sleep, Instant};
+
+    use super::*;
+
+    type Shared<T> = Arc<Mutex<Vec<T>>>;
+
+    /// Follows the paused tokio clock from a fixed point in time.
+    #[derive(Clone)]
+    struct MockRunner {
+        start: DateTime<Utc>,
+        instant: Instant,
+        tasks: Shared<Task>,
+        runs: Shared<(i32, DateTime<Utc>)>,
+        finished: Shared<(i32, bool)>,
+    }
+
+    impl MockRunner {
+        fn new() -> Self {
+            Self {
+                start: Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap(),
+                instant: Instant::now(),
+                tasks: Default::default(),
+                runs: Default::default(),
+                finished: Default::default(),
+            }
+        }
+        /// Task IDs with the seconds into the hour they ran at.
+        fn runs(&self) -> Vec<(i32, u32)> {
+            self.runs
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(id, t)| (*id, t.second() + 60 * t.minute()))
+                .collect()
+        }
+    }
+
+    #[async_trait]
+    impl TaskRunner for MockRunner {
+        async fn load_tasks(&self) -> anyhow::Result<Vec<Task>> {
+            Ok(self.tasks.lock().unwrap().clone())
+        }
+        async fn timezone(&self, t: &Task) -> Tz {
+            t.timezone
+                .as_deref()
+                .and_then(|tz| tz.parse().ok())
+                .unwrap_or(Tz::UTC)
+        }
+        async fn set_last_run(&self, _t: &Task, _last_run: DateTime<Utc>) {}
+        async fn run(&self, t: &Task) -> anyhow::Result<()> {
+            self.runs.lock().unwrap().push((t.id, self.now()));
+            match t.cmd.as_str() {
+                "fail" => Err(anyhow::anyhow!("failed")),
+                _ => Ok(()),
+            }
+        }
+        async fn finished(&self, t: &Task, res: anyhow::Result<()>) {
+            self.finished.lock().unwrap().push((t.id, res.is_ok()));
+        }
+        fn now(&self) -> DateTime<Utc> {
+            self.start + chrono::Duration::from_std(self.instant.elapsed()).unwrap()
+        }
+    }
+
+    fn task(runner: &MockRunner, id: i32, cron: &str) -> Task {
+        Task {
+            id,
+            cron: cron.to_owned(),
+            cmd: "say".to_owned(),
+            arg: Some("hi".to_owned()),
+            channel_id: 1,
+            creator_id: Some(1),
+            timezone: None,
+            catch_up: "once".to_owned(),
+            last_run: runner.now(),
+            last_result: None,
+            last_result_at: None,
+            enabled: true,
+            description: None,
+            created_at: runner.now(),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn runs_on_schedule() {
+        let runner = MockRunner::new();
+        let mut s = Scheduler::new(runner.clone());
+        s.handle(SchedulerMsg::Add(task(&runner, 1, "*/10 * * * * * *")))
+            .await;
+        sleep(Duration::from_secs(35)).await;
+        assert_eq!(runner.runs(), vec![(1, 10), (1, 20), (1, 30)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn remove() {
+        let runner = MockRunner::new();
+        let mut s = Scheduler::new(runner.clone());
+        s.handle(SchedulerMsg::Add(task(&runner, 1, "*/10 * * * * * *")))
+            .await;
+        sleep(Duration::from_secs(15)).await;
+        s.handle(SchedulerMsg::Remove(1)).await;
+        sleep(Duration::from_secs(30)).await;
+        assert_eq!(runner.runs(), vec![(1, 10)]);
+        assert!(s.running.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn update() {
+        let runner = MockRunner::new();
+        let mut s = Scheduler::new(runner.clone());
+        s.handle(SchedulerMsg::Add(task(&runner, 1, "*/10 * * * * * *")))
+            .await;
+        sleep(Duration::from_secs(15)).await;
+        // An unchanged task keeps running
+        s.handle(SchedulerMsg::Update(task(&runner, 1, "*/10 * * * * * *")))
+            .await;
+        sleep(Duration::from_secs(10)).await;
+        s.handle(SchedulerMsg::Update(task(&runner, 1, "*/3 * * * * * *")))
+            .await;
+        sleep(Duration::from_secs(4)).await;
+        let mut paused = task(&runner, 1, "*/3 * * * * * *");
+        paused.enabled = false;
+        s.handle(SchedulerMsg::Update(paused)).await;
+        sleep(Duration::from_secs(30)).await;
+        assert_eq!(runner.runs(), vec![(1, 10), (1, 20), (1, 27)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reload_all() {
+        let runner = MockRunner::new();
+        let mut s = Scheduler::new(runner.clone());
+        s.handle(SchedulerMsg::Add(task(&runner, 1, "*/10 * * * * * *")))
+            .await;
+        s.handle(SchedulerMsg::Add(task(&runner, 2, "*/10 * * * * * *")))
+            .await;
+        // Task 2 was removed from the database
+        *runner.tasks.lock().unwrap() = vec![task(&runner, 1, "*/10 * * * * * *")];
+        s.handle(SchedulerMsg::ReloadAll).await;
+        sleep(Duration::from_secs(15)).await;
+        assert_eq!(runner.runs(), vec![(1, 10)]);
+        assert_eq!(s.running.len(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn catch_up() {
+        // Missed runs at 23:59:40, 23:59:50 and 00:00:00
+        for (catch_up, runs) in [("skip", 0), ("once", 1), ("all", 3)] {
+            let runner = MockRunner::new();
+            let mut s = Scheduler::new(runner.clone());
+            let mut t = task(&runner, 1, "*/10 * * * * * *");
+            t.catch_up = catch_up.to_owned();
+            t.last_run = runner.now() - chrono::Duration::try_seconds(25).unwrap();
+            s.handle(SchedulerMsg::Add(t)).await;
+            sleep(Duration::from_secs(5)).await;
+            assert_eq!(runner.runs(), vec![(1, 0); runs], "{catch_up}");
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn finished() {
+        let runner = MockRunner::new();
+        let mut s = Scheduler::new(runner.clone());
+        s.handle(SchedulerMsg::Add(task(&runner, 1, "5 0 0 1 1 * 2026")))
+            .await;
+        let mut failing = task(&runner, 2, "*/10 * * * * * *");
+        failing.cmd = "fail".to_owned();
+        s.handle(SchedulerMsg::Add(failing)).await;
+        sleep(Duration::from_secs(25)).await;
+        assert_eq!(runner.runs(), vec![(1, 5), (2, 10)]);
+        assert_eq!(
+            *runner.finished.lock().unwrap(),
+            vec![(1, true), (2, false)]
+        );
+        // Finished tasks are forgotten on the next message
+        s.handle(SchedulerMsg::Remove(3)).await;
+        assert!(s.running.is_empty());
+    }
+}
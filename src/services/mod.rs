@@ -6,15 +6,23 @@ use anyhow::anyhow;
 use chrono::Duration;
 use rand::{rngs::StdRng, Rng, SeedableRng};
 use serenity::{
-    builder::{CreateEmbed, CreateEmbedAuthor, CreateMessage, EditGuild, EditMember},
+    builder::{
+        CreateAllowedMentions, CreateEmbed, CreateEmbedAuthor, CreateMessage, EditGuild, EditMember,
+    },
     client::Context,
     framework::standard::CommandResult,
-    model::prelude::{Guild, Message, Timestamp, UserId},
+    model::prelude::{ChannelId, Guild, GuildId, Message, Timestamp, UserId},
 };
+use tracing::warn;
 
 use cool_text::{to_cool_text, Font};
 
-use crate::{database::WallaceDBClient, discord::get_db_handler};
+use crate::{
+    commands::items::{BONK_SHIELD, COUNTER_SPELL},
+    database::WallaceDBClient,
+    discord::{get_db_handler, get_guild_settings},
+    model::{BonkEvent, BonkOutcome, TransactionReason},
+};
 
 pub async fn set_server_name(
     ctx: &Context,
@@ -23,6 +31,7 @@ pub async fn set_server_name(
     name: &str,
 ) -> CommandResult {
     guild.edit(ctx, EditGuild::new().name(name)).await?;
+    log_to_guild(ctx, guild.id.get(), format!("Server name set to '{name}'")).await;
     if let Some(msg) = reply_to {
         msg.channel_id
             .say(ctx, format!("Set server name to '{}'", name))
@@ -31,6 +40,24 @@ pub async fn set_server_name(
     Ok(())
 }
 
+/// Post in the log channel of the guild, if it has one. Nobody is pinged by the log.
+pub async fn log_to_guild(ctx: &Context, guild_id: u64, text: impl Into<String>) {
+    let Some(cid) = get_guild_settings(ctx, guild_id).await.log_channel_id else {
+        return;
+    };
+    if let Err(e) = ChannelId::new(cid as u64)
+        .send_message(
+            ctx,
+            CreateMessage::new()
+                .content(text)
+                .allowed_mentions(CreateAllowedMentions::new()),
+        )
+        .await
+    {
+        warn!("Failed to log to guild {guild_id}: {e}");
+    }
+}
+
 const BONK_EMOTES: &[&str] = &[
     "https://cdn.7tv.app/emote/631b61a98cf0978e2955b04f/2x.gif",
     "https://cdn.7tv.app/emote/60d174a626215e098873e43e/2x.gif",
@@ -50,57 +77,169 @@ const BONK_EMOTES: &[&str] = &[
     "https://cdn.7tv.app/emote/61bcc6e25804e220aa6adc77/2x.gif",
     "https://cdn.7tv.app/emote/62734c1ade98b688d09661d6/2x.gif",
 ];
-pub async fn bonk_user(ctx: &Context, msg: &Message, uid: u64, duration: u32) -> CommandResult {
-    let gid = msg.guild_id.ok_or("Failed to get guild")?;
-    if let Err(e) = gid
-        .edit_member(
-            ctx,
-            UserId::new(uid),
-            EditMember::new().disable_communication_until(
-                Timestamp::now()
-                    .checked_add_signed(Duration::try_seconds(duration as i64).unwrap())
-                    .expect("Failed to add date")
-                    .to_rfc3339(),
-            ),
-        )
+/// How the target of a bonk defended themselves.
+#[derive(Clone, Copy, PartialEq)]
+pub enum BonkDefense {
+    Blocked,
+    Reflected,
+}
+
+/// Check the buffs of the target of a bonk cast by someone else.
+pub async fn bonk_defense(ctx: &Context, gid: u64, uid: u64) -> Option<BonkDefense> {
+    let db = get_db_handler(ctx).await;
+    if db
+        .has_active_buff(gid, uid, COUNTER_SPELL.id)
         .await
+        .unwrap_or(false)
     {
-        let s = e.to_string();
-        let _ = msg
-            .channel_id
-            .say(
+        Some(BonkDefense::Reflected)
+    } else if db
+        .has_active_buff(gid, uid, BONK_SHIELD.id)
+        .await
+        .unwrap_or(false)
+    {
+        Some(BonkDefense::Blocked)
+    } else {
+        None
+    }
+}
+
+async fn member_name(ctx: &Context, gid: GuildId, uid: u64) -> String {
+    gid.member(ctx, uid)
+        .await
+        .map(|m| m.nick.unwrap_or(m.user.name))
+        .unwrap_or_else(|_| "?".into())
+}
+
+/// The details of a bonk that are kept in the bonk stats.
+pub struct Bonk {
+    /// Who the caster wanted to bonk.
+    pub target: u64,
+    pub size: Option<&'static str>,
+    pub chance: Option<u32>,
+    pub cost: i64,
+}
+
+/// Bonk `uid`, which is either the target of the bonk or the caster if it backfired.
+/// Returns false if the bonk could not be done, which the author has been told about.
+pub async fn bonk_user(
+    ctx: &Context,
+    msg: &Message,
+    uid: u64,
+    duration: u32,
+    bonk: Bonk,
+) -> CommandResult<bool> {
+    let gid = msg.guild_id.ok_or("Failed to get guild")?;
+    let caster = msg.author.id.get();
+    // You can't defend against your own bonks
+    let defense = if uid == caster {
+        None
+    } else {
+        bonk_defense(ctx, gid.get(), uid).await
+    };
+    let victim = match defense {
+        Some(BonkDefense::Reflected) => caster,
+        _ => uid,
+    };
+    if defense != Some(BonkDefense::Blocked) {
+        if let Err(e) = gid
+            .edit_member(
                 ctx,
-                if s == "Missing Permissions" {
-                    "That guy is too powerful... I can't do it... 😔".into()
-                } else {
-                    s
-                },
+                UserId::new(victim),
+                EditMember::new().disable_communication_until(
+                    Timestamp::now()
+                        .checked_add_signed(Duration::try_seconds(duration as i64).unwrap())
+                        .expect("Failed to add date")
+                        .to_rfc3339(),
+                ),
             )
-            .await;
-        return Ok(());
+            .await
+        {
+            let s = e.to_string();
+            let _ = msg
+                .channel_id
+                .say(
+                    ctx,
+                    if s == "Missing Permissions" {
+                        "That guy is too powerful... I can't do it... 😔".into()
+                    } else {
+                        s
+                    },
+                )
+                .await;
+            return Ok(false);
+        };
+    }
+    let outcome = match defense {
+        Some(BonkDefense::Blocked) => BonkOutcome::Blocked,
+        Some(BonkDefense::Reflected) => BonkOutcome::Reflected,
+        None if uid != bonk.target => BonkOutcome::Backfire,
+        None => BonkOutcome::Hit,
     };
+    let db = get_db_handler(ctx).await;
+    let _ = db
+        .create_bonk_event(BonkEvent {
+            guild_id: gid.get(),
+            caster_id: caster,
+            target_id: bonk.target,
+            victim_id: (defense != Some(BonkDefense::Blocked)).then_some(victim),
+            size: bonk.size,
+            chance: bonk.chance,
+            outcome,
+            duration,
+            cost: bonk.cost,
+        })
+        .await;
     let mut rng: StdRng = SeedableRng::from_entropy();
-    let tn = gid
-        .member(ctx, uid)
-        .await
-        .map(|m| m.nick.unwrap_or(m.user.name))
-        .unwrap_or_else(|_| "?".into());
-    let _ = msg
-        .channel_id
-        .send_message(
+    let tn = member_name(ctx, gid, uid).await;
+    let cn = msg
+        .member
+        .as_ref()
+        .and_then(|m| m.nick.clone())
+        .unwrap_or_else(|| msg.author.name.clone());
+    if defense != Some(BonkDefense::Blocked) {
+        let vn = if victim == uid { &tn } else { &cn };
+        log_to_guild(
             ctx,
-            CreateMessage::new().add_embed(
-                CreateEmbed::new()
-                    .author(CreateEmbedAuthor::new(format!(
-                        "{}🔨🙂",
-                        to_cool_text("BONK!", Font::BoldScript)
-                    )))
-                    .title(format!("Timed out {tn} for {duration} seconds."))
-                    .thumbnail(BONK_EMOTES[rng.gen_range(0..BONK_EMOTES.len())]),
-            ),
+            gid.get(),
+            format!("{cn} bonked {vn} for {duration} seconds"),
         )
         .await;
-    Ok(())
+    }
+    let embed = match defense {
+        None => CreateEmbed::new()
+            .author(CreateEmbedAuthor::new(format!(
+                "{}🔨🙂",
+                to_cool_text("BONK!", Font::BoldScript)
+            )))
+            .title(format!("Timed out {tn} for {duration} seconds."))
+            .thumbnail(BONK_EMOTES[rng.gen_range(0..BONK_EMOTES.len())]),
+        Some(BonkDefense::Blocked) => CreateEmbed::new()
+            .author(CreateEmbedAuthor::new(format!(
+                "{}🛡️😎",
+                to_cool_text("BLOCKED!", Font::BoldScript)
+            )))
+            .title(format!(
+                "{tn} blocked the bonk with a {}.",
+                BONK_SHIELD.name
+            )),
+        Some(BonkDefense::Reflected) => CreateEmbed::new()
+            .author(CreateEmbedAuthor::new(format!(
+                "{}🪞😈",
+                to_cool_text("REFLECTED!", Font::BoldScript)
+            )))
+            .title(format!("Timed out {cn} for {duration} seconds."))
+            .description(format!(
+                "{tn} reflected the bonk with a {}!",
+                COUNTER_SPELL.name
+            ))
+            .thumbnail(BONK_EMOTES[rng.gen_range(0..BONK_EMOTES.len())]),
+    };
+    let _ = msg
+        .channel_id
+        .send_message(ctx, CreateMessage::new().add_embed(embed))
+        .await;
+    Ok(true)
 }
 
 pub async fn unbonk_user(ctx: &Context, msg: &Message, uid: u64) -> CommandResult {
@@ -174,10 +313,22 @@ pub async fn nickname_user(ctx: &Context, msg: &Message, uid: u64, nick: String)
     Ok(())
 }
 
-pub async fn do_payment(ctx: &Context, msg: &Message, amount: i64) -> CommandResult {
+pub async fn do_payment(
+    ctx: &Context,
+    msg: &Message,
+    amount: i64,
+    reason: TransactionReason,
+) -> CommandResult {
+    let Some(gid) = msg.guild_id else {
+        let _ = msg
+            .channel_id
+            .say(ctx, "Bank accounts only exist in servers 😋")
+            .await;
+        return Err(anyhow!("").into());
+    };
     let db = get_db_handler(ctx).await;
     if let Err(e) = db
-        .subtract_bank_account_balance(msg.author.id.get(), amount)
+        .subtract_bank_account_balance(gid.get(), msg.author.id.get(), amount, reason)
         .await
     {
         let _ = msg.channel_id.say(ctx, e.to_string()).await;
@@ -185,16 +336,43 @@ pub async fn do_payment(ctx: &Context, msg: &Message, amount: i64) -> CommandRes
     } else {
         let _ = msg
             .channel_id
-            .send_message(
-                ctx,
-                CreateMessage::new().add_embed(
-                    CreateEmbed::new().author(
-                        CreateEmbedAuthor::new(format!("-{amount} 𝓚"))
-                            .icon_url("https://cdn.7tv.app/emote/60edf43ba60faa2a91cfb082/1x.gif"),
-                    ),
-                ),
-            )
+            .send_message(ctx, payment_message(amount))
             .await;
         Ok(())
     }
 }
+
+/// Show that `amount` was paid.
+pub fn payment_message(amount: i64) -> CreateMessage {
+    CreateMessage::new().add_embed(
+        CreateEmbed::new().author(
+            CreateEmbedAuthor::new(format!("-{amount} 𝓚"))
+                .icon_url("https://cdn.7tv.app/emote/60edf43ba60faa2a91cfb082/1x.gif"),
+        ),
+    )
+}
+
+/// Check that the author may use the casino: they must be marked mature,
+/// and can't have defaulted on a loan in the server. Tells them why if they can't.
+pub async fn check_can_gamble(ctx: &Context, msg: &Message) -> bool {
+    let refusal = match gamble_refusal(ctx, msg).await {
+        Ok(None) => return true,
+        Ok(Some(r)) => r.to_owned(),
+        Err(e) => e.to_string(),
+    };
+    let _ = msg.channel_id.say(ctx, refusal).await;
+    false
+}
+async fn gamble_refusal(ctx: &Context, msg: &Message) -> anyhow::Result<Option<&'static str>> {
+    let db = get_db_handler(ctx).await;
+    let uid = msg.author.id.get();
+    if !db.get_user_mature(uid).await? {
+        return Ok(Some("User must be marked as mature ☝🤓"));
+    }
+    if let Some(gid) = msg.guild_id {
+        if db.get_loan(gid.get(), uid).await?.defaulted {
+            return Ok(Some("No gambling until your loan is repaid 🧐"));
+        }
+    }
+    Ok(None)
+}